@@ -2,6 +2,8 @@ use anyhow::{Context, Result};
 use log::{debug, info};
 use std::path::Path;
 use tokio::process::Command;
+use crate::encoding::AudioEncoding;
+use crate::envelope::{self, Duck, FadeCurve};
 use crate::resources::TempFile;
 
 /// Audio format configuration for whisper processing
@@ -83,6 +85,40 @@ pub async fn extract_audio(video_path: &Path) -> Result<TempFile> {
     Ok(temp_file)
 }
 
+/// Extract the audio track at its original sample rate and channel layout,
+/// for censoring strategies that need the full-quality programme
+pub async fn extract_programme_audio(video_path: &Path) -> Result<TempFile> {
+    let audio_filename = format!("babymode_programme_{}.wav", std::process::id());
+    let audio_path = std::env::temp_dir().join(audio_filename);
+
+    info!("Extracting programme audio from {:?} to {:?}", video_path, audio_path);
+
+    let output = Command::new("ffmpeg")
+        .args([
+            "-i", video_path.to_str().context("Invalid video path")?,
+            "-vn",
+            "-map", "0:a:0",
+            "-acodec", "pcm_s16le",
+            "-y",
+            audio_path.to_str().context("Invalid audio path")?,
+        ])
+        .output()
+        .await
+        .context("Failed to execute ffmpeg")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg failed to extract programme audio: {}", error);
+    }
+
+    let temp_file = TempFile::new(audio_path);
+    if !temp_file.exists() {
+        anyhow::bail!("Programme audio extraction failed - output file not created");
+    }
+
+    Ok(temp_file)
+}
+
 /// Get audio duration using ffprobe
 pub async fn get_audio_duration(audio_path: &Path) -> Result<f64> {
     let output = Command::new("ffprobe")
@@ -118,6 +154,155 @@ pub async fn get_audio_duration(audio_path: &Path) -> Result<f64> {
     Ok(duration)
 }
 
+/// Format of the first audio stream in a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioStreamInfo {
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub channel_layout: Option<String>,
+    pub codec: String,
+    pub bit_rate: Option<u64>,
+}
+
+impl AudioStreamInfo {
+    /// Channel layout usable in filter options, e.g. `stereo` or `6c`
+    pub fn layout(&self) -> String {
+        self.channel_layout.clone()
+            .unwrap_or_else(|| format!("{}c", self.channels))
+    }
+}
+
+/// Probe sample rate, channel layout and codec of the first audio stream
+pub async fn probe_audio_stream(path: &Path) -> Result<AudioStreamInfo> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v", "quiet",
+            "-print_format", "json",
+            "-show_streams",
+            "-select_streams", "a:0",
+            path.to_str().context("Invalid audio path")?,
+        ])
+        .output()
+        .await
+        .context("Failed to execute ffprobe")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffprobe failed: {}", error);
+    }
+
+    let probe_data: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse ffprobe JSON output")?;
+
+    let stream = probe_data.get("streams")
+        .and_then(|s| s.as_array())
+        .and_then(|s| s.first())
+        .context("No audio stream found")?;
+
+    let info = AudioStreamInfo {
+        sample_rate: stream.get("sample_rate")
+            .and_then(|r| r.as_str())
+            .and_then(|r| r.parse().ok())
+            .context("Could not parse audio sample rate")?,
+        channels: stream.get("channels")
+            .and_then(|c| c.as_u64())
+            .context("Could not parse audio channel count")? as u32,
+        channel_layout: stream.get("channel_layout")
+            .and_then(|l| l.as_str())
+            .map(|l| l.to_string()),
+        codec: stream.get("codec_name")
+            .and_then(|c| c.as_str())
+            .unwrap_or("unknown")
+            .to_string(),
+        bit_rate: stream.get("bit_rate")
+            .and_then(|b| b.as_str())
+            .and_then(|b| b.parse().ok()),
+    };
+
+    debug!("Audio stream info: {:?}", info);
+    Ok(info)
+}
+
+/// Decode a time range of the audio as mono f32 samples at `sample_rate`
+pub async fn read_pcm_samples(
+    path: &Path,
+    start_time: f64,
+    duration: f64,
+    sample_rate: u32,
+) -> Result<Vec<f32>> {
+    let output = Command::new("ffmpeg")
+        .args([
+            "-v", "error",
+            "-ss", &format!("{:.3}", start_time.max(0.0)),
+            "-t", &format!("{:.3}", duration.max(0.0)),
+            "-i", path.to_str().context("Invalid audio path")?,
+            "-ac", "1",
+            "-ar", &sample_rate.to_string(),
+            "-f", "f32le",
+            "-",
+        ])
+        .output()
+        .await
+        .context("Failed to execute ffmpeg for PCM decoding")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg failed to decode PCM: {}", error);
+    }
+
+    Ok(output.stdout
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+/// RMS level of the active (non-silent) parts of `samples`, or None if
+/// everything is below the -50 dBFS gate
+pub fn active_rms(samples: &[f32], sample_rate: u32) -> Option<f32> {
+    const GATE: f32 = 0.003_162; // -50 dBFS
+    let frame_len = (sample_rate as usize / 50).max(1); // 20ms frames
+
+    let mut total_power = 0.0f64;
+    let mut active_frames = 0usize;
+    for frame in samples.chunks(frame_len) {
+        let power = frame.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / frame.len() as f64;
+        if power.sqrt() as f32 >= GATE {
+            total_power += power;
+            active_frames += 1;
+        }
+    }
+
+    if active_frames == 0 {
+        None
+    } else {
+        Some((total_power / active_frames as f64).sqrt() as f32)
+    }
+}
+
+/// Measure the dialogue level around a segment, excluding the segment itself
+pub async fn measure_surrounding_level(
+    path: &Path,
+    segment: &AudioSegment,
+    window: f64,
+) -> Result<Option<f32>> {
+    const ANALYSIS_RATE: u32 = 16000;
+
+    let window_start = (segment.start_time - window).max(0.0);
+    let samples = read_pcm_samples(
+        path,
+        window_start,
+        segment.end_time + window - window_start,
+        ANALYSIS_RATE,
+    ).await?;
+
+    let to_index = |t: f64| (((t - window_start) * ANALYSIS_RATE as f64) as usize).min(samples.len());
+    let (before, rest) = samples.split_at(to_index(segment.start_time));
+    let after = &rest[to_index(segment.end_time) - before.len()..];
+
+    let surrounding: Vec<f32> = before.iter().chain(after.iter()).copied().collect();
+    Ok(active_rms(&surrounding, ANALYSIS_RATE))
+}
+
 /// Apply volume changes to audio segments
 pub async fn apply_volume_changes(
     input_path: &Path,
@@ -176,66 +361,74 @@ pub async fn apply_volume_changes(
     Ok(())
 }
 
-/// Apply isolation and inversion censoring to completely remove profanity
-pub async fn apply_isolation_censoring(
+/// Apply a gain envelope to the audio, attenuating each duck window
+pub async fn apply_ducking(
     input_path: &Path,
     output_path: &Path,
-    censor_segments: &[AudioSegment],
-    _fade_duration: f32,
+    ducks: &[Duck],
 ) -> Result<()> {
-    info!("Applying isolation censoring to {} segments", censor_segments.len());
-
-    if censor_segments.is_empty() {
+    if ducks.is_empty() {
         tokio::fs::copy(input_path, output_path).await
             .context("Failed to copy audio file")?;
         return Ok(());
     }
 
-    // Build volume filter that sets volume to 0 for each segment
-    let mut volume_conditions = Vec::new();
-    
-    for segment in censor_segments.iter() {
-        // Apply complete silence (volume=0) for this segment
-        volume_conditions.push(format!(
-            "volume=enable='between(t,{:.3},{:.3})':volume=0",
-            segment.start_time, segment.end_time
-        ));
-    }
-    
-    let filter_complex = volume_conditions.join(",");
-    debug!("Silence filter: {}", filter_complex);
+    let filter = envelope::volume_filter(ducks);
+    debug!("Envelope filter: {}", filter);
 
     let output = Command::new("ffmpeg")
         .args([
             "-i", input_path.to_str().context("Invalid input path")?,
-            "-af", &filter_complex,
+            "-af", &filter,
             "-c:a", "pcm_s16le",
             "-y",
             output_path.to_str().context("Invalid output path")?,
         ])
         .output()
         .await
-        .context("Failed to execute ffmpeg for isolation censoring")?;
+        .context("Failed to execute ffmpeg for envelope")?;
 
     if !output.status.success() {
         let error = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("ffmpeg failed to apply isolation censoring: {}", error);
+        anyhow::bail!("ffmpeg failed to apply envelope: {}", error);
     }
 
+    Ok(())
+}
+
+/// Apply isolation censoring to completely remove profanity
+pub async fn apply_isolation_censoring(
+    input_path: &Path,
+    output_path: &Path,
+    censor_segments: &[AudioSegment],
+    fade_duration: f32,
+    fade_curve: FadeCurve,
+) -> Result<()> {
+    info!("Applying isolation censoring to {} segments", censor_segments.len());
+
+    let ducks = envelope::ducks_for_segments(censor_segments, 0.0, fade_duration, fade_curve);
+    apply_ducking(input_path, output_path, &ducks).await?;
+
     info!("Successfully applied isolation censoring to: {:?}", output_path);
     Ok(())
 }
 
-/// Apply smooth fade in/out to audio segments for natural censoring (legacy)
+/// Apply smooth fade in/out to audio segments for natural censoring
 pub async fn apply_smooth_censoring(
     input_path: &Path,
     output_path: &Path,
     censor_segments: &[AudioSegment],
-    _target_volume: f32,
+    target_volume: f32,
     fade_duration: f32,
+    fade_curve: FadeCurve,
 ) -> Result<()> {
-    // Use isolation censoring for more effective results
-    apply_isolation_censoring(input_path, output_path, censor_segments, fade_duration).await
+    info!("Applying smooth censoring to {} segments", censor_segments.len());
+
+    let ducks = envelope::ducks_for_segments(censor_segments, target_volume, fade_duration, fade_curve);
+    apply_ducking(input_path, output_path, &ducks).await?;
+
+    info!("Successfully applied smooth censoring to: {:?}", output_path);
+    Ok(())
 }
 
 /// Convert audio to format suitable for Whisper
@@ -294,6 +487,119 @@ pub async fn extract_audio_segment(
     Ok(())
 }
 
+/// Join the `(start, end)` windows of `input_path` back to back into one
+/// PCM file, so they can be analysed in a single pass
+pub async fn extract_windows(input_path: &Path, output_path: &Path, windows: &[(f64, f64)]) -> Result<()> {
+    let select = windows.iter()
+        .map(|(start, end)| format!("between(t,{:.3},{:.3})", start, end))
+        .collect::<Vec<_>>()
+        .join("+");
+    let output = Command::new("ffmpeg")
+        .args([
+            "-i", input_path.to_str().context("Invalid input path")?,
+            "-af", &format!("aselect='{}',asetpts=N/SR/TB", select),
+            "-c:a", "pcm_s16le",
+            "-y",
+            output_path.to_str().context("Invalid output path")?,
+        ])
+        .output()
+        .await
+        .context("Failed to execute ffmpeg for window extraction")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg failed to extract audio windows: {}", error);
+    }
+
+    debug!("Extracted {} audio windows to {:?}", windows.len(), output_path);
+    Ok(())
+}
+
+/// Encoder for an audio-only output file, picked from its extension
+pub fn audio_encoder(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "mp3" => Some("libmp3lame"),
+        "m4a" | "aac" => Some("aac"),
+        "flac" => Some("flac"),
+        "wav" => Some("pcm_s16le"),
+        "aiff" | "aif" => Some("pcm_s16be"),
+        "opus" => Some("libopus"),
+        "ogg" | "oga" => Some("libvorbis"),
+        "wma" => Some("wmav2"),
+        _ => None,
+    }
+}
+
+/// Whether the format of `path` can carry cover art
+fn supports_cover_art(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref(),
+        Some("mp3" | "m4a" | "flac")
+    )
+}
+
+/// ffmpeg arguments that encode `audio_path` to `output_path`, taking tags
+/// and, when `keep_cover_art` is set, the cover image from `source_path`
+pub fn audio_output_args(
+    audio_path: &Path,
+    source_path: &Path,
+    output_path: &Path,
+    keep_cover_art: bool,
+    encoding: &AudioEncoding,
+) -> Result<Vec<String>> {
+    let mut args: Vec<String> = vec![
+        "-i".into(), audio_path.to_str().context("Invalid audio path")?.into(),
+        "-i".into(), source_path.to_str().context("Invalid source path")?.into(),
+        "-map".into(), "0:a:0".into(),
+    ];
+    if keep_cover_art && supports_cover_art(output_path) {
+        // Optional map, so files without artwork still encode
+        args.extend([
+            "-map", "1:v?",
+            "-c:v", "copy",
+            "-disposition:v", "attached_pic",
+        ].map(String::from));
+    }
+    args.extend([
+        "-map_metadata", "1",
+        "-map_metadata:s:a:0", "1:s:a:0",
+    ].map(String::from));
+    args.extend(encoding.args());
+    if encoding.encoder == "libmp3lame" {
+        args.extend(["-id3v2_version", "3"].map(String::from));
+    }
+    args.extend(["-y".to_string(), output_path.to_str().context("Invalid output path")?.to_string()]);
+    Ok(args)
+}
+
+/// Write censored audio as an audio-only file, in the format given by the
+/// extension of `output_path`, keeping tags and optionally cover art from
+/// `source_path`
+pub async fn write_audio_file(
+    audio_path: &Path,
+    source_path: &Path,
+    output_path: &Path,
+    keep_cover_art: bool,
+    encoding: &AudioEncoding,
+) -> Result<()> {
+    info!("Writing audio {:?} to {:?}", audio_path, output_path);
+
+    let output = Command::new("ffmpeg")
+        .args(audio_output_args(audio_path, source_path, output_path, keep_cover_art, encoding)?)
+        .output()
+        .await
+        .context("Failed to execute ffmpeg")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg failed to write audio file: {}", error);
+    }
+
+    info!("Successfully wrote audio to: {:?}", output_path);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -306,6 +612,18 @@ mod tests {
         assert!((segment.duration - 4.7).abs() < 1e-10);
     }
 
+    #[test]
+    fn test_active_rms_gates_silence() {
+        let rate = 1000;
+        assert_eq!(active_rms(&vec![0.0; 1000], rate), None);
+
+        // Half a second of full-scale square wave, half a second of silence
+        let mut samples: Vec<f32> = (0..500).map(|i| if i % 2 == 0 { 0.5 } else { -0.5 }).collect();
+        samples.resize(1000, 0.0);
+        let rms = active_rms(&samples, rate).unwrap();
+        assert!((rms - 0.5).abs() < 1e-6);
+    }
+
     #[test]
     fn test_audio_config_default() {
         let config = AudioConfig::default();
@@ -313,4 +631,40 @@ mod tests {
         assert_eq!(config.channels, 1);
         assert_eq!(config.format, "wav");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_audio_output_args() {
+        assert_eq!(audio_encoder(Path::new("episode.MP3")), Some("libmp3lame"));
+        assert_eq!(audio_encoder(Path::new("book.m4a")), Some("aac"));
+        assert_eq!(audio_encoder(Path::new("clip.mp4")), None);
+
+        let mp3 = AudioEncoding {
+            encoder: "libmp3lame".to_string(),
+            bitrate: Some("128k".to_string()),
+            sample_format: None,
+            sample_rate: None,
+        };
+        let args = audio_output_args(
+            Path::new("/tmp/censored.wav"),
+            Path::new("episode.mp3"),
+            Path::new("episode_censored.mp3"),
+            true,
+            &mp3,
+        ).unwrap();
+        let joined = args.join(" ");
+        assert!(joined.contains("-map 1:v? -c:v copy"));
+        assert!(joined.contains("-map_metadata 1"));
+        assert!(joined.ends_with("-c:a libmp3lame -b:a 128k -id3v2_version 3 -y episode_censored.mp3"));
+
+        // Ogg can't carry the picture
+        let opus = AudioEncoding { encoder: "libopus".to_string(), bitrate: None, ..mp3 };
+        let args = audio_output_args(
+            Path::new("/tmp/censored.wav"),
+            Path::new("episode.mp3"),
+            Path::new("episode.opus"),
+            true,
+            &opus,
+        ).unwrap();
+        assert!(!args.contains(&"1:v?".to_string()));
+    }
+}
@@ -0,0 +1,451 @@
+use anyhow::{Context, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::error::{config_error, BabymodeError};
+use crate::resources::TempFile;
+use crate::subtitles::{self, CensoredTrack, CueChange, SubtitleFormat, SubtitleMask, SubtitleStream};
+use crate::whisper::WordDetection;
+
+/// Lines per cue
+const MAX_LINES: usize = 2;
+/// Silence that always starts a new cue, in seconds
+const PAUSE: f64 = 1.0;
+/// Shortest time a cue stays on screen when the next one leaves room
+const MIN_DURATION: f64 = 0.8;
+
+/// Sidecar formats captions can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptionFormat {
+    Srt,
+    Vtt,
+}
+
+impl CaptionFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CaptionFormat::Srt => "srt",
+            CaptionFormat::Vtt => "vtt",
+        }
+    }
+}
+
+impl std::str::FromStr for CaptionFormat {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "srt" | "subrip" => Ok(CaptionFormat::Srt),
+            "vtt" | "webvtt" => Ok(CaptionFormat::Vtt),
+            _ => Err(config_error(
+                "captions",
+                format!("Invalid caption format '{}'. Valid options: srt, vtt", s)
+            )),
+        }
+    }
+}
+
+/// How transcript words are laid out into cues
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptionOptions {
+    /// Longest line, in characters
+    pub line_length: usize,
+    /// Longest a cue stays on screen, in seconds
+    pub max_duration: f64,
+    /// Time each word as it is spoken
+    pub karaoke: bool,
+}
+
+impl Default for CaptionOptions {
+    fn default() -> Self {
+        Self {
+            line_length: 42,
+            max_duration: 6.0,
+            karaoke: false,
+        }
+    }
+}
+
+impl From<&Config> for CaptionOptions {
+    fn from(config: &Config) -> Self {
+        Self {
+            line_length: config.caption_line_length,
+            max_duration: config.caption_max_duration as f64,
+            karaoke: config.caption_karaoke,
+        }
+    }
+}
+
+/// One transcribed word of a caption
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptionWord {
+    /// The word as spoken
+    pub text: String,
+    /// The word as shown, when it had to be masked
+    pub masked: Option<String>,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl CaptionWord {
+    fn shown(&self) -> &str {
+        self.masked.as_deref().unwrap_or(&self.text)
+    }
+}
+
+/// One cue of the generated captions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Caption {
+    pub start: f64,
+    pub end: f64,
+    pub words: Vec<CaptionWord>,
+}
+
+impl Caption {
+    /// Text as shown, wrapped to `line_length`
+    pub fn lines(&self, line_length: usize) -> Vec<String> {
+        let shown: Vec<&str> = self.words.iter().map(|w| w.shown()).collect();
+        wrap(&shown, line_length)
+            .into_iter()
+            .map(|line| shown[line].join(" "))
+            .collect()
+    }
+
+    fn is_masked(&self) -> bool {
+        self.words.iter().any(|w| w.masked.is_some())
+    }
+}
+
+/// Split `words` into lines of at most `line_length` characters. A word
+/// longer than a line gets a line to itself.
+fn wrap(words: &[&str], line_length: usize) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut length = 0;
+    for (i, word) in words.iter().enumerate() {
+        let width = word.chars().count();
+        if i > start && length + 1 + width > line_length {
+            lines.push(start..i);
+            start = i;
+            length = width;
+        } else {
+            length += if i > start { 1 + width } else { width };
+        }
+    }
+    if start < words.len() {
+        lines.push(start..words.len());
+    }
+    lines
+}
+
+/// Lay the transcript out into cues, masking swear words. A new cue starts
+/// after a sentence ends, at a pause, or when the words would no longer fit
+/// in the line or time limits.
+pub fn build_captions(
+    words: &[WordDetection],
+    swear_words: &[String],
+    mask: &SubtitleMask,
+    options: &CaptionOptions,
+) -> Vec<Caption> {
+    let mut captions: Vec<Caption> = Vec::new();
+    let mut current: Vec<CaptionWord> = Vec::new();
+
+    for word in words.iter().filter(|w| !w.text.is_empty()) {
+        let word = CaptionWord {
+            masked: subtitles::mask_text(&word.text, swear_words, mask),
+            text: word.text.clone(),
+            start: word.start_time,
+            end: word.end_time,
+        };
+
+        if let (Some(first), Some(last)) = (current.first(), current.last()) {
+            let mut shown: Vec<&str> = current.iter().map(|w| w.shown()).collect();
+            shown.push(word.shown());
+            let sentence_end = last.text.ends_with(['.', '?', '!']);
+            if sentence_end
+                || word.start - last.end >= PAUSE
+                || word.end - first.start > options.max_duration
+                || wrap(&shown, options.line_length).len() > MAX_LINES
+            {
+                captions.push(caption(std::mem::take(&mut current)));
+            }
+        }
+        current.push(word);
+    }
+    if !current.is_empty() {
+        captions.push(caption(current));
+    }
+
+    // Let short cues linger, without running into the next one
+    for i in 0..captions.len() {
+        let limit = captions.get(i + 1).map(|next| next.start).unwrap_or(f64::INFINITY);
+        let caption = &mut captions[i];
+        if caption.end - caption.start < MIN_DURATION {
+            caption.end = (caption.start + MIN_DURATION).min(limit).max(caption.end);
+        }
+    }
+    captions
+}
+
+fn caption(words: Vec<CaptionWord>) -> Caption {
+    Caption {
+        start: words[0].start,
+        end: words[words.len() - 1].end,
+        words,
+    }
+}
+
+/// `HH:MM:SS,mmm`, with `.` before the milliseconds for WebVTT
+fn timestamp(seconds: f64, separator: char) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
+
+/// Lines of `caption` with word `current` wrapped in `<u>`, for karaoke
+/// SRT cues
+fn highlighted_lines(caption: &Caption, current: usize, line_length: usize) -> Vec<String> {
+    let shown: Vec<&str> = caption.words.iter().map(|w| w.shown()).collect();
+    wrap(&shown, line_length)
+        .into_iter()
+        .map(|line| line
+            .map(|i| if i == current { format!("<u>{}</u>", shown[i]) } else { shown[i].to_string() })
+            .collect::<Vec<_>>()
+            .join(" "))
+        .collect()
+}
+
+/// Lines of `caption` with a WebVTT timestamp tag before every word after
+/// the first
+fn timed_lines(caption: &Caption, line_length: usize) -> Vec<String> {
+    let shown: Vec<&str> = caption.words.iter().map(|w| w.shown()).collect();
+    wrap(&shown, line_length)
+        .into_iter()
+        .map(|line| line
+            .map(|i| match i {
+                0 => vtt_escape(shown[i]),
+                _ => format!("<{}>{}", timestamp(caption.words[i].start, '.'), vtt_escape(shown[i])),
+            })
+            .collect::<Vec<_>>()
+            .join(" "))
+        .collect()
+}
+
+/// `text` safe for WebVTT cue text, where `&` and `<` start markup and
+/// `-->` ends the cue timing
+fn vtt_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Write `captions` as a subtitle file. Karaoke SRT repeats each cue once
+/// per word with the spoken word underlined, since SRT has no inline timing.
+pub fn render(captions: &[Caption], format: CaptionFormat, options: &CaptionOptions) -> String {
+    let mut out = String::new();
+    match format {
+        CaptionFormat::Srt => {
+            let mut number = 0;
+            for caption in captions {
+                if options.karaoke {
+                    for (i, word) in caption.words.iter().enumerate() {
+                        let end = caption.words.get(i + 1).map(|next| next.start).unwrap_or(caption.end);
+                        number += 1;
+                        out.push_str(&format!(
+                            "{}\n{} --> {}\n{}\n\n",
+                            number,
+                            timestamp(word.start, ','),
+                            timestamp(end.max(word.end), ','),
+                            highlighted_lines(caption, i, options.line_length).join("\n")
+                        ));
+                    }
+                } else {
+                    number += 1;
+                    out.push_str(&format!(
+                        "{}\n{} --> {}\n{}\n\n",
+                        number,
+                        timestamp(caption.start, ','),
+                        timestamp(caption.end, ','),
+                        caption.lines(options.line_length).join("\n")
+                    ));
+                }
+            }
+        }
+        CaptionFormat::Vtt => {
+            out.push_str("WEBVTT\n\n");
+            for caption in captions {
+                let lines = if options.karaoke {
+                    timed_lines(caption, options.line_length)
+                } else {
+                    caption.lines(options.line_length).iter().map(|line| vtt_escape(line)).collect()
+                };
+                out.push_str(&format!(
+                    "{} --> {}\n{}\n\n",
+                    timestamp(caption.start, '.'),
+                    timestamp(caption.end, '.'),
+                    lines.join("\n")
+                ));
+            }
+        }
+    }
+    out
+}
+
+/// Cues that had a word masked, for the run report
+pub fn changes(captions: &[Caption]) -> Vec<CueChange> {
+    captions.iter()
+        .enumerate()
+        .filter(|(_, caption)| caption.is_masked())
+        .map(|(i, caption)| CueChange {
+            cue: i + 1,
+            start: timestamp(caption.start, ','),
+            original: caption.words.iter().map(|w| w.text.as_str()).collect::<Vec<_>>().join(" "),
+            censored: caption.words.iter().map(|w| w.shown()).collect::<Vec<_>>().join(" "),
+        })
+        .collect()
+}
+
+/// Sidecar path for `output_path`, e.g. `film.srt` next to `film.mp4`
+pub fn sidecar_path(output_path: &Path, format: CaptionFormat) -> PathBuf {
+    output_path.with_extension(format.extension())
+}
+
+/// Write a sidecar file next to `output_path` for each of `formats`
+pub async fn write_sidecars(
+    captions: &[Caption],
+    output_path: &Path,
+    formats: &[CaptionFormat],
+    options: &CaptionOptions,
+) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for &format in formats {
+        let path = sidecar_path(output_path, format);
+        tokio::fs::write(&path, render(captions, format, options)).await
+            .with_context(|| format!("Failed to write captions to {:?}", path))?;
+        info!("Wrote {} captions to {:?}", captions.len(), path);
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// `captions` as an SRT track to mux into the output as a soft subtitle
+/// stream. Karaoke timing is left out, as players show it poorly in
+/// embedded tracks.
+pub async fn mux_track(captions: &[Caption], options: &CaptionOptions) -> Result<CensoredTrack> {
+    let file = TempFile::new(std::env::temp_dir().join(format!(
+        "babymode_captions_{}.srt",
+        std::process::id()
+    )));
+    let plain = CaptionOptions { karaoke: false, ..options.clone() };
+    tokio::fs::write(file.path(), render(captions, CaptionFormat::Srt, &plain)).await
+        .context("Failed to write caption track")?;
+
+    Ok(CensoredTrack {
+        stream: SubtitleStream {
+            index: None,
+            codec: "subrip".to_string(),
+            // The transcript is always English, see `whisper::transcribe`
+            language: Some("eng".to_string()),
+            title: Some("Transcript".to_string()),
+            default: false,
+            forced: false,
+        },
+        format: SubtitleFormat::Srt,
+        file,
+        changes: changes(captions),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, start: f64, end: f64) -> WordDetection {
+        WordDetection {
+            word: text.to_lowercase(),
+            text: text.to_string(),
+            start_time: start,
+            end_time: end,
+            ..Default::default()
+        }
+    }
+
+    fn transcript() -> Vec<WordDetection> {
+        vec![
+            word("Well,", 0.0, 0.3),
+            word("that", 0.3, 0.5),
+            word("went", 0.5, 0.7),
+            word("to", 0.7, 0.8),
+            word("shit.", 0.8, 1.2),
+            word("Again?", 3.0, 3.4),
+        ]
+    }
+
+    #[test]
+    fn test_build_captions() {
+        let swear_words = vec!["shit".to_string()];
+        let captions = build_captions(&transcript(), &swear_words, &SubtitleMask::Asterisks, &CaptionOptions::default());
+        assert_eq!(captions.len(), 2);
+        assert_eq!(captions[0].lines(42), vec!["Well, that went to ****."]);
+        assert_eq!(captions[0].end, 1.2);
+        // Lingers for the minimum duration
+        assert_eq!(captions[1].end, 3.8);
+
+        let changes = changes(&captions);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].original, "Well, that went to shit.");
+
+        // Line and time limits split a cue
+        let narrow = CaptionOptions { line_length: 10, ..Default::default() };
+        let captions = build_captions(&transcript(), &swear_words, &SubtitleMask::Asterisks, &narrow);
+        assert_eq!(captions[0].lines(10), vec!["Well, that", "went to"]);
+        let short = CaptionOptions { max_duration: 0.6, ..Default::default() };
+        let captions = build_captions(&transcript(), &swear_words, &SubtitleMask::Asterisks, &short);
+        assert_eq!(captions[0].words.len(), 2);
+    }
+
+    #[test]
+    fn test_render() {
+        let swear_words = vec!["shit".to_string()];
+        let options = CaptionOptions::default();
+        let captions = build_captions(&transcript()[..5], &swear_words, &SubtitleMask::Grawlix, &options);
+
+        assert_eq!(
+            render(&captions, CaptionFormat::Srt, &options),
+            "1\n00:00:00,000 --> 00:00:01,200\nWell, that went to @#$%.\n\n"
+        );
+        assert_eq!(
+            render(&captions, CaptionFormat::Vtt, &options),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:01.200\nWell, that went to @#$%.\n\n"
+        );
+
+        let karaoke = CaptionOptions { karaoke: true, ..options };
+        let vtt = render(&captions, CaptionFormat::Vtt, &karaoke);
+        assert!(vtt.contains("Well, <00:00:00.300>that <00:00:00.500>went"));
+        let srt = render(&captions, CaptionFormat::Srt, &karaoke);
+        assert!(srt.contains("2\n00:00:00,300 --> 00:00:00,500\nWell, <u>that</u> went"));
+        assert!(srt.starts_with("1\n") && srt.contains("\n5\n"));
+
+        // Cue text can't open WebVTT markup, but the word timings still do
+        let marked = vec![word("Tom", 0.0, 0.3), word("&", 0.3, 0.5), word("<Jerry>", 0.5, 0.9)];
+        let captions = build_captions(&marked, &swear_words, &SubtitleMask::Grawlix, &options);
+        assert_eq!(
+            render(&captions, CaptionFormat::Vtt, &options),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:00.900\nTom &amp; &lt;Jerry&gt;\n\n"
+        );
+        let vtt = render(&captions, CaptionFormat::Vtt, &karaoke);
+        assert!(vtt.contains("Tom <00:00:00.300>&amp; <00:00:00.500>&lt;Jerry&gt;"));
+
+        assert_eq!(timestamp(3723.456, ','), "01:02:03,456");
+        assert_eq!("WebVTT".parse::<CaptionFormat>().unwrap(), CaptionFormat::Vtt);
+        assert!("ass".parse::<CaptionFormat>().is_err());
+    }
+}
@@ -1,10 +1,13 @@
 use anyhow::{Context, Result};
 use log::{debug, info};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 use crate::audio::{AudioSegment, apply_smooth_censoring};
+use crate::envelope::FadeCurve;
 use crate::resources::TempFile;
-use crate::whisper::{WordDetection, merge_detections};
+use crate::plugins::StrategyAssignment;
+use crate::whisper::WordDetection;
 use crate::Config;
 
 /// Censoring strategy options
@@ -31,8 +34,8 @@ impl Default for CensorStrategy {
 pub struct CensorConfig {
     pub strategy: CensorStrategy,
     pub fade_duration: f32,
-    pub merge_gap: f32, // Gap between detections to merge (in seconds)
-    pub padding: f32,   // Extra padding around detected words (in seconds)
+    pub fade_curve: FadeCurve,
+    pub segments: SegmentOptions,
 }
 
 impl From<&Config> for CensorConfig {
@@ -40,12 +43,106 @@ impl From<&Config> for CensorConfig {
         Self {
             strategy: CensorStrategy::VolumeReduction(config.censor_volume),
             fade_duration: config.fade_duration,
-            merge_gap: 0.5, // Merge detections within 0.5 seconds
-            padding: 0.1,   // 100ms padding around each word
+            fade_curve: config.fade_curve,
+            segments: SegmentOptions::from(config),
         }
     }
 }
 
+/// How detections are turned into the segments handed to a strategy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SegmentOptions {
+    pub merge_gap: f32,    // Gap between detections to merge (in seconds)
+    pub pre_padding: f32,  // Extra time before each segment (in seconds)
+    pub post_padding: f32, // Extra time after each segment (in seconds)
+}
+
+impl Default for SegmentOptions {
+    fn default() -> Self {
+        Self::from(&Config::default())
+    }
+}
+
+impl From<&Config> for SegmentOptions {
+    fn from(config: &Config) -> Self {
+        // Imported labels already mark exactly what to censor
+        let imported = config.label_file.is_some();
+        Self {
+            merge_gap: if imported { 0.0 } else { config.merge_gap },
+            pre_padding: if imported { 0.0 } else { config.pre_padding },
+            post_padding: if imported { 0.0 } else { config.post_padding },
+        }
+    }
+}
+
+/// Merge segments that are within `options.merge_gap` of each other, pad
+/// them and clamp them to the audio. Segments that overlap once padded are
+/// fused, so strategies never see overlapping or out-of-range segments.
+pub fn prepare_segments(segments: Vec<AudioSegment>, options: &SegmentOptions, duration: Option<f64>) -> Vec<AudioSegment> {
+    let merged = merge_segments(segments, options.merge_gap as f64);
+    let padded = add_padding_to_segments(merged, options.pre_padding, options.post_padding, duration);
+    merge_segments(padded, 0.0)
+}
+
+/// Run the merge and pad stage on the segments of every assignment, then
+/// clip segments that overlap another group's. Earlier rules win and the
+/// default strategy comes last, so no audio is censored twice.
+pub fn prepare_assignments(assignments: &mut [StrategyAssignment], options: &SegmentOptions, duration: Option<f64>) {
+    for assignment in assignments.iter_mut() {
+        let count = assignment.segments.len();
+        assignment.segments = prepare_segments(std::mem::take(&mut assignment.segments), options, duration);
+        debug!("Prepared {} {} detections as {} segments", count, assignment.strategy, assignment.segments.len());
+    }
+
+    let mut order: Vec<usize> = (0..assignments.len()).collect();
+    order.sort_by_key(|&i| assignments[i].rule.unwrap_or(usize::MAX));
+    let mut claimed: Vec<AudioSegment> = Vec::new();
+    for i in order {
+        let assignment = &mut assignments[i];
+        assignment.segments = subtract_segments(std::mem::take(&mut assignment.segments), &claimed);
+        claimed = merge_segments(claimed.into_iter().chain(assignment.segments.iter().cloned()).collect(), 0.0);
+    }
+}
+
+/// Parts of `segments` not covered by any of the sorted, disjoint `claimed`
+fn subtract_segments(segments: Vec<AudioSegment>, claimed: &[AudioSegment]) -> Vec<AudioSegment> {
+    let mut remaining = Vec::with_capacity(segments.len());
+    for segment in segments {
+        let mut start = segment.start_time;
+        for taken in claimed {
+            if taken.end_time <= start || taken.start_time >= segment.end_time {
+                continue;
+            }
+            if taken.start_time > start {
+                remaining.push(AudioSegment::new(start, taken.start_time));
+            }
+            start = start.max(taken.end_time);
+        }
+        if segment.end_time > start {
+            remaining.push(AudioSegment::new(start, segment.end_time));
+        }
+    }
+    remaining
+}
+
+/// Join segments that overlap or are no more than `merge_gap` apart
+fn merge_segments(mut segments: Vec<AudioSegment>, merge_gap: f64) -> Vec<AudioSegment> {
+    segments.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+
+    let mut merged: Vec<AudioSegment> = Vec::with_capacity(segments.len());
+    for segment in segments {
+        match merged.last_mut() {
+            Some(last) if segment.start_time <= last.end_time + merge_gap => {
+                if segment.end_time > last.end_time {
+                    *last = AudioSegment::new(last.start_time, segment.end_time);
+                }
+            }
+            _ => merged.push(segment),
+        }
+    }
+    merged
+}
+
 /// Apply censoring to audio based on word detections
 pub async fn apply_censoring(
     input_audio_path: &Path,
@@ -61,11 +158,9 @@ pub async fn apply_censoring(
     
     let censor_config = CensorConfig::from(config);
     
-    // Merge nearby detections to avoid choppy audio
-    let audio_segments = merge_detections(detections.to_vec(), censor_config.merge_gap as f64);
-    
-    // Add padding to segments
-    let padded_segments = add_padding_to_segments(audio_segments, censor_config.padding);
+    // Merge nearby detections to avoid choppy audio, then pad them
+    let audio_duration = crate::audio::get_audio_duration(input_audio_path).await.ok();
+    let padded_segments = prepare_segments(detection_segments(detections), &censor_config.segments, audio_duration);
     
     // Apply the censoring strategy
     match censor_config.strategy {
@@ -76,6 +171,7 @@ pub async fn apply_censoring(
                 &padded_segments,
                 volume,
                 censor_config.fade_duration,
+                censor_config.fade_curve,
             ).await?;
         }
         CensorStrategy::Silence => {
@@ -84,6 +180,7 @@ pub async fn apply_censoring(
                 &output_path,
                 &padded_segments,
                 censor_config.fade_duration,
+                censor_config.fade_curve,
             ).await?;
         }
         CensorStrategy::Beep(frequency) => {
@@ -111,13 +208,26 @@ pub async fn apply_censoring(
     Ok(temp_file)
 }
 
-/// Add padding around segments to ensure smooth transitions
-fn add_padding_to_segments(segments: Vec<AudioSegment>, padding: f32) -> Vec<AudioSegment> {
+fn detection_segments(detections: &[WordDetection]) -> Vec<AudioSegment> {
+    detections.iter().map(WordDetection::to_audio_segment).collect()
+}
+
+/// Add padding around segments to ensure smooth transitions, keeping them
+/// within `0..duration`. Segments left empty by the clamp are dropped.
+fn add_padding_to_segments(
+    segments: Vec<AudioSegment>,
+    pre_padding: f32,
+    post_padding: f32,
+    duration: Option<f64>,
+) -> Vec<AudioSegment> {
     segments.into_iter()
-        .map(|segment| {
-            let new_start = (segment.start_time - padding as f64).max(0.0);
-            let new_end = segment.end_time + padding as f64;
-            AudioSegment::new(new_start, new_end)
+        .filter_map(|segment| {
+            let new_start = (segment.start_time - pre_padding as f64).max(0.0);
+            let mut new_end = segment.end_time + post_padding as f64;
+            if let Some(duration) = duration {
+                new_end = new_end.min(duration);
+            }
+            (new_end > new_start).then(|| AudioSegment::new(new_start, new_end))
         })
         .collect()
 }
@@ -129,6 +239,7 @@ async fn apply_volume_censoring(
     segments: &[AudioSegment],
     target_volume: f32,
     fade_duration: f32,
+    fade_curve: FadeCurve,
 ) -> Result<()> {
     debug!("Applying volume reduction censoring (volume: {:.2}, fade: {:.2}s)", 
            target_volume, fade_duration);
@@ -139,6 +250,7 @@ async fn apply_volume_censoring(
         segments,
         target_volume,
         fade_duration,
+        fade_curve,
     ).await
 }
 
@@ -148,6 +260,7 @@ async fn apply_silence_censoring(
     output_path: &Path,
     segments: &[AudioSegment],
     fade_duration: f32,
+    fade_curve: FadeCurve,
 ) -> Result<()> {
     debug!("Applying silence censoring (fade: {:.2}s)", fade_duration);
     
@@ -158,6 +271,7 @@ async fn apply_silence_censoring(
         segments,
         0.0,
         fade_duration,
+        fade_curve,
     ).await
 }
 
@@ -169,60 +283,18 @@ async fn apply_beep_censoring(
     frequency: f32,
     fade_duration: f32,
 ) -> Result<()> {
-    use tokio::process::Command;
-    
+    use crate::plugins::{BeepStrategy, CensoringConfig, CensoringStrategy};
+
     debug!("Applying beep censoring (freq: {:.0}Hz, fade: {:.2}s)", frequency, fade_duration);
-    
-    if segments.is_empty() {
-        tokio::fs::copy(input_path, output_path).await
-            .context("Failed to copy audio file")?;
-        return Ok(());
-    }
-    
-    // Build complex filter for beep replacement
-    let mut filters = Vec::new();
-    
-    // Start with the original audio
-    filters.push("[0:a]".to_string());
-    
-    for (i, segment) in segments.iter().enumerate() {
-        let beep_duration = segment.duration;
-        
-        // Generate a sine wave beep for this segment
-        let beep_filter = format!(
-            "sine=frequency={}:duration={}:sample_rate=16000[beep{}]",
-            frequency, beep_duration, i
-        );
-        filters.push(beep_filter);
-        
-        // Replace the audio segment with the beep
-        let replace_filter = format!(
-            "[0:a][beep{}]amix=inputs=2:duration=first:dropout_transition={}[mixed{}]",
-            i, fade_duration, i
-        );
-        filters.push(replace_filter);
-    }
-    
-    let filter_complex = filters.join(";");
-    
-    let output = Command::new("ffmpeg")
-        .args([
-            "-i", input_path.to_str().context("Invalid input path")?,
-            "-filter_complex", &filter_complex,
-            "-c:a", "pcm_s16le",
-            "-y",
-            output_path.to_str().context("Invalid output path")?,
-        ])
-        .output()
-        .await
-        .context("Failed to execute ffmpeg for beep censoring")?;
-    
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("ffmpeg failed to apply beep censoring: {}", error);
-    }
-    
-    Ok(())
+
+    let config = CensoringConfig {
+        fade_duration,
+        beep_frequency: Some(frequency),
+        ..Default::default()
+    };
+    BeepStrategy.validate_config(&config)?;
+    BeepStrategy.apply_censoring(input_path, output_path, segments, &config).await
+        .context("Failed to apply beep censoring")
 }
 
 /// Apply white noise censoring - replace swear words with white noise
@@ -233,58 +305,25 @@ async fn apply_noise_censoring(
     noise_volume: f32,
     fade_duration: f32,
 ) -> Result<()> {
-    use tokio::process::Command;
-    
+    use crate::plugins::{CensoringConfig, CensoringStrategy, NoiseStrategy};
+
     debug!("Applying white noise censoring (volume: {:.2}, fade: {:.2}s)", 
            noise_volume, fade_duration);
-    
-    if segments.is_empty() {
-        tokio::fs::copy(input_path, output_path).await
-            .context("Failed to copy audio file")?;
-        return Ok(());
-    }
-    
-    // Build filter to replace segments with white noise
-    let mut filters = Vec::new();
-    
-    for (i, segment) in segments.iter().enumerate() {
-        // Create white noise for the duration of this segment
-        let noise_duration = segment.duration;
-        let noise_filter = format!(
-            "anoisesrc=duration={}:sample_rate=16000:amplitude={}[noise{}]",
-            noise_duration, noise_volume, i
-        );
-        filters.push(noise_filter);
-        
-        // Apply the noise with smooth transitions
-        let enable_condition = format!("between(t,{},{})", segment.start_time, segment.end_time);
-        let mix_filter = format!(
-            "[0:a][noise{}]amix=inputs=2:duration=first:dropout_transition={}:enable='{}'[mixed{}]",
-            i, fade_duration, enable_condition, i
-        );
-        filters.push(mix_filter);
-    }
-    
-    let filter_complex = filters.join(";");
-    
-    let output = Command::new("ffmpeg")
-        .args([
-            "-i", input_path.to_str().context("Invalid input path")?,
-            "-filter_complex", &filter_complex,
-            "-c:a", "pcm_s16le",
-            "-y",
-            output_path.to_str().context("Invalid output path")?,
-        ])
-        .output()
-        .await
-        .context("Failed to execute ffmpeg for noise censoring")?;
-    
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("ffmpeg failed to apply noise censoring: {}", error);
-    }
-    
-    Ok(())
+
+    // The legacy volume is a fixed noise level rather than a dialogue match
+    let mut config = CensoringConfig {
+        fade_duration,
+        ..Default::default()
+    };
+    config.custom_params.insert("level_match".to_string(), false.into());
+    config.custom_params.insert(
+        "level_db".to_string(),
+        (20.0 * (noise_volume.max(0.001) as f64).log10()).clamp(-60.0, 0.0).into(),
+    );
+
+    NoiseStrategy.validate_config(&config)?;
+    NoiseStrategy.apply_censoring(input_path, output_path, segments, &config).await
+        .context("Failed to apply noise censoring")
 }
 
 /// Preview censoring effects without writing to file
@@ -295,11 +334,8 @@ pub async fn preview_censoring(
 ) -> Result<Vec<AudioSegment>> {
     let censor_config = CensorConfig::from(config);
     
-    // Merge nearby detections
-    let audio_segments = merge_detections(detections.to_vec(), censor_config.merge_gap as f64);
-    
-    // Add padding to segments
-    let padded_segments = add_padding_to_segments(audio_segments, censor_config.padding);
+    // Merge nearby detections and pad them
+    let padded_segments = prepare_segments(detection_segments(detections), &censor_config.segments, None);
     
     info!("Preview: {} segments will be censored", padded_segments.len());
     for (i, segment) in padded_segments.iter().enumerate() {
@@ -311,7 +347,7 @@ pub async fn preview_censoring(
 }
 
 /// Get statistics about censoring operations
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CensoringStats {
     pub total_detections: usize,
     pub merged_segments: usize,
@@ -328,9 +364,12 @@ pub async fn get_censoring_stats(
     let censor_config = CensorConfig::from(config);
     let audio_duration = crate::audio::get_audio_duration(audio_path).await?;
     
-    // Merge nearby detections
-    let audio_segments = merge_detections(detections.to_vec(), censor_config.merge_gap as f64);
-    let padded_segments = add_padding_to_segments(audio_segments, censor_config.padding);
+    // Merge nearby detections and pad them
+    let padded_segments = prepare_segments(
+        detection_segments(detections),
+        &censor_config.segments,
+        Some(audio_duration),
+    );
     
     let total_censored_duration: f64 = padded_segments.iter()
         .map(|s| s.duration)
@@ -362,7 +401,7 @@ mod tests {
             AudioSegment::new(15.0, 16.5),
         ];
         
-        let padded = add_padding_to_segments(segments, 0.2);
+        let padded = add_padding_to_segments(segments, 0.2, 0.2, None);
         
         assert_eq!(padded.len(), 2);
         assert!((padded[0].start_time - 9.8).abs() < 1e-6);
@@ -374,10 +413,77 @@ mod tests {
     #[test]
     fn test_padding_prevents_negative_time() {
         let segments = vec![AudioSegment::new(0.1, 0.5)];
-        let padded = add_padding_to_segments(segments, 0.2);
+        let padded = add_padding_to_segments(segments, 0.2, 0.2, None);
         
         assert_eq!(padded[0].start_time, 0.0); // Should not go below 0
         assert!((padded[0].end_time - 0.7).abs() < 1e-6);
+
+        // Nor past the end of the audio
+        let segments = vec![AudioSegment::new(9.7, 9.9), AudioSegment::new(10.3, 10.5)];
+        let padded = add_padding_to_segments(segments, 0.2, 0.2, Some(10.0));
+        assert_eq!(padded.len(), 1);
+        assert_eq!(padded[0].end_time, 10.0);
+    }
+
+    #[test]
+    fn test_prepare_segments() {
+        let options = SegmentOptions {
+            merge_gap: 0.2,
+            pre_padding: 0.05,
+            post_padding: 0.25,
+        };
+        let segments = vec![
+            AudioSegment::new(5.0, 5.4),
+            AudioSegment::new(1.0, 1.2),
+            AudioSegment::new(1.35, 1.6), // within the merge gap
+            AudioSegment::new(1.85, 2.3), // only overlaps once padded
+            AudioSegment::new(5.8, 6.0),
+        ];
+        let prepared = prepare_segments(segments, &options, Some(6.1));
+        let spans: Vec<(f64, f64)> = prepared.iter()
+            .map(|s| ((s.start_time * 100.0).round() / 100.0, (s.end_time * 100.0).round() / 100.0))
+            .collect();
+        assert_eq!(spans, vec![(0.95, 2.55), (4.95, 5.65), (5.75, 6.1)]);
+    }
+
+    #[test]
+    fn test_padded_groups_do_not_overlap() {
+        use crate::rules::{self, StrategyRule};
+
+        let options = SegmentOptions {
+            merge_gap: 0.0,
+            pre_padding: 0.2,
+            post_padding: 0.2,
+        };
+        let rules: Vec<StrategyRule> = serde_yaml::from_str("- category: slur\n  strategy: beep\n").unwrap();
+        let config = Config { strategy: "reverse".to_string(), rules, ..Default::default() };
+        let word = |start, end, category: &str| WordDetection {
+            start_time: start,
+            end_time: end,
+            is_swear: true,
+            category: Some(category.to_string()),
+            ..Default::default()
+        };
+        // Adjacent words, the second handled by the higher-priority rule
+        let detections = vec![word(1.0, 1.3, "profanity"), word(1.4, 1.7, "slur")];
+
+        let mut assignments = rules::assign_strategies(&detections, &config);
+        prepare_assignments(&mut assignments, &options, None);
+        let spans = |strategy: &str| -> Vec<(f64, f64)> {
+            assignments.iter().find(|a| a.strategy == strategy).unwrap().segments.iter()
+                .map(|s| ((s.start_time * 100.0).round() / 100.0, (s.end_time * 100.0).round() / 100.0))
+                .collect()
+        };
+        assert_eq!(spans("beep"), vec![(1.2, 1.9)]);
+        assert_eq!(spans("reverse"), vec![(0.8, 1.2)]);
+
+        // A lower-priority segment spanning a higher one is split around it
+        let claimed = vec![AudioSegment::new(2.0, 3.0)];
+        let split = subtract_segments(vec![AudioSegment::new(1.0, 4.0)], &claimed);
+        assert_eq!(split.len(), 2);
+        assert_eq!((split[0].start_time, split[0].end_time), (1.0, 2.0));
+        assert_eq!((split[1].start_time, split[1].end_time), (3.0, 4.0));
+        assert!(subtract_segments(vec![AudioSegment::new(2.2, 2.8)], &claimed).is_empty());
     }
 
     #[test]
@@ -398,5 +504,6 @@ mod tests {
         }
         
         assert_eq!(censor_config.fade_duration, 0.3);
+        assert_eq!(censor_config.segments, SegmentOptions::default());
     }
 }
\ No newline at end of file
@@ -1,5 +1,18 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
+use crate::captions::CaptionFormat;
+use crate::encoding::EncodingSettings;
+use crate::envelope::FadeCurve;
 use crate::error::{config_error, BabymodeError, Result};
+use crate::export::ExportFormat;
+use crate::indicator::IndicatorSettings;
+use crate::lexicon::{Lexicon, LexiconEntry};
+use crate::loudness::LoudnessTarget;
+use crate::player::PlayerFormat;
+use crate::rules::StrategyRule;
+use crate::subtitles::SubtitleMask;
+use crate::transcript::{CueLocalizer, TranscriptSource};
+use crate::visual::{BlurRegion, BlurStyle};
 
 /// Whisper model variants
 #[derive(Debug, Clone, PartialEq)]
@@ -49,7 +62,70 @@ pub struct Config {
     pub whisper_model: WhisperModel,
     pub censor_volume: f32,
     pub fade_duration: f32,
+    pub fade_curve: FadeCurve,
     pub swear_words: Vec<String>,
+    pub replacement_audio: Option<PathBuf>,
+    /// Name of the censoring strategy to apply
+    pub strategy: String,
+    /// Parameters for each strategy, keyed by strategy name
+    pub strategy_params: HashMap<String, HashMap<String, serde_json::Value>>,
+    /// Category and severity of censored words
+    pub lexicon: Lexicon,
+    /// Per-detection strategy choices, first match wins
+    pub rules: Vec<StrategyRule>,
+    /// Snap word edges to nearby low-energy points before censoring
+    pub refine_segments: bool,
+    /// How far (in seconds) refinement may move each edge
+    pub refine_window: f32,
+    /// Detections closer than this (in seconds) are censored as one segment
+    pub merge_gap: f32,
+    /// Extra time censored before each segment, in seconds
+    pub pre_padding: f32,
+    /// Extra time censored after each segment, in seconds
+    pub post_padding: f32,
+    /// Normalise the censored programme to this loudness
+    pub loudness: Option<LoudnessTarget>,
+    /// Output audio encoding, mirroring the source where unset
+    pub encoding: EncodingSettings,
+    /// Carry soft subtitle tracks into the output with swear words masked
+    pub censor_subtitles: bool,
+    /// What replaces swear words in subtitles
+    pub subtitle_mask: SubtitleMask,
+    /// Caption sidecars to write from the transcript
+    pub caption_formats: Vec<CaptionFormat>,
+    /// Longest caption line, in characters
+    pub caption_line_length: usize,
+    /// Longest a caption stays on screen, in seconds
+    pub caption_max_duration: f32,
+    /// Time captions word by word
+    pub caption_karaoke: bool,
+    /// Add the transcript captions to the output as a soft subtitle stream
+    pub mux_captions: bool,
+    /// Where the words to censor are read from
+    pub transcript_source: TranscriptSource,
+    /// Subtitle file to read instead of the input's own tracks
+    pub subtitle_file: Option<PathBuf>,
+    /// How swear words found in subtitle cues are pinned to the audio
+    pub cue_localizer: CueLocalizer,
+    /// Hide the speaker's mouth during censored segments
+    pub mouth_blur: Option<BlurStyle>,
+    /// Area hidden by the mouth blur
+    pub blur_region: BlurRegion,
+    /// Extra time the mouth stays hidden around each segment, in seconds
+    pub blur_margin: f32,
+    /// On-screen indicator drawn during censored segments
+    pub indicator: IndicatorSettings,
+    /// Edit decision lists to write next to the output
+    pub export_formats: Vec<ExportFormat>,
+    /// Mute files for media players to write next to the input
+    pub player_sidecars: Vec<PlayerFormat>,
+    /// Only detect and write the exports and sidecars, without rendering
+    /// the output
+    pub detect_only: bool,
+    /// Audacity label file to censor instead of detecting swear words
+    pub label_file: Option<PathBuf>,
+    /// Where to write the JSON run report
+    pub report_file: Option<PathBuf>,
 }
 
 impl Config {
@@ -92,6 +168,27 @@ impl Config {
             ));
         }
 
+        // Validate indicator settings and asset
+        self.indicator.indicator()?;
+        if let Some(ref asset) = self.indicator.asset {
+            if !asset.is_file() {
+                return Err(config_error(
+                    "indicator_asset",
+                    format!("Indicator image does not exist: {}", asset.display())
+                ));
+            }
+        }
+
+        // Validate the imported label file exists
+        if let Some(ref path) = self.label_file {
+            if !path.is_file() {
+                return Err(config_error(
+                    "label_file",
+                    format!("Label file does not exist: {}", path.display())
+                ));
+            }
+        }
+
         // Validate swear words list is not empty
         if self.swear_words.is_empty() {
             return Err(config_error(
@@ -111,9 +208,8 @@ impl Config {
                 .and_then(|s| s.to_str())
                 .ok_or_else(|| config_error("input_file", "Invalid filename"))?;
                 
-            let input_ext = self.input_file
-                .extension()
-                .and_then(|s| s.to_str())
+            let input_ext = self.encoding.container.as_deref()
+                .or_else(|| self.input_file.extension().and_then(|s| s.to_str()))
                 .unwrap_or("mp4");
             
             let mut output_path = self.input_file.clone();
@@ -122,6 +218,16 @@ impl Config {
         }
         Ok(())
     }
+
+    /// Whether a run with nothing to censor still has to re-encode rather
+    /// than copy the input: the container or audio encoding changes,
+    /// subtitles were edited, or loudness is normalised
+    pub fn clean_copy_needs_encode(&self, subtitles_changed: bool) -> bool {
+        let extension = |path: &std::path::Path| path.extension().map(|e| e.to_ascii_lowercase());
+        let same_format = self.output_file.as_deref()
+            .is_some_and(|output| extension(output) == extension(&self.input_file));
+        !same_format || !self.encoding.is_match_source() || subtitles_changed || self.loudness.is_some()
+    }
 }
 
 impl Default for Config {
@@ -132,6 +238,7 @@ impl Default for Config {
             whisper_model: WhisperModel::Base,
             censor_volume: 0.1, // 10% volume during censoring
             fade_duration: 0.2, // 200ms fade in/out
+            fade_curve: FadeCurve::Linear,
             swear_words: vec![
                 "fuck".to_string(),
                 "shit".to_string(),
@@ -141,6 +248,37 @@ impl Default for Config {
                 "bitch".to_string(),
                 "bastard".to_string(),
             ],
+            replacement_audio: None,
+            strategy: "silence".to_string(),
+            strategy_params: HashMap::new(),
+            lexicon: Lexicon::default(),
+            rules: Vec::new(),
+            refine_segments: false,
+            refine_window: 0.15,
+            merge_gap: 0.5, // Merge detections within 0.5 seconds
+            pre_padding: 0.1, // 100ms before each word
+            post_padding: 0.1, // 100ms after each word
+            loudness: None,
+            encoding: EncodingSettings::default(),
+            censor_subtitles: true,
+            subtitle_mask: SubtitleMask::default(),
+            caption_formats: Vec::new(),
+            caption_line_length: 42,
+            caption_max_duration: 6.0,
+            caption_karaoke: false,
+            mux_captions: false,
+            transcript_source: TranscriptSource::default(),
+            subtitle_file: None,
+            cue_localizer: CueLocalizer::default(),
+            mouth_blur: None,
+            blur_region: BlurRegion::default(),
+            blur_margin: 0.25,
+            indicator: IndicatorSettings::default(),
+            export_formats: Vec::new(),
+            player_sidecars: Vec::new(),
+            detect_only: false,
+            label_file: None,
+            report_file: None,
         }
     }
 }
@@ -153,7 +291,41 @@ pub struct ConfigBuilder {
     whisper_model: Option<WhisperModel>,
     censor_volume: Option<f32>,
     fade_duration: Option<f32>,
+    fade_curve: Option<FadeCurve>,
     swear_words: Option<Vec<String>>,
+    replacement_audio: Option<PathBuf>,
+    strategy: Option<String>,
+    strategy_params: HashMap<String, HashMap<String, serde_json::Value>>,
+    /// Parameters for whichever strategy ends up selected
+    selected_strategy_params: HashMap<String, serde_json::Value>,
+    lexicon_entries: HashMap<String, LexiconEntry>,
+    rules: Option<Vec<StrategyRule>>,
+    refine_segments: Option<bool>,
+    refine_window: Option<f32>,
+    merge_gap: Option<f32>,
+    pre_padding: Option<f32>,
+    post_padding: Option<f32>,
+    loudness: Option<LoudnessTarget>,
+    encoding: EncodingSettings,
+    censor_subtitles: Option<bool>,
+    subtitle_mask: Option<SubtitleMask>,
+    caption_formats: Option<Vec<CaptionFormat>>,
+    caption_line_length: Option<usize>,
+    caption_max_duration: Option<f32>,
+    caption_karaoke: Option<bool>,
+    mux_captions: Option<bool>,
+    transcript_source: Option<TranscriptSource>,
+    subtitle_file: Option<PathBuf>,
+    cue_localizer: Option<CueLocalizer>,
+    mouth_blur: Option<BlurStyle>,
+    blur_region: Option<BlurRegion>,
+    blur_margin: Option<f32>,
+    indicator: IndicatorSettings,
+    export_formats: Option<Vec<ExportFormat>>,
+    player_sidecars: Option<Vec<PlayerFormat>>,
+    detect_only: Option<bool>,
+    label_file: Option<PathBuf>,
+    report_file: Option<PathBuf>,
 }
 
 impl ConfigBuilder {
@@ -198,6 +370,11 @@ impl ConfigBuilder {
         Ok(self)
     }
 
+    pub fn fade_curve(mut self, curve: FadeCurve) -> Self {
+        self.fade_curve = Some(curve);
+        self
+    }
+
     pub fn swear_words(mut self, words: Vec<String>) -> Result<Self> {
         if words.is_empty() {
             return Err(config_error("swear_words", "Swear words list cannot be empty"));
@@ -216,6 +393,227 @@ impl ConfigBuilder {
         Ok(self)
     }
 
+    pub fn replacement_audio(mut self, path: PathBuf) -> Self {
+        self.replacement_audio = Some(path);
+        self
+    }
+
+    pub fn strategy(mut self, name: impl Into<String>) -> Self {
+        self.strategy = Some(name.into());
+        self
+    }
+
+    /// Set parameters of the named strategy, replacing earlier values of the
+    /// same keys
+    pub fn strategy_params(mut self, strategy: &str, params: HashMap<String, serde_json::Value>) -> Self {
+        self.strategy_params.entry(strategy.to_string()).or_default().extend(params);
+        self
+    }
+
+    /// Set a parameter of the strategy selected when the config is built
+    pub fn strategy_param(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.selected_strategy_params.insert(key.into(), value);
+        self
+    }
+
+    /// Classify words, on top of the built-in lexicon
+    pub fn lexicon_entries(mut self, entries: HashMap<String, LexiconEntry>) -> Self {
+        self.lexicon_entries.extend(entries);
+        self
+    }
+
+    pub fn rules(mut self, rules: Vec<StrategyRule>) -> Result<Self> {
+        if let Some(rule) = rules.iter().find(|r| r.strategy.trim().is_empty()) {
+            return Err(config_error("rules", format!("Rule has no strategy: {:?}", rule)));
+        }
+        self.rules = Some(rules);
+        Ok(self)
+    }
+
+    pub fn refine_segments(mut self, enabled: bool) -> Self {
+        self.refine_segments = Some(enabled);
+        self
+    }
+
+    pub fn refine_window(mut self, window: f32) -> Result<Self> {
+        if !(0.01..=0.5).contains(&window) {
+            return Err(config_error(
+                "refine_window",
+                format!("Refine window must be between 0.01 and 0.5 seconds, got {}", window)
+            ));
+        }
+        self.refine_window = Some(window);
+        Ok(self)
+    }
+
+    pub fn merge_gap(mut self, gap: f32) -> Result<Self> {
+        if !(0.0..=5.0).contains(&gap) {
+            return Err(config_error(
+                "merge_gap",
+                format!("Merge gap must be between 0.0 and 5.0 seconds, got {}", gap)
+            ));
+        }
+        self.merge_gap = Some(gap);
+        Ok(self)
+    }
+
+    pub fn pre_padding(mut self, padding: f32) -> Result<Self> {
+        self.pre_padding = Some(check_padding("pre_padding", padding)?);
+        Ok(self)
+    }
+
+    pub fn post_padding(mut self, padding: f32) -> Result<Self> {
+        self.post_padding = Some(check_padding("post_padding", padding)?);
+        Ok(self)
+    }
+
+    pub fn loudness(mut self, target: LoudnessTarget) -> Self {
+        self.loudness = Some(target);
+        self
+    }
+
+    /// Set encoding options, keeping earlier ones that `settings` leaves unset
+    pub fn encoding(mut self, settings: EncodingSettings) -> Result<Self> {
+        if let Some(rate) = settings.sample_rate {
+            if !(8000..=192000).contains(&rate) {
+                return Err(config_error(
+                    "sample_rate",
+                    format!("Sample rate must be between 8000 and 192000 Hz, got {}", rate)
+                ));
+            }
+        }
+        if let Some(ref container) = settings.container {
+            if container.is_empty() || !container.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(config_error("container", format!("Invalid container '{}'", container)));
+            }
+        }
+        self.encoding.merge(settings);
+        Ok(self)
+    }
+
+    pub fn censor_subtitles(mut self, enabled: bool) -> Self {
+        self.censor_subtitles = Some(enabled);
+        self
+    }
+
+    pub fn subtitle_mask(mut self, mask: SubtitleMask) -> Result<Self> {
+        if mask == SubtitleMask::Text(String::new()) {
+            return Err(config_error("subtitle_mask", "Subtitle mask text cannot be empty"));
+        }
+        self.subtitle_mask = Some(mask);
+        Ok(self)
+    }
+
+    pub fn caption_formats(mut self, formats: Vec<CaptionFormat>) -> Self {
+        self.caption_formats = Some(formats);
+        self
+    }
+
+    pub fn caption_line_length(mut self, length: usize) -> Result<Self> {
+        if !(10..=200).contains(&length) {
+            return Err(config_error(
+                "caption_line_length",
+                format!("Caption line length must be between 10 and 200 characters, got {}", length)
+            ));
+        }
+        self.caption_line_length = Some(length);
+        Ok(self)
+    }
+
+    pub fn caption_max_duration(mut self, duration: f32) -> Result<Self> {
+        if !(1.0..=30.0).contains(&duration) {
+            return Err(config_error(
+                "caption_max_duration",
+                format!("Caption duration must be between 1 and 30 seconds, got {}", duration)
+            ));
+        }
+        self.caption_max_duration = Some(duration);
+        Ok(self)
+    }
+
+    pub fn caption_karaoke(mut self, enabled: bool) -> Self {
+        self.caption_karaoke = Some(enabled);
+        self
+    }
+
+    pub fn mux_captions(mut self, enabled: bool) -> Self {
+        self.mux_captions = Some(enabled);
+        self
+    }
+
+    pub fn transcript_source(mut self, source: TranscriptSource) -> Self {
+        self.transcript_source = Some(source);
+        self
+    }
+
+    /// Read swear words from this subtitle file. Implies the subtitles
+    /// transcript source unless another is set.
+    pub fn subtitle_file(mut self, path: PathBuf) -> Self {
+        self.subtitle_file = Some(path);
+        self
+    }
+
+    pub fn cue_localizer(mut self, localizer: CueLocalizer) -> Self {
+        self.cue_localizer = Some(localizer);
+        self
+    }
+
+    pub fn mouth_blur(mut self, style: BlurStyle) -> Self {
+        self.mouth_blur = Some(style);
+        self
+    }
+
+    pub fn blur_region(mut self, region: BlurRegion) -> Self {
+        self.blur_region = Some(region);
+        self
+    }
+
+    pub fn blur_margin(mut self, margin: f32) -> Result<Self> {
+        if !(0.0..=2.0).contains(&margin) {
+            return Err(config_error(
+                "blur_margin",
+                format!("Blur margin must be between 0.0 and 2.0 seconds, got {}", margin)
+            ));
+        }
+        self.blur_margin = Some(margin);
+        Ok(self)
+    }
+
+    /// Set indicator options, keeping earlier ones that `settings` leaves unset
+    pub fn indicator(mut self, settings: IndicatorSettings) -> Result<Self> {
+        settings.check()?;
+        self.indicator.merge(settings);
+        Ok(self)
+    }
+
+    pub fn export_formats(mut self, formats: Vec<ExportFormat>) -> Self {
+        self.export_formats = Some(formats);
+        self
+    }
+
+    pub fn player_sidecars(mut self, formats: Vec<PlayerFormat>) -> Self {
+        self.player_sidecars = Some(formats);
+        self
+    }
+
+    pub fn detect_only(mut self, enabled: bool) -> Self {
+        self.detect_only = Some(enabled);
+        self
+    }
+
+    /// Censor the labels in this Audacity label file instead of detecting
+    /// swear words
+    pub fn label_file(mut self, path: PathBuf) -> Self {
+        self.label_file = Some(path);
+        self
+    }
+
+    /// Write a JSON report of the run to this path
+    pub fn report_file(mut self, path: PathBuf) -> Self {
+        self.report_file = Some(path);
+        self
+    }
+
     pub fn build(self) -> Result<Config> {
         let input_file = self.input_file
             .ok_or_else(|| config_error("input_file", "Input file is required"))?;
@@ -226,8 +624,55 @@ impl ConfigBuilder {
             whisper_model: self.whisper_model.unwrap_or(WhisperModel::Base),
             censor_volume: self.censor_volume.unwrap_or(0.1),
             fade_duration: self.fade_duration.unwrap_or(0.2),
+            fade_curve: self.fade_curve.unwrap_or_default(),
             swear_words: self.swear_words.unwrap_or_else(|| Config::default().swear_words),
+            replacement_audio: self.replacement_audio,
+            strategy: self.strategy.unwrap_or_else(|| Config::default().strategy),
+            strategy_params: self.strategy_params,
+            lexicon: Lexicon::default(),
+            rules: self.rules.unwrap_or_default(),
+            refine_segments: self.refine_segments.unwrap_or(false),
+            refine_window: self.refine_window.unwrap_or(0.15),
+            merge_gap: self.merge_gap.unwrap_or(0.5),
+            pre_padding: self.pre_padding.unwrap_or(0.1),
+            post_padding: self.post_padding.unwrap_or(0.1),
+            loudness: self.loudness,
+            encoding: self.encoding,
+            censor_subtitles: self.censor_subtitles.unwrap_or(true),
+            subtitle_mask: self.subtitle_mask.unwrap_or_default(),
+            caption_formats: self.caption_formats.unwrap_or_default(),
+            caption_line_length: self.caption_line_length.unwrap_or(42),
+            caption_max_duration: self.caption_max_duration.unwrap_or(6.0),
+            caption_karaoke: self.caption_karaoke.unwrap_or(false),
+            mux_captions: self.mux_captions.unwrap_or(false),
+            transcript_source: self.transcript_source.unwrap_or(match self.subtitle_file {
+                Some(_) => TranscriptSource::Subtitles,
+                None => TranscriptSource::Whisper,
+            }),
+            subtitle_file: self.subtitle_file,
+            cue_localizer: self.cue_localizer.unwrap_or_default(),
+            mouth_blur: self.mouth_blur,
+            blur_region: self.blur_region.unwrap_or_default(),
+            blur_margin: self.blur_margin.unwrap_or(0.25),
+            indicator: self.indicator,
+            export_formats: self.export_formats.unwrap_or_default(),
+            player_sidecars: self.player_sidecars.unwrap_or_default(),
+            detect_only: self.detect_only.unwrap_or(false),
+            label_file: self.label_file,
+            report_file: self.report_file,
         };
+        // Detection alone is only useful with something to write it to
+        if config.detect_only && config.export_formats.is_empty() && config.player_sidecars.is_empty() {
+            config.player_sidecars = PlayerFormat::ALL.to_vec();
+        }
+        for (word, entry) in self.lexicon_entries {
+            config.lexicon.insert(&word, entry);
+        }
+        if !self.selected_strategy_params.is_empty() {
+            config.strategy_params.entry(config.strategy.clone())
+                .or_default()
+                .extend(self.selected_strategy_params);
+        }
 
         config.validate()?;
         config.ensure_output_file()?;
@@ -236,6 +681,16 @@ impl ConfigBuilder {
     }
 }
 
+fn check_padding(field: &str, padding: f32) -> Result<f32> {
+    if !(0.0..=2.0).contains(&padding) {
+        return Err(config_error(
+            field,
+            format!("Padding must be between 0.0 and 2.0 seconds, got {}", padding)
+        ));
+    }
+    Ok(padding)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -264,6 +719,36 @@ mod tests {
 
         assert_eq!(config.censor_volume, 0.2);
         assert_eq!(config.fade_duration, 0.5);
+        assert_eq!(config.strategy, "silence");
+        assert_eq!(config.merge_gap, 0.5);
+        assert_eq!(config.pre_padding, 0.1);
+        assert_eq!(config.post_padding, 0.1);
+
+        assert!(Config::builder().pre_padding(-0.1).is_err());
+        assert!(Config::builder().merge_gap(10.0).is_err());
+    }
+
+    #[test]
+    fn test_strategy_params_layering() {
+        let temp_dir = tempdir().unwrap();
+        let input_path = temp_dir.path().join("test.mp4");
+        File::create(&input_path).unwrap();
+
+        let file_params = HashMap::from([
+            ("preset".to_string(), serde_json::json!("broadcast")),
+            ("frequency".to_string(), serde_json::json!(800)),
+        ]);
+        let config = Config::builder()
+            .input_file(input_path)
+            .strategy_params("beep", file_params)
+            .strategy_param("frequency", serde_json::json!("1200"))
+            .strategy("beep")
+            .build()
+            .unwrap();
+
+        let beep = &config.strategy_params["beep"];
+        assert_eq!(beep["preset"], "broadcast");
+        assert_eq!(beep["frequency"], "1200");
     }
 
     #[test]
@@ -276,4 +761,23 @@ mod tests {
         
         assert!(config.validate().is_err());
     }
+
+    #[test]
+    fn test_clean_copy_needs_encode() {
+        let mut config = Config {
+            input_file: PathBuf::from("film.MP4"),
+            output_file: Some(PathBuf::from("film_censored.mp4")),
+            ..Default::default()
+        };
+        assert!(!config.clean_copy_needs_encode(false));
+        assert!(config.clean_copy_needs_encode(true));
+
+        // Normalising has to touch the audio even when nothing is censored
+        config.loudness = Some(LoudnessTarget::STREAMING);
+        assert!(config.clean_copy_needs_encode(false));
+
+        config.loudness = None;
+        config.output_file = Some(PathBuf::from("film_censored.mkv"));
+        assert!(config.clean_copy_needs_encode(false));
+    }
 }
\ No newline at end of file
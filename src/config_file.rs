@@ -1,6 +1,18 @@
+use crate::captions::CaptionFormat;
 use crate::config::{ConfigBuilder, WhisperModel};
+use crate::encoding::EncodingSettings;
+use crate::envelope::FadeCurve;
 use crate::error::{BabymodeError, Result};
+use crate::export::ExportFormat;
+use crate::indicator::IndicatorSettings;
+use crate::lexicon::LexiconEntry;
+use crate::player::PlayerFormat;
+use crate::rules::StrategyRule;
+use crate::subtitles::SubtitleMask;
+use crate::transcript::{CueLocalizer, TranscriptSource};
+use crate::visual::{BlurRegion, BlurStyle};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
@@ -13,8 +25,73 @@ pub struct ConfigFile {
     pub censor_volume: Option<f32>,
     /// Default fade duration in seconds
     pub fade_duration: Option<f32>,
+    /// Default fade curve (linear, logarithmic, equal_power, s_curve)
+    pub fade_curve: Option<String>,
     /// Custom swear words list
     pub swear_words: Option<Vec<String>>,
+    /// Clip, or folder of clips, used by the replacement strategy
+    pub replacement_audio: Option<PathBuf>,
+    /// Default censoring strategy
+    pub strategy: Option<String>,
+    /// Parameters for each strategy, keyed by strategy name
+    pub strategies: Option<HashMap<String, HashMap<String, serde_json::Value>>>,
+    /// Directory of external strategy plugins
+    pub plugins_dir: Option<PathBuf>,
+    /// Category and severity of words, keyed by word
+    pub lexicon: Option<HashMap<String, LexiconEntry>>,
+    /// Strategy rules by category, severity or confidence
+    pub rules: Option<Vec<StrategyRule>>,
+    /// Snap word edges to nearby low-energy points
+    pub refine_segments: Option<bool>,
+    /// Furthest refinement may move an edge, in seconds
+    pub refine_window: Option<f32>,
+    /// Detections closer than this are censored as one segment, in seconds
+    pub merge_gap: Option<f32>,
+    /// Extra time censored before each segment, in seconds
+    pub pre_padding: Option<f32>,
+    /// Extra time censored after each segment, in seconds
+    pub post_padding: Option<f32>,
+    /// Loudness target: ebu_r128, atsc_a85, streaming or a LUFS value
+    pub loudness: Option<String>,
+    /// Named encoding preset: web, archive, broadcast or one from `encoding_presets`
+    pub encoding_preset: Option<String>,
+    /// Encoding options, over those of the preset
+    pub encoding: Option<EncodingSettings>,
+    /// Custom encoding presets, keyed by name
+    pub encoding_presets: Option<HashMap<String, EncodingSettings>>,
+    /// Keep soft subtitle tracks, with swear words masked
+    pub censor_subtitles: Option<bool>,
+    /// Subtitle mask: asterisks, grawlix or replacement text
+    pub subtitle_mask: Option<SubtitleMask>,
+    /// Caption sidecars to write from the transcript: srt, vtt
+    pub captions: Option<Vec<CaptionFormat>>,
+    /// Longest caption line, in characters
+    pub caption_line_length: Option<usize>,
+    /// Longest a caption stays on screen, in seconds
+    pub caption_max_duration: Option<f32>,
+    /// Time captions word by word
+    pub caption_karaoke: Option<bool>,
+    /// Add the captions to the output as a soft subtitle stream
+    pub mux_captions: Option<bool>,
+    /// Where swear words are read from: whisper or subtitles
+    pub transcript_source: Option<TranscriptSource>,
+    /// How words found in subtitle cues are timed: whisper or energy
+    pub cue_localizer: Option<CueLocalizer>,
+    /// Hide the speaker's mouth during censored segments: blur or pixelate
+    pub mouth_blur: Option<BlurStyle>,
+    /// Area to hide: face, or x,y,width,height
+    pub blur_region: Option<BlurRegion>,
+    /// Extra time the mouth stays hidden around each segment, in seconds
+    pub blur_margin: Option<f32>,
+    /// On-screen indicator drawn during censored segments
+    pub indicator: Option<IndicatorSettings>,
+    /// Edit decision lists to write next to the output: edl, fcpxml, mlt
+    pub export: Option<Vec<ExportFormat>>,
+    /// Mute files for media players to write next to the input: kodi, mpv, segments
+    pub player_sidecars: Option<Vec<PlayerFormat>>,
+    /// Only detect and write the exports and sidecars, without rendering
+    #[serde(alias = "export_only")]
+    pub detect_only: Option<bool>,
     /// Default output directory
     pub output_directory: Option<PathBuf>,
     /// Enable progress indicators by default
@@ -22,22 +99,53 @@ pub struct ConfigFile {
     /// Language for processing (future enhancement)
     pub language: Option<String>,
     /// Custom profiles
-    pub profiles: Option<std::collections::HashMap<String, ProfileConfig>>,
+    pub profiles: Option<HashMap<String, ProfileConfig>>,
 }
 
 /// Profile-specific configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ProfileConfig {
     pub whisper_model: Option<String>,
     pub censor_volume: Option<f32>,
     pub fade_duration: Option<f32>,
+    pub fade_curve: Option<String>,
     pub swear_words: Option<Vec<String>>,
+    pub replacement_audio: Option<PathBuf>,
+    pub strategy: Option<String>,
+    pub strategies: Option<HashMap<String, HashMap<String, serde_json::Value>>>,
+    pub lexicon: Option<HashMap<String, LexiconEntry>>,
+    pub rules: Option<Vec<StrategyRule>>,
+    pub refine_segments: Option<bool>,
+    pub refine_window: Option<f32>,
+    pub merge_gap: Option<f32>,
+    pub pre_padding: Option<f32>,
+    pub post_padding: Option<f32>,
+    pub loudness: Option<String>,
+    pub encoding_preset: Option<String>,
+    pub encoding: Option<EncodingSettings>,
+    pub censor_subtitles: Option<bool>,
+    pub subtitle_mask: Option<SubtitleMask>,
+    pub captions: Option<Vec<CaptionFormat>>,
+    pub caption_line_length: Option<usize>,
+    pub caption_max_duration: Option<f32>,
+    pub caption_karaoke: Option<bool>,
+    pub mux_captions: Option<bool>,
+    pub transcript_source: Option<TranscriptSource>,
+    pub cue_localizer: Option<CueLocalizer>,
+    pub mouth_blur: Option<BlurStyle>,
+    pub blur_region: Option<BlurRegion>,
+    pub blur_margin: Option<f32>,
+    pub indicator: Option<IndicatorSettings>,
+    pub export: Option<Vec<ExportFormat>>,
+    pub player_sidecars: Option<Vec<PlayerFormat>>,
+    #[serde(alias = "export_only")]
+    pub detect_only: Option<bool>,
     pub description: Option<String>,
 }
 
 impl Default for ConfigFile {
     fn default() -> Self {
-        let mut profiles = std::collections::HashMap::new();
+        let mut profiles = HashMap::new();
         
         // Add some default profiles
         profiles.insert("strict".to_string(), ProfileConfig {
@@ -50,6 +158,7 @@ impl Default for ConfigFile {
             ]),
             whisper_model: Some("base".to_string()),
             description: Some("Strict censoring with complete silence".to_string()),
+            ..Default::default()
         });
         
         profiles.insert("mild".to_string(), ProfileConfig {
@@ -60,6 +169,7 @@ impl Default for ConfigFile {
             ]),
             whisper_model: Some("tiny".to_string()),
             description: Some("Mild censoring for minor profanity only".to_string()),
+            ..Default::default()
         });
         
         profiles.insert("family".to_string(), ProfileConfig {
@@ -72,13 +182,46 @@ impl Default for ConfigFile {
             ]),
             whisper_model: Some("small".to_string()),
             description: Some("Family-friendly censoring profile".to_string()),
+            ..Default::default()
         });
 
         Self {
             whisper_model: Some("base".to_string()),
             censor_volume: Some(0.1),
             fade_duration: Some(0.2),
+            fade_curve: None,
             swear_words: None, // Use defaults
+            replacement_audio: None,
+            strategy: None,
+            strategies: None,
+            plugins_dir: None,
+            lexicon: None,
+            rules: None,
+            refine_segments: None,
+            refine_window: None,
+            merge_gap: None,
+            pre_padding: None,
+            post_padding: None,
+            loudness: None,
+            encoding_preset: None,
+            encoding: None,
+            encoding_presets: None,
+            censor_subtitles: None,
+            subtitle_mask: None,
+            captions: None,
+            caption_line_length: None,
+            caption_max_duration: None,
+            caption_karaoke: None,
+            mux_captions: None,
+            transcript_source: None,
+            cue_localizer: None,
+            mouth_blur: None,
+            blur_region: None,
+            blur_margin: None,
+            indicator: None,
+            export: None,
+            player_sidecars: None,
+            detect_only: None,
             output_directory: None,
             show_progress: Some(true),
             language: Some("en".to_string()),
@@ -210,10 +353,131 @@ impl ConfigFile {
             builder = builder.fade_duration(fade)?;
         }
 
+        if let Some(ref curve) = self.fade_curve {
+            let curve: FadeCurve = curve.parse()?;
+            builder = builder.fade_curve(curve);
+        }
+
         if let Some(ref words) = self.swear_words {
             builder = builder.swear_words(words.clone())?;
         }
 
+        if let Some(ref clip) = self.replacement_audio {
+            builder = builder.replacement_audio(clip.clone());
+        }
+
+        if let Some(ref strategy) = self.strategy {
+            builder = builder.strategy(strategy.clone());
+        }
+
+        for (name, params) in self.strategies.iter().flatten() {
+            builder = builder.strategy_params(name, params.clone());
+        }
+
+        if let Some(ref lexicon) = self.lexicon {
+            builder = builder.lexicon_entries(lexicon.clone());
+        }
+
+        if let Some(ref rules) = self.rules {
+            builder = builder.rules(rules.clone())?;
+        }
+
+        if let Some(refine) = self.refine_segments {
+            builder = builder.refine_segments(refine);
+        }
+
+        if let Some(window) = self.refine_window {
+            builder = builder.refine_window(window)?;
+        }
+
+        if let Some(gap) = self.merge_gap {
+            builder = builder.merge_gap(gap)?;
+        }
+
+        if let Some(padding) = self.pre_padding {
+            builder = builder.pre_padding(padding)?;
+        }
+
+        if let Some(padding) = self.post_padding {
+            builder = builder.post_padding(padding)?;
+        }
+
+        if let Some(ref loudness) = self.loudness {
+            builder = builder.loudness(loudness.parse()?);
+        }
+
+        if let Some(ref preset) = self.encoding_preset {
+            builder = builder.encoding(self.encoding_preset(preset)?)?;
+        }
+
+        if let Some(ref encoding) = self.encoding {
+            builder = builder.encoding(encoding.clone())?;
+        }
+
+        if let Some(enabled) = self.censor_subtitles {
+            builder = builder.censor_subtitles(enabled);
+        }
+
+        if let Some(ref mask) = self.subtitle_mask {
+            builder = builder.subtitle_mask(mask.clone())?;
+        }
+
+        if let Some(ref formats) = self.captions {
+            builder = builder.caption_formats(formats.clone());
+        }
+
+        if let Some(length) = self.caption_line_length {
+            builder = builder.caption_line_length(length)?;
+        }
+
+        if let Some(duration) = self.caption_max_duration {
+            builder = builder.caption_max_duration(duration)?;
+        }
+
+        if let Some(enabled) = self.caption_karaoke {
+            builder = builder.caption_karaoke(enabled);
+        }
+
+        if let Some(enabled) = self.mux_captions {
+            builder = builder.mux_captions(enabled);
+        }
+
+        if let Some(source) = self.transcript_source {
+            builder = builder.transcript_source(source);
+        }
+
+        if let Some(localizer) = self.cue_localizer {
+            builder = builder.cue_localizer(localizer);
+        }
+
+        if let Some(style) = self.mouth_blur {
+            builder = builder.mouth_blur(style);
+        }
+
+        if let Some(ref region) = self.blur_region {
+            builder = builder.blur_region(region.clone());
+        }
+
+        if let Some(margin) = self.blur_margin {
+            builder = builder.blur_margin(margin)?;
+        }
+
+        if let Some(ref indicator) = self.indicator {
+            builder = builder.indicator(indicator.clone())?;
+        }
+
+        if let Some(ref formats) = self.export {
+            builder = builder.export_formats(formats.clone());
+        }
+
+        if let Some(ref formats) = self.player_sidecars {
+            builder = builder.player_sidecars(formats.clone());
+        }
+
+        if let Some(enabled) = self.detect_only {
+            builder = builder.detect_only(enabled);
+        }
+
         Ok(builder)
     }
 
@@ -245,13 +509,150 @@ impl ConfigFile {
             builder = builder.fade_duration(fade)?;
         }
 
+        if let Some(ref curve) = profile.fade_curve {
+            let curve: FadeCurve = curve.parse()?;
+            builder = builder.fade_curve(curve);
+        }
+
         if let Some(ref words) = profile.swear_words {
             builder = builder.swear_words(words.clone())?;
         }
 
+        if let Some(ref clip) = profile.replacement_audio {
+            builder = builder.replacement_audio(clip.clone());
+        }
+
+        if let Some(ref strategy) = profile.strategy {
+            builder = builder.strategy(strategy.clone());
+        }
+
+        for (name, params) in profile.strategies.iter().flatten() {
+            builder = builder.strategy_params(name, params.clone());
+        }
+
+        if let Some(ref lexicon) = profile.lexicon {
+            builder = builder.lexicon_entries(lexicon.clone());
+        }
+
+        if let Some(ref rules) = profile.rules {
+            builder = builder.rules(rules.clone())?;
+        }
+
+        if let Some(refine) = profile.refine_segments {
+            builder = builder.refine_segments(refine);
+        }
+
+        if let Some(window) = profile.refine_window {
+            builder = builder.refine_window(window)?;
+        }
+
+        if let Some(gap) = profile.merge_gap {
+            builder = builder.merge_gap(gap)?;
+        }
+
+        if let Some(padding) = profile.pre_padding {
+            builder = builder.pre_padding(padding)?;
+        }
+
+        if let Some(padding) = profile.post_padding {
+            builder = builder.post_padding(padding)?;
+        }
+
+        if let Some(ref loudness) = profile.loudness {
+            builder = builder.loudness(loudness.parse()?);
+        }
+
+        if let Some(ref preset) = profile.encoding_preset {
+            builder = builder.encoding(self.encoding_preset(preset)?)?;
+        }
+
+        if let Some(ref encoding) = profile.encoding {
+            builder = builder.encoding(encoding.clone())?;
+        }
+
+        if let Some(enabled) = profile.censor_subtitles {
+            builder = builder.censor_subtitles(enabled);
+        }
+
+        if let Some(ref mask) = profile.subtitle_mask {
+            builder = builder.subtitle_mask(mask.clone())?;
+        }
+
+        if let Some(ref formats) = profile.captions {
+            builder = builder.caption_formats(formats.clone());
+        }
+
+        if let Some(length) = profile.caption_line_length {
+            builder = builder.caption_line_length(length)?;
+        }
+
+        if let Some(duration) = profile.caption_max_duration {
+            builder = builder.caption_max_duration(duration)?;
+        }
+
+        if let Some(enabled) = profile.caption_karaoke {
+            builder = builder.caption_karaoke(enabled);
+        }
+
+        if let Some(enabled) = profile.mux_captions {
+            builder = builder.mux_captions(enabled);
+        }
+
+        if let Some(source) = profile.transcript_source {
+            builder = builder.transcript_source(source);
+        }
+
+        if let Some(localizer) = profile.cue_localizer {
+            builder = builder.cue_localizer(localizer);
+        }
+
+        if let Some(style) = profile.mouth_blur {
+            builder = builder.mouth_blur(style);
+        }
+
+        if let Some(ref region) = profile.blur_region {
+            builder = builder.blur_region(region.clone());
+        }
+
+        if let Some(margin) = profile.blur_margin {
+            builder = builder.blur_margin(margin)?;
+        }
+
+        if let Some(ref indicator) = profile.indicator {
+            builder = builder.indicator(indicator.clone())?;
+        }
+
+        if let Some(ref formats) = profile.export {
+            builder = builder.export_formats(formats.clone());
+        }
+
+        if let Some(ref formats) = profile.player_sidecars {
+            builder = builder.player_sidecars(formats.clone());
+        }
+
+        if let Some(enabled) = profile.detect_only {
+            builder = builder.detect_only(enabled);
+        }
+
         Ok(builder)
     }
 
+    /// Encoding preset by name, from `encoding_presets` or the built-ins
+    pub fn encoding_preset(&self, name: &str) -> Result<EncodingSettings> {
+        self.encoding_presets.as_ref()
+            .and_then(|presets| presets.get(name))
+            .cloned()
+            .or_else(|| EncodingSettings::preset(name))
+            .ok_or_else(|| {
+                let mut known: Vec<String> = EncodingSettings::PRESETS.iter().map(|p| p.to_string()).collect();
+                known.extend(self.encoding_presets.iter().flat_map(|p| p.keys().cloned()));
+                BabymodeError::Config {
+                    field: "encoding_preset".to_string(),
+                    message: format!("Unknown encoding preset '{}'. Known presets: {}", name, known.join(", ")),
+                }
+            })
+    }
+
     /// List available profiles
     pub fn list_profiles(&self) -> Vec<String> {
         self.profiles
@@ -264,6 +665,7 @@ impl ConfigFile {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::encoding::AudioCodec;
     use tempfile::tempdir;
 
     #[tokio::test]
@@ -306,6 +708,68 @@ mod tests {
         assert!(profiles.contains(&"family".to_string()));
     }
 
+    #[test]
+    fn test_strategy_sections() {
+        let yaml = r#"
+strategy: beep
+merge_gap: 0.3
+strategies:
+  beep:
+    preset: duck_quack
+    frequency: 800
+profiles:
+  quiet:
+    post_padding: 0.25
+    strategies:
+      beep:
+        level_offset_db: -9
+"#;
+        let config: ConfigFile = serde_yaml::from_str(yaml).unwrap();
+        let temp_dir = tempdir().unwrap();
+        let input_path = temp_dir.path().join("test.mp4");
+        std::fs::File::create(&input_path).unwrap();
+
+        let builder = ConfigBuilder::new().input_file(input_path);
+        let built = config.apply_profile_to_builder("quiet", builder).unwrap().build().unwrap();
+        let beep = &built.strategy_params["beep"];
+        assert_eq!(built.strategy, "beep");
+        assert_eq!(beep["preset"], "duck_quack");
+        assert_eq!(beep["frequency"], 800);
+        assert_eq!(beep["level_offset_db"], -9);
+        assert_eq!(built.merge_gap, 0.3);
+        assert_eq!(built.pre_padding, 0.1);
+        assert_eq!(built.post_padding, 0.25);
+    }
+
+    #[test]
+    fn test_encoding_presets() {
+        let yaml = r#"
+encoding_presets:
+  podcast:
+    codec: mp3
+    bitrate: 96k
+    container: mp3
+profiles:
+  delivery:
+    encoding_preset: broadcast
+    encoding:
+      sample_rate: 44100
+"#;
+        let config: ConfigFile = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.encoding_preset("podcast").unwrap().bitrate.as_deref(), Some("96k"));
+        assert!(config.encoding_preset("cinema").is_err());
+
+        let temp_dir = tempdir().unwrap();
+        let input_path = temp_dir.path().join("test.mp4");
+        std::fs::File::create(&input_path).unwrap();
+
+        let builder = ConfigBuilder::new().input_file(input_path);
+        let built = config.apply_profile_to_builder("delivery", builder).unwrap().build().unwrap();
+        assert_eq!(built.encoding.codec, Some(AudioCodec::Pcm));
+        assert_eq!(built.encoding.sample_rate, Some(44100));
+        assert_eq!(built.output_file.unwrap().extension().unwrap(), "mov");
+    }
+
     #[tokio::test]
     async fn test_apply_profile() {
         let config = ConfigFile::default();
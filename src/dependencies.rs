@@ -1,13 +1,28 @@
+use crate::config::Config;
 use crate::error::{BabymodeError, Result};
+use crate::visual::BlurRegion;
 use log::{info, warn};
+use serde::{Deserialize, Serialize};
 use tokio::process::Command;
 
-/// Check if all required system dependencies are available
-pub async fn validate_dependencies() -> Result<()> {
+/// Versions of the external tools a run used, `None` where a tool could not
+/// be run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolVersions {
+    pub ffmpeg: Option<String>,
+    pub python: Option<String>,
+    pub faster_whisper: Option<String>,
+}
+
+/// Check if all system dependencies `config` needs are available
+pub async fn validate_dependencies(config: &Config) -> Result<()> {
     info!("Validating system dependencies...");
     
     check_ffmpeg().await?;
     check_python_and_whisper().await?;
+    if config.mouth_blur.is_some() && config.blur_region == BlurRegion::Face {
+        check_opencv().await?;
+    }
     
     info!("All dependencies validated successfully");
     Ok(())
@@ -111,6 +126,49 @@ async fn check_python_and_whisper() -> Result<()> {
     Ok(())
 }
 
+/// Check that OpenCV can be imported, for mouth blur face detection
+async fn check_opencv() -> Result<()> {
+    let missing = || BabymodeError::MissingDependency {
+        name: "OpenCV".to_string(),
+        suggestion: "Face detection for --mouth-blur needs OpenCV: pip install opencv-python \
+                     (or give a fixed --blur-region)".to_string(),
+    };
+    let output = Command::new("python3")
+        .args(["-c", "import cv2; print(f'OpenCV {cv2.__version__}')"])
+        .output()
+        .await
+        .map_err(|_| missing())?;
+    if !output.status.success() {
+        return Err(missing());
+    }
+
+    info!("OpenCV found: {}", String::from_utf8_lossy(&output.stdout).trim());
+    Ok(())
+}
+
+/// First line `program` prints to stdout, if it runs successfully
+async fn first_line(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+}
+
+/// Versions of FFmpeg, Python and faster-whisper, for run reports
+pub async fn tool_versions() -> ToolVersions {
+    ToolVersions {
+        ffmpeg: first_line("ffmpeg", &["-version"]).await
+            .map(|line| line.split(" Copyright").next().unwrap_or(&line).to_string()),
+        python: first_line("python3", &["--version"]).await,
+        faster_whisper: first_line("python3", &["-c", "import faster_whisper; print(faster_whisper.__version__)"]).await,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -119,7 +177,7 @@ mod tests {
     async fn test_dependency_validation() {
         // This test will only pass if dependencies are installed
         // In CI/CD, this could be configured to expect failure
-        let result = validate_dependencies().await;
+        let result = validate_dependencies(&Config::default()).await;
         
         // Don't fail the test if dependencies aren't available in test environment
         match result {
@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::audio::{self, AudioStreamInfo};
+use crate::error::{config_error, BabymodeError, Result};
+
+/// Audio codecs the output can be encoded with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioCodec {
+    Aac,
+    Opus,
+    Ac3,
+    Flac,
+    Mp3,
+    /// Uncompressed PCM, with the bit depth taken from the sample format
+    Pcm,
+}
+
+impl AudioCodec {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AudioCodec::Aac => "aac",
+            AudioCodec::Opus => "opus",
+            AudioCodec::Ac3 => "ac3",
+            AudioCodec::Flac => "flac",
+            AudioCodec::Mp3 => "mp3",
+            AudioCodec::Pcm => "pcm",
+        }
+    }
+
+    /// ffmpeg encoder for this codec
+    fn encoder(&self, sample_format: Option<&str>) -> String {
+        match self {
+            AudioCodec::Aac => "aac".to_string(),
+            AudioCodec::Opus => "libopus".to_string(),
+            AudioCodec::Ac3 => "ac3".to_string(),
+            AudioCodec::Flac => "flac".to_string(),
+            AudioCodec::Mp3 => "libmp3lame".to_string(),
+            AudioCodec::Pcm => pcm_encoder(sample_format.unwrap_or("s16")),
+        }
+    }
+}
+
+impl std::str::FromStr for AudioCodec {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "aac" => Ok(AudioCodec::Aac),
+            "opus" => Ok(AudioCodec::Opus),
+            "ac3" | "ac-3" => Ok(AudioCodec::Ac3),
+            "flac" => Ok(AudioCodec::Flac),
+            "mp3" => Ok(AudioCodec::Mp3),
+            "pcm" | "wav" => Ok(AudioCodec::Pcm),
+            _ => Err(config_error(
+                "audio_codec",
+                format!("Invalid audio codec '{}'. Valid options: aac, opus, ac3, flac, mp3, pcm", s)
+            )),
+        }
+    }
+}
+
+/// PCM encoder for a sample format such as `s16`, `s24` or `flt`
+fn pcm_encoder(sample_format: &str) -> String {
+    match sample_format.trim_end_matches('p') {
+        "u8" => "pcm_u8".to_string(),
+        "s32" => "pcm_s32le".to_string(),
+        "s24" => "pcm_s24le".to_string(),
+        "flt" | "f32" => "pcm_f32le".to_string(),
+        "dbl" | "f64" => "pcm_f64le".to_string(),
+        _ => "pcm_s16le".to_string(),
+    }
+}
+
+/// How the output audio is encoded. Anything left unset mirrors the source
+/// stream where the output container allows it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EncodingSettings {
+    pub codec: Option<AudioCodec>,
+    /// Bitrate for lossy codecs, e.g. `192k`
+    pub bitrate: Option<String>,
+    /// Sample format, e.g. `s16`, `s24` or `fltp`
+    pub sample_format: Option<String>,
+    pub sample_rate: Option<u32>,
+    /// Extension of the output file when none is given, e.g. `mkv`
+    pub container: Option<String>,
+}
+
+impl EncodingSettings {
+    pub const PRESETS: [&'static str; 3] = ["web", "archive", "broadcast"];
+
+    /// Built-in preset by name
+    pub fn preset(name: &str) -> Option<Self> {
+        let settings = match name.to_lowercase().as_str() {
+            "web" => Self {
+                codec: Some(AudioCodec::Aac),
+                bitrate: Some("160k".to_string()),
+                sample_rate: Some(48000),
+                container: Some("mp4".to_string()),
+                ..Default::default()
+            },
+            "archive" => Self {
+                codec: Some(AudioCodec::Flac),
+                container: Some("mkv".to_string()),
+                ..Default::default()
+            },
+            "broadcast" => Self {
+                codec: Some(AudioCodec::Pcm),
+                sample_format: Some("s24".to_string()),
+                sample_rate: Some(48000),
+                container: Some("mov".to_string()),
+                ..Default::default()
+            },
+            _ => return None,
+        };
+        Some(settings)
+    }
+
+    /// Whether nothing is set, so the output mirrors the source
+    pub fn is_match_source(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Take every setting `other` has
+    pub fn merge(&mut self, other: EncodingSettings) {
+        self.codec = other.codec.or(self.codec);
+        self.bitrate = other.bitrate.or(self.bitrate.take());
+        self.sample_format = other.sample_format.or(self.sample_format.take());
+        self.sample_rate = other.sample_rate.or(self.sample_rate);
+        self.container = other.container.or(self.container.take());
+    }
+}
+
+/// Concrete encoder options for one output file
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioEncoding {
+    pub encoder: String,
+    pub bitrate: Option<String>,
+    pub sample_format: Option<String>,
+    pub sample_rate: Option<u32>,
+}
+
+impl AudioEncoding {
+    /// ffmpeg output options for the audio stream
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec!["-c:a".to_string(), self.encoder.clone()];
+        if let Some(ref bitrate) = self.bitrate {
+            args.extend(["-b:a".to_string(), bitrate.clone()]);
+        }
+        if let Some(ref format) = self.sample_format {
+            args.extend(["-sample_fmt".to_string(), format.clone()]);
+        }
+        if let Some(rate) = self.sample_rate {
+            args.extend(["-ar".to_string(), rate.to_string()]);
+        }
+        args
+    }
+}
+
+fn is_lossless(encoder: &str) -> bool {
+    encoder.starts_with("pcm_") || matches!(encoder, "flac" | "alac")
+}
+
+/// Encoder that reproduces a source codec, when ffmpeg has one
+fn encoder_for_source(codec: &str) -> Option<String> {
+    match codec {
+        "aac" | "ac3" | "eac3" | "flac" | "alac" => Some(codec.to_string()),
+        "opus" => Some("libopus".to_string()),
+        "mp3" => Some("libmp3lame".to_string()),
+        "vorbis" => Some("libvorbis".to_string()),
+        pcm if pcm.starts_with("pcm_") => Some(pcm.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether the container of `output_path` can hold `encoder`. Unknown
+/// containers are assumed to accept anything and left to ffmpeg.
+pub fn container_accepts(output_path: &Path, encoder: &str) -> bool {
+    let extension = output_path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "mp4" | "m4v" | "m4a" | "3gp" => {
+            matches!(encoder, "aac" | "ac3" | "eac3" | "libmp3lame" | "alac" | "flac" | "libopus")
+        }
+        "webm" => matches!(encoder, "libopus" | "libvorbis"),
+        "avi" => matches!(encoder, "libmp3lame" | "ac3") || encoder.starts_with("pcm_"),
+        "mov" => !matches!(encoder, "libvorbis"),
+        "mp3" => encoder == "libmp3lame",
+        "aac" => encoder == "aac",
+        "flac" => encoder == "flac",
+        "opus" => encoder == "libopus",
+        "ogg" | "oga" => matches!(encoder, "libopus" | "libvorbis" | "flac"),
+        "wav" => encoder.starts_with("pcm_") && !encoder.ends_with("be"),
+        "aiff" | "aif" => encoder.starts_with("pcm_") && !encoder.ends_with("le"),
+        "wma" => encoder == "wmav2",
+        _ => true,
+    }
+}
+
+/// Encoder options for `output_path`. Settings win; anything unset mirrors
+/// `source`, falling back to the container's usual codec when the source
+/// codec can't be written there.
+pub fn resolve(settings: &EncodingSettings, source: Option<&AudioStreamInfo>, output_path: &Path) -> Result<AudioEncoding> {
+    let audio_only = crate::video::is_audio_file(output_path);
+    let container_default = || {
+        if audio_only {
+            audio::audio_encoder(output_path).unwrap_or("aac").to_string()
+        } else {
+            "aac".to_string()
+        }
+    };
+
+    let (encoder, from_source) = match settings.codec {
+        Some(codec) => {
+            let encoder = codec.encoder(settings.sample_format.as_deref());
+            if !container_accepts(output_path, &encoder) {
+                return Err(config_error(
+                    "audio_codec",
+                    format!("{} audio can't be written to {:?}", codec.as_str(), output_path)
+                ));
+            }
+            (encoder, false)
+        }
+        None => match source.and_then(|s| encoder_for_source(&s.codec)) {
+            Some(encoder) if container_accepts(output_path, &encoder) => (encoder, true),
+            _ => (container_default(), false),
+        },
+    };
+
+    let bitrate = match settings.bitrate {
+        Some(ref bitrate) => Some(bitrate.clone()),
+        None if is_lossless(&encoder) => None,
+        None => source.filter(|_| from_source)
+            .and_then(|s| s.bit_rate)
+            .map(|rate| format!("{}k", rate.div_ceil(1000))),
+    };
+
+    // PCM depth is part of the encoder name, and other encoders carry
+    // 24-bit audio in 32-bit samples
+    let sample_format = settings.sample_format.as_deref()
+        .filter(|_| !encoder.starts_with("pcm_"))
+        .map(|format| if format == "s24" { "s32".to_string() } else { format.to_string() });
+
+    Ok(AudioEncoding {
+        encoder,
+        bitrate,
+        sample_format,
+        sample_rate: settings.sample_rate,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(codec: &str, bit_rate: Option<u64>) -> AudioStreamInfo {
+        AudioStreamInfo {
+            sample_rate: 48000,
+            channels: 2,
+            channel_layout: Some("stereo".to_string()),
+            codec: codec.to_string(),
+            bit_rate,
+        }
+    }
+
+    #[test]
+    fn test_match_source() {
+        let settings = EncodingSettings::default();
+        assert!(settings.is_match_source());
+
+        // Same codec and bitrate as the original stream
+        let encoding = resolve(&settings, Some(&source("ac3", Some(384000))), Path::new("out.mkv")).unwrap();
+        assert_eq!(encoding.args(), vec!["-c:a", "ac3", "-b:a", "384k"]);
+
+        // Vorbis can't go in an MP4, so fall back to AAC at its default bitrate
+        let encoding = resolve(&settings, Some(&source("vorbis", Some(128000))), Path::new("out.mp4")).unwrap();
+        assert_eq!(encoding.args(), vec!["-c:a", "aac"]);
+
+        // Audio-only outputs keep the source codec when the container takes it
+        let encoding = resolve(&settings, Some(&source("alac", None)), Path::new("out.m4a")).unwrap();
+        assert_eq!(encoding.args(), vec!["-c:a", "alac"]);
+        let encoding = resolve(&settings, Some(&source("opus", Some(96000))), Path::new("out.ogg")).unwrap();
+        assert_eq!(encoding.args(), vec!["-c:a", "libopus", "-b:a", "96k"]);
+        let encoding = resolve(&settings, Some(&source("pcm_s24le", None)), Path::new("out.wav")).unwrap();
+        assert_eq!(encoding.args(), vec!["-c:a", "pcm_s24le"]);
+
+        // and otherwise follow the extension
+        let encoding = resolve(&settings, Some(&source("flac", None)), Path::new("out.mp3")).unwrap();
+        assert_eq!(encoding.encoder, "libmp3lame");
+        let encoding = resolve(&settings, Some(&source("pcm_s16be", None)), Path::new("out.wav")).unwrap();
+        assert_eq!(encoding.encoder, "pcm_s16le");
+    }
+
+    #[test]
+    fn test_presets_and_overrides() {
+        let mut settings = EncodingSettings::preset("broadcast").unwrap();
+        let encoding = resolve(&settings, None, Path::new("out.mov")).unwrap();
+        assert_eq!(encoding.args(), vec!["-c:a", "pcm_s24le", "-ar", "48000"]);
+
+        // 24-bit FLAC instead of PCM
+        settings.merge(EncodingSettings {
+            codec: Some(AudioCodec::Flac),
+            ..Default::default()
+        });
+        assert_eq!(settings.container.as_deref(), Some("mov"));
+        let encoding = resolve(&settings, None, Path::new("out.mkv")).unwrap();
+        assert_eq!(encoding.args(), vec!["-c:a", "flac", "-sample_fmt", "s32", "-ar", "48000"]);
+
+        assert!(resolve(&settings, None, Path::new("out.webm")).is_err());
+        assert!(EncodingSettings::preset("cinema").is_none());
+        assert_eq!("AC-3".parse::<AudioCodec>().unwrap(), AudioCodec::Ac3);
+    }
+}
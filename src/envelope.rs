@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+use crate::audio::AudioSegment;
+use crate::error::{config_error, BabymodeError, Result};
+
+/// Frame size used when evaluating gain expressions, so the envelope is
+/// updated every few milliseconds instead of once per decoder frame.
+const ENVELOPE_FRAME_SAMPLES: u32 = 64;
+
+/// Shape of the gain transition at the edges of a censored segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FadeCurve {
+    /// Straight line in amplitude
+    #[default]
+    Linear,
+    /// Fast initial change that settles gently
+    Logarithmic,
+    /// Quarter-sine, keeps perceived loudness constant across crossfades
+    EqualPower,
+    /// Raised cosine, eases in and out
+    SCurve,
+}
+
+impl FadeCurve {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FadeCurve::Linear => "linear",
+            FadeCurve::Logarithmic => "logarithmic",
+            FadeCurve::EqualPower => "equal_power",
+            FadeCurve::SCurve => "s_curve",
+        }
+    }
+
+    /// Level (0.0-1.0) of a fade-in after `x` (0.0-1.0) of its duration
+    pub fn level(&self, x: f64) -> f64 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            FadeCurve::Linear => x,
+            FadeCurve::Logarithmic => (1.0 + 9.0 * x).log10(),
+            FadeCurve::EqualPower => (x * std::f64::consts::FRAC_PI_2).sin(),
+            FadeCurve::SCurve => (1.0 - (x * std::f64::consts::PI).cos()) / 2.0,
+        }
+    }
+
+    /// Name of the matching `afade` curve, for fading generated audio
+    pub fn afade_name(&self) -> &'static str {
+        match self {
+            FadeCurve::Linear => "tri",
+            FadeCurve::Logarithmic => "log",
+            FadeCurve::EqualPower => "qsin",
+            FadeCurve::SCurve => "hsin",
+        }
+    }
+
+    /// FFmpeg expression equivalent of [`FadeCurve::level`] for the expression `x`
+    pub fn expression(&self, x: &str) -> String {
+        let x = format!("clip({},0,1)", x);
+        match self {
+            FadeCurve::Linear => x,
+            FadeCurve::Logarithmic => format!("log(1+9*{})/log(10)", x),
+            FadeCurve::EqualPower => format!("sin({}*PI/2)", x),
+            FadeCurve::SCurve => format!("(1-cos({}*PI))/2", x),
+        }
+    }
+}
+
+impl std::str::FromStr for FadeCurve {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "linear" => Ok(FadeCurve::Linear),
+            "log" | "logarithmic" => Ok(FadeCurve::Logarithmic),
+            "equal_power" => Ok(FadeCurve::EqualPower),
+            "s_curve" | "scurve" => Ok(FadeCurve::SCurve),
+            _ => Err(config_error(
+                "fade_curve",
+                format!("Invalid fade curve '{}'. Valid options: linear, logarithmic, equal_power, s_curve", s)
+            )),
+        }
+    }
+}
+
+/// Attenuation window applied to the programme audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duck {
+    pub start: f64,
+    pub end: f64,
+    /// Gain held in the middle of the window (0.0 = silence)
+    pub level: f32,
+    /// Requested fade length at each edge, in seconds
+    pub fade: f32,
+    pub curve: FadeCurve,
+}
+
+impl Duck {
+    pub fn new(segment: &AudioSegment, level: f32, fade: f32, curve: FadeCurve) -> Self {
+        Self {
+            start: segment.start_time,
+            end: segment.end_time,
+            level,
+            fade,
+            curve,
+        }
+    }
+
+    /// Fade length actually used, limited to half the window so the
+    /// fade-out and fade-in never cross
+    pub fn effective_fade(&self) -> f64 {
+        (self.fade.max(0.0) as f64).min((self.end - self.start) / 2.0)
+    }
+
+    /// Gain of this window at time `t`, 1.0 outside of it
+    pub fn gain_at(&self, t: f64) -> f64 {
+        if t < self.start || t > self.end {
+            return 1.0;
+        }
+        let fade = self.effective_fade();
+        let original = if fade <= 0.0 {
+            0.0
+        } else if t < self.start + fade {
+            self.curve.level((self.start + fade - t) / fade)
+        } else if t > self.end - fade {
+            self.curve.level((t - (self.end - fade)) / fade)
+        } else {
+            0.0
+        };
+        self.level as f64 + (1.0 - self.level as f64) * original
+    }
+
+    /// FFmpeg gain expression over `t`, valid inside the window
+    fn gain_expression(&self) -> String {
+        let fade = self.effective_fade();
+        if fade <= 0.0 {
+            return format!("{}", self.level);
+        }
+        let fade_in_end = self.start + fade;
+        let fade_out_start = self.end - fade;
+        let original = format!(
+            "if(lt(t,{:.4}),{},if(gt(t,{:.4}),{},0))",
+            fade_in_end,
+            self.curve.expression(&format!("({:.4}-t)/{:.4}", fade_in_end, fade)),
+            fade_out_start,
+            self.curve.expression(&format!("(t-{:.4})/{:.4}", fade_out_start, fade)),
+        );
+        format!("{}+{}*{}", self.level, 1.0 - self.level, original)
+    }
+}
+
+/// Build duck windows with the same level and fade for every segment
+pub fn ducks_for_segments(segments: &[AudioSegment], level: f32, fade: f32, curve: FadeCurve) -> Vec<Duck> {
+    segments.iter()
+        .map(|segment| Duck::new(segment, level, fade, curve))
+        .collect()
+}
+
+/// Sort windows and merge any that overlap or touch, so that the resulting
+/// envelope never jumps back up to full volume between them
+pub fn normalize_ducks(ducks: &[Duck]) -> Vec<Duck> {
+    let mut sorted: Vec<Duck> = ducks.iter()
+        .filter(|d| d.end > d.start)
+        .cloned()
+        .collect();
+    sorted.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut merged: Vec<Duck> = Vec::with_capacity(sorted.len());
+    for duck in sorted {
+        match merged.last_mut() {
+            Some(last) if duck.start <= last.end => {
+                last.end = last.end.max(duck.end);
+                last.level = last.level.min(duck.level);
+                last.fade = last.fade.min(duck.fade);
+            }
+            _ => merged.push(duck),
+        }
+    }
+    merged
+}
+
+/// Combined gain of all windows at time `t`
+pub fn gain_at(ducks: &[Duck], t: f64) -> f64 {
+    normalize_ducks(ducks).iter()
+        .map(|d| d.gain_at(t))
+        .fold(1.0, f64::min)
+}
+
+/// Build an audio filter chain that applies the envelope described by `ducks`
+pub fn volume_filter(ducks: &[Duck]) -> String {
+    let ducks = normalize_ducks(ducks);
+    if ducks.is_empty() {
+        return "anull".to_string();
+    }
+
+    let mut filters = vec![format!("asetnsamples=n={}:p=0", ENVELOPE_FRAME_SAMPLES)];
+    for duck in &ducks {
+        filters.push(format!(
+            "volume=enable='between(t,{:.4},{:.4})':volume='{}':eval=frame",
+            duck.start, duck.end, duck.gain_expression()
+        ));
+    }
+    filters.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn duck(start: f64, end: f64, level: f32, fade: f32, curve: FadeCurve) -> Duck {
+        Duck::new(&AudioSegment::new(start, end), level, fade, curve)
+    }
+
+    #[test]
+    fn test_curves_reach_endpoints() {
+        for curve in [FadeCurve::Linear, FadeCurve::Logarithmic, FadeCurve::EqualPower, FadeCurve::SCurve] {
+            assert!(curve.level(0.0).abs() < 1e-9, "{:?}", curve);
+            assert!((curve.level(1.0) - 1.0).abs() < 1e-9, "{:?}", curve);
+            assert!(curve.level(0.25) < curve.level(0.75), "{:?}", curve);
+        }
+        assert!((FadeCurve::SCurve.level(0.5) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_duck_gain_shape() {
+        let d = duck(1.0, 2.0, 0.2, 0.2, FadeCurve::Linear);
+        assert_eq!(d.gain_at(0.5), 1.0);
+        assert!((d.gain_at(1.0) - 1.0).abs() < 1e-6);
+        assert!((d.gain_at(1.1) - 0.6).abs() < 1e-6);
+        assert!((d.gain_at(1.5) - 0.2).abs() < 1e-6);
+        assert!((d.gain_at(1.9) - 0.6).abs() < 1e-6);
+        assert!((d.gain_at(2.0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fade_clamped_for_short_segments() {
+        let d = duck(1.0, 1.2, 0.0, 0.5, FadeCurve::EqualPower);
+        assert!((d.effective_fade() - 0.1).abs() < 1e-6);
+        // Fully attenuated exactly at the midpoint, no discontinuity
+        assert!(d.gain_at(1.1).abs() < 1e-6);
+        assert!(d.gain_at(1.05) > 0.0 && d.gain_at(1.05) < 1.0);
+    }
+
+    #[test]
+    fn test_overlapping_ducks_are_merged() {
+        let ducks = vec![
+            duck(3.0, 4.0, 0.5, 0.1, FadeCurve::Linear),
+            duck(1.0, 2.0, 0.0, 0.2, FadeCurve::Linear),
+            duck(1.8, 3.0, 0.3, 0.2, FadeCurve::Linear),
+        ];
+        let merged = normalize_ducks(&ducks);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].start, 1.0);
+        assert_eq!(merged[0].end, 4.0);
+        assert_eq!(merged[0].level, 0.0);
+        // No bump back to full volume where the inputs overlapped
+        assert!(gain_at(&ducks, 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_volume_filter() {
+        assert_eq!(volume_filter(&[]), "anull");
+
+        let filter = volume_filter(&[duck(1.0, 2.0, 0.0, 0.0, FadeCurve::Linear)]);
+        assert!(filter.starts_with("asetnsamples"));
+        assert!(filter.contains("between(t,1.0000,2.0000)"));
+        assert!(filter.contains("volume='0'"));
+
+        let filter = volume_filter(&[duck(1.0, 2.0, 0.1, 0.2, FadeCurve::SCurve)]);
+        assert!(filter.contains("cos("));
+        assert!(filter.contains("eval=frame"));
+    }
+
+    #[test]
+    fn test_fade_curve_parsing() {
+        assert_eq!("equal-power".parse::<FadeCurve>().unwrap(), FadeCurve::EqualPower);
+        assert_eq!("LOG".parse::<FadeCurve>().unwrap(), FadeCurve::Logarithmic);
+        assert!("cubic".parse::<FadeCurve>().is_err());
+    }
+}
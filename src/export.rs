@@ -0,0 +1,443 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::audio;
+use crate::error::{config_error, BabymodeError};
+use crate::labels;
+use crate::plugins::StrategyAssignment;
+use crate::video;
+use crate::whisper::WordDetection;
+
+/// Frame rate assumed for sources without video
+const AUDIO_FRAME_RATE: f64 = 25.0;
+
+/// Volume, in dB, that the MLT mute automation drops to
+const MUTE_LEVEL: i32 = -70;
+
+/// Edit decision list formats the censor segments can be exported as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// CMX3600 EDL, one audio event per segment
+    Edl,
+    /// Final Cut Pro XML, with a marker per segment
+    Fcpxml,
+    /// MLT XML for Kdenlive and Shotcut, with markers and mute automation
+    Mlt,
+    /// Audacity label track, a label per segment
+    Audacity,
+    /// Reaper region list CSV, a region per detected word
+    Reaper,
+    /// Adobe Audition marker list, a marker per detected word
+    Audition,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Edl => "edl",
+            ExportFormat::Fcpxml => "fcpxml",
+            ExportFormat::Mlt => "mlt",
+            ExportFormat::Audacity => "labels.txt",
+            ExportFormat::Reaper => "regions.csv",
+            ExportFormat::Audition => "markers.csv",
+        }
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "edl" | "cmx3600" => Ok(ExportFormat::Edl),
+            "fcpxml" | "fcp" => Ok(ExportFormat::Fcpxml),
+            "mlt" | "kdenlive" | "shotcut" => Ok(ExportFormat::Mlt),
+            "audacity" | "labels" => Ok(ExportFormat::Audacity),
+            "reaper" => Ok(ExportFormat::Reaper),
+            "audition" => Ok(ExportFormat::Audition),
+            _ => Err(config_error(
+                "export",
+                format!("Invalid export format '{}'. Valid options: edl, fcpxml, mlt, audacity, reaper, audition", s)
+            )),
+        }
+    }
+}
+
+/// Exact frame rate as a fraction, e.g. 30000/1001 for 29.97
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRate {
+    pub num: u64,
+    pub den: u64,
+}
+
+impl FrameRate {
+    /// Recover the exact rate from a probed one, recognising the NTSC
+    /// rates (23.976, 29.97, 59.94...)
+    pub fn from_fps(fps: f64) -> Self {
+        let nominal = fps.round().max(1.0);
+        if (fps - nominal).abs() < 0.005 {
+            FrameRate { num: nominal as u64, den: 1 }
+        } else if (nominal * 1000.0 / 1001.0 - fps).abs() < 0.005 {
+            FrameRate { num: nominal as u64 * 1000, den: 1001 }
+        } else {
+            FrameRate { num: (fps * 1000.0).round() as u64, den: 1000 }
+        }
+    }
+
+    /// Frames counted per timecode second
+    pub fn nominal(&self) -> u64 {
+        (self.num as f64 / self.den as f64).round().max(1.0) as u64
+    }
+
+    /// Whether timecode drops frame numbers to keep up with the clock, as
+    /// 29.97 and 59.94 do
+    pub fn drop_frame(&self) -> bool {
+        self.den == 1001 && self.nominal().is_multiple_of(30)
+    }
+
+    /// Nearest frame to `secs`
+    pub fn frames(&self, secs: f64) -> u64 {
+        (secs.max(0.0) * self.num as f64 / self.den as f64).round() as u64
+    }
+
+    /// SMPTE timecode of frame `frame`, `;` before the frames when drop-frame
+    pub fn timecode(&self, frame: u64) -> String {
+        let nominal = self.nominal();
+        let mut frame = frame;
+        let separator = if self.drop_frame() {
+            // Frame numbers 0 and 1 (0-3 at 59.94) are skipped each minute,
+            // except every tenth minute
+            let dropped = nominal / 15;
+            let per_ten_minutes = nominal * 600 - dropped * 9;
+            let per_minute = nominal * 60 - dropped;
+            let tens = frame / per_ten_minutes;
+            let rest = frame % per_ten_minutes;
+            frame += dropped * 9 * tens;
+            if rest > dropped {
+                frame += dropped * ((rest - dropped) / per_minute);
+            }
+            ';'
+        } else {
+            ':'
+        };
+        let seconds = frame / nominal;
+        format!(
+            "{:02}:{:02}:{:02}{}{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            separator,
+            frame % nominal
+        )
+    }
+
+    /// FCPXML rational time of frame `frame`
+    fn rational(&self, frame: u64) -> String {
+        if frame == 0 {
+            "0s".to_string()
+        } else {
+            format!("{}/{}s", frame * self.den, self.num)
+        }
+    }
+}
+
+/// The file the segments refer to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportSource {
+    pub path: PathBuf,
+    pub duration: f64,
+    pub rate: FrameRate,
+    /// Frame size, `None` for audio
+    pub size: Option<(u32, u32)>,
+}
+
+impl ExportSource {
+    /// Probe `path` for its duration, frame rate and size. Audio-only files
+    /// get a 25 fps timebase.
+    pub async fn probe(path: &Path) -> Result<Self> {
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        match video::get_video_metadata(&path).await {
+            Ok(metadata) => Ok(ExportSource {
+                rate: FrameRate::from_fps(metadata.fps),
+                duration: metadata.duration,
+                size: Some((metadata.width, metadata.height)),
+                path,
+            }),
+            Err(_) => {
+                warn!("No video in {:?}, exporting at {} fps", path, AUDIO_FRAME_RATE);
+                Ok(ExportSource {
+                    duration: audio::get_audio_duration(&path).await?,
+                    rate: FrameRate::from_fps(AUDIO_FRAME_RATE),
+                    size: None,
+                    path,
+                })
+            }
+        }
+    }
+
+    fn name(&self) -> String {
+        self.path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+    }
+}
+
+/// A censored stretch and what was said in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportSegment {
+    pub start: f64,
+    pub end: f64,
+    pub strategy: String,
+    /// Censored words inside the segment, in order
+    pub words: Vec<String>,
+}
+
+impl ExportSegment {
+    fn label(&self) -> String {
+        format!("Censor {} ({})", self.words.join(", "), self.strategy)
+    }
+}
+
+/// The merged and padded segments of every assignment, labelled with the
+/// detections they cover, in time order
+pub fn export_segments(assignments: &[StrategyAssignment], detections: &[WordDetection]) -> Vec<ExportSegment> {
+    let mut segments: Vec<ExportSegment> = assignments.iter()
+        .flat_map(|assignment| assignment.segments.iter().map(move |segment| (assignment, segment)))
+        .map(|(assignment, segment)| ExportSegment {
+            start: segment.start_time,
+            end: segment.end_time,
+            strategy: assignment.strategy.clone(),
+            words: detections.iter()
+                .filter(|d| d.start_time < segment.end_time && d.end_time > segment.start_time)
+                .map(|d| d.word.clone())
+                .collect(),
+        })
+        .collect();
+    segments.sort_by(|a, b| a.start.total_cmp(&b.start));
+    segments
+}
+
+/// Escape text for an XML attribute or element
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// `file://` URL of `path`, with each path segment percent-encoded
+fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let segments: Vec<String> = path.split('/')
+        .map(|segment| segment.bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~'
+                | b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'=' | b':' | b'@' => {
+                    (b as char).to_string()
+                }
+                _ => format!("%{:02X}", b),
+            })
+            .collect())
+        .collect();
+    format!("file://{}", segments.join("/"))
+}
+
+/// CMX3600 EDL with an audio event per segment, record times matching the
+/// source
+pub fn render_edl(segments: &[ExportSegment], source: &ExportSource) -> String {
+    let rate = source.rate;
+    let mut edl = format!(
+        "TITLE: {}\nFCM: {}\n\n",
+        source.name(),
+        if rate.drop_frame() { "DROP FRAME" } else { "NON-DROP FRAME" }
+    );
+    for (i, segment) in segments.iter().enumerate() {
+        let start = rate.timecode(rate.frames(segment.start));
+        let end = rate.timecode(rate.frames(segment.end));
+        edl.push_str(&format!(
+            "{:03}  AX       AA    C        {} {} {} {}\n",
+            i + 1, start, end, start, end
+        ));
+        edl.push_str(&format!("* FROM CLIP NAME: {}\n", source.path.file_name().unwrap_or_default().to_string_lossy()));
+        edl.push_str(&format!("* COMMENT: {}\n\n", segment.label()));
+    }
+    edl
+}
+
+/// FCPXML 1.9 project holding the whole source as one clip, with a marker
+/// per segment
+pub fn render_fcpxml(segments: &[ExportSegment], source: &ExportSource) -> String {
+    let rate = source.rate;
+    let duration = rate.rational(rate.frames(source.duration));
+    let tc_format = if rate.drop_frame() { "DF" } else { "NDF" };
+    let name = xml_escape(&source.name());
+    let size = source.size
+        .map(|(width, height)| format!(" width=\"{}\" height=\"{}\"", width, height))
+        .unwrap_or_default();
+    let src = xml_escape(&file_url(&source.path));
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE fcpxml>\n<fcpxml version=\"1.9\">\n");
+    xml.push_str("  <resources>\n");
+    xml.push_str(&format!("    <format id=\"r1\" frameDuration=\"{}\"{}/>\n", rate.rational(1), size));
+    xml.push_str(&format!(
+        "    <asset id=\"r2\" name=\"{}\" start=\"0s\" duration=\"{}\" hasVideo=\"{}\" hasAudio=\"1\" format=\"r1\">\n",
+        name, duration, if source.size.is_some() { 1 } else { 0 }
+    ));
+    xml.push_str(&format!("      <media-rep kind=\"original-media\" src=\"{}\"/>\n", src));
+    xml.push_str("    </asset>\n  </resources>\n");
+    xml.push_str("  <library>\n    <event name=\"babymode\">\n");
+    xml.push_str(&format!("      <project name=\"{} censored\">\n", name));
+    xml.push_str(&format!(
+        "        <sequence format=\"r1\" duration=\"{}\" tcStart=\"0s\" tcFormat=\"{}\">\n          <spine>\n",
+        duration, tc_format
+    ));
+    xml.push_str(&format!(
+        "            <asset-clip ref=\"r2\" name=\"{}\" offset=\"0s\" start=\"0s\" duration=\"{}\" tcFormat=\"{}\">\n",
+        name, duration, tc_format
+    ));
+    for segment in segments {
+        let start = rate.frames(segment.start);
+        let length = rate.frames(segment.end).saturating_sub(start).max(1);
+        xml.push_str(&format!(
+            "              <marker start=\"{}\" duration=\"{}\" value=\"{}\"/>\n",
+            rate.rational(start), rate.rational(length), xml_escape(&segment.label())
+        ));
+    }
+    xml.push_str("            </asset-clip>\n          </spine>\n        </sequence>\n      </project>\n");
+    xml.push_str("    </event>\n  </library>\n</fcpxml>\n");
+    xml
+}
+
+/// MLT XML playlist of the whole source with a marker per segment and a
+/// volume filter keyframed to mute each one
+pub fn render_mlt(segments: &[ExportSegment], source: &ExportSource) -> String {
+    let rate = source.rate;
+    let last = rate.frames(source.duration).saturating_sub(1);
+    let (width, height) = source.size.unwrap_or((1920, 1080));
+    let markers: Vec<serde_json::Value> = segments.iter()
+        .map(|segment| serde_json::json!({
+            "pos": rate.frames(segment.start),
+            "comment": segment.label(),
+            "type": 0,
+        }))
+        .collect();
+    let mut levels = vec!["0=0".to_string()];
+    for segment in segments {
+        levels.push(format!("{}|={}", rate.frames(segment.start), MUTE_LEVEL));
+        levels.push(format!("{}|=0", rate.frames(segment.end)));
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<mlt LC_NUMERIC=\"C\" producer=\"main\">\n");
+    xml.push_str(&format!(
+        "  <profile frame_rate_num=\"{}\" frame_rate_den=\"{}\" width=\"{}\" height=\"{}\" progressive=\"1\"/>\n",
+        rate.num, rate.den, width, height
+    ));
+    xml.push_str(&format!("  <producer id=\"source\" in=\"0\" out=\"{}\">\n", last));
+    xml.push_str(&format!("    <property name=\"resource\">{}</property>\n", xml_escape(&source.path.display().to_string())));
+    xml.push_str(&format!(
+        "    <property name=\"kdenlive:markers\">{}</property>\n  </producer>\n",
+        xml_escape(&serde_json::Value::Array(markers).to_string())
+    ));
+    xml.push_str("  <playlist id=\"main\">\n");
+    xml.push_str(&format!("    <entry producer=\"source\" in=\"0\" out=\"{}\">\n", last));
+    xml.push_str("      <filter>\n        <property name=\"mlt_service\">volume</property>\n");
+    xml.push_str(&format!("        <property name=\"level\">{}</property>\n", levels.join(";")));
+    xml.push_str("      </filter>\n    </entry>\n  </playlist>\n</mlt>\n");
+    xml
+}
+
+/// `segments` in `format`. The label formats list `detections`, word by
+/// word, instead.
+pub fn render(
+    format: ExportFormat,
+    segments: &[ExportSegment],
+    detections: &[WordDetection],
+    source: &ExportSource,
+) -> String {
+    match format {
+        ExportFormat::Edl => render_edl(segments, source),
+        ExportFormat::Fcpxml => render_fcpxml(segments, source),
+        ExportFormat::Mlt => render_mlt(segments, source),
+        ExportFormat::Audacity => labels::render_audacity(segments),
+        ExportFormat::Reaper => labels::render_reaper(detections),
+        ExportFormat::Audition => labels::render_audition(detections),
+    }
+}
+
+/// Write an export next to `output_path` for each of `formats`
+pub async fn write_exports(
+    segments: &[ExportSegment],
+    detections: &[WordDetection],
+    source: &ExportSource,
+    output_path: &Path,
+    formats: &[ExportFormat],
+) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for &format in formats {
+        let path = output_path.with_extension(format.extension());
+        tokio::fs::write(&path, render(format, segments, detections, source)).await
+            .with_context(|| format!("Failed to write export to {:?}", path))?;
+        info!("Wrote {} censor segments to {:?}", segments.len(), path);
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timecode() {
+        let ntsc = FrameRate::from_fps(29.97002997);
+        assert_eq!(ntsc, FrameRate { num: 30000, den: 1001 });
+        assert!(ntsc.drop_frame());
+        assert_eq!(ntsc.timecode(1799), "00:00:59;29");
+        assert_eq!(ntsc.timecode(1800), "00:01:00;02");
+        assert_eq!(ntsc.timecode(17982), "00:10:00;00");
+        assert_eq!(ntsc.timecode(ntsc.frames(3600.0)), "01:00:00;00");
+
+        let film = FrameRate::from_fps(23.976);
+        assert_eq!(film, FrameRate { num: 24000, den: 1001 });
+        assert!(!film.drop_frame());
+        assert_eq!(film.timecode(24), "00:00:01:00");
+
+        let pal = FrameRate::from_fps(25.0);
+        assert_eq!(pal.timecode(pal.frames(61.4)), "00:01:01:10");
+        assert_eq!(pal.rational(pal.frames(2.0)), "50/25s");
+    }
+
+    #[test]
+    fn test_render() {
+        let source = ExportSource {
+            path: PathBuf::from("/media/film & co.mp4"),
+            duration: 10.0,
+            rate: FrameRate::from_fps(25.0),
+            size: Some((1920, 1080)),
+        };
+        let segments = vec![ExportSegment {
+            start: 1.0,
+            end: 1.5,
+            strategy: "beep".to_string(),
+            words: vec!["damn".to_string()],
+        }];
+
+        let edl = render_edl(&segments, &source);
+        assert!(edl.starts_with("TITLE: film & co\nFCM: NON-DROP FRAME\n"));
+        assert!(edl.contains("001  AX       AA    C        00:00:01:00 00:00:01:13 00:00:01:00 00:00:01:13\n"));
+        assert!(edl.contains("* COMMENT: Censor damn (beep)\n"));
+
+        let fcpxml = render_fcpxml(&segments, &source);
+        assert!(fcpxml.contains("<format id=\"r1\" frameDuration=\"1/25s\" width=\"1920\" height=\"1080\"/>"));
+        assert!(fcpxml.contains("src=\"file:///media/film%20&amp;%20co.mp4\""));
+        assert_eq!(file_url(Path::new("/media/café #2.mov")), "file:///media/caf%C3%A9%20%232.mov");
+        assert!(fcpxml.contains("<marker start=\"25/25s\" duration=\"13/25s\" value=\"Censor damn (beep)\"/>"));
+
+        let mlt = render_mlt(&segments, &source);
+        assert!(mlt.contains("<property name=\"level\">0=0;25|=-70;38|=0</property>"));
+        assert!(mlt.contains("&quot;pos&quot;:25"));
+        assert!("kdenlive".parse::<ExportFormat>().unwrap() == ExportFormat::Mlt);
+    }
+}
@@ -0,0 +1,387 @@
+//! Censoring strategies implemented by external executables.
+//!
+//! Any executable in a plugins directory is treated as a strategy and talks
+//! to babymode over a small JSON protocol. Loading a plugin runs it, so only
+//! the configured directory or the user's config directory is searched,
+//! never the working directory.
+//!
+//! * `<plugin> --describe` prints a description to stdout:
+//!
+//!   ```json
+//!   {"protocol": 1, "name": "robot", "description": "Vocoder effect",
+//!    "parameters": [{"name": "depth", "type": "number", "min": 0, "max": 1,
+//!                    "default": 0.5, "description": "Effect depth"}]}
+//!   ```
+//!
+//!   Parameter types are `number` and `integer` (with `min`/`max`), `bool`,
+//!   `choice` (with `options`, and optionally `aliases` mapping other
+//!   spellings to an option) and `text`, as in [`ParamSpec`].
+//!
+//! * `<plugin> --apply` reads a request from stdin and writes the censored
+//!   audio to `output`, at the sample rate and channel layout of `input`:
+//!
+//!   ```json
+//!   {"protocol": 1, "input": "/tmp/in.wav", "output": "/tmp/out.wav",
+//!    "segments": [{"start": 1.2, "end": 1.6}], "params": {"depth": 0.5},
+//!    "volume": 0.1, "fade_duration": 0.2, "fade_curve": "linear"}
+//!   ```
+//!
+//!   `params` has already been checked against the declared schema, with
+//!   defaults filled in. A non-zero exit status fails the run and stderr is
+//!   included in the error.
+
+use crate::audio::AudioSegment;
+use crate::envelope::FadeCurve;
+use crate::error::{BabymodeError, Result};
+use crate::plugins::{CensoringConfig, CensoringStrategy, ParamSpec};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Version of the JSON protocol spoken with plugins
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// What a plugin reports about itself with `--describe`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginDescription {
+    #[serde(default = "default_protocol")]
+    pub protocol: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub parameters: Vec<ParamSpec>,
+}
+
+fn default_protocol() -> u32 {
+    PROTOCOL_VERSION
+}
+
+/// Segment as sent to a plugin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginSegment {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Request sent to a plugin on stdin with `--apply`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginRequest {
+    pub protocol: u32,
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub segments: Vec<PluginSegment>,
+    pub params: HashMap<String, Value>,
+    pub volume: f32,
+    pub fade_duration: f32,
+    pub fade_curve: FadeCurve,
+}
+
+impl PluginRequest {
+    pub fn new(input_path: &Path, output_path: &Path, segments: &[AudioSegment], config: &CensoringConfig) -> Self {
+        Self {
+            protocol: PROTOCOL_VERSION,
+            input: input_path.to_path_buf(),
+            output: output_path.to_path_buf(),
+            segments: segments.iter()
+                .map(|s| PluginSegment { start: s.start_time, end: s.end_time })
+                .collect(),
+            params: config.custom_params.clone(),
+            volume: config.volume,
+            fade_duration: config.fade_duration,
+            fade_curve: config.fade_curve,
+        }
+    }
+}
+
+/// Strategy backed by an external executable
+pub struct ExternalStrategy {
+    program: PathBuf,
+    description: PluginDescription,
+}
+
+impl ExternalStrategy {
+    /// Ask `program` to describe itself
+    pub async fn load(program: &Path) -> Result<Self> {
+        let output = Command::new(program)
+            .arg("--describe")
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|e| BabymodeError::Processing {
+                message: format!("Failed to run plugin {}: {}", program.display(), e),
+            })?;
+
+        if !output.status.success() {
+            return Err(BabymodeError::Processing {
+                message: format!(
+                    "Plugin {} failed to describe itself: {}",
+                    program.display(),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            });
+        }
+
+        let description = parse_description(&output.stdout).map_err(|message| BabymodeError::Processing {
+            message: format!("Plugin {}: {}", program.display(), message),
+        })?;
+
+        Ok(Self {
+            program: program.to_path_buf(),
+            description,
+        })
+    }
+
+    pub fn program(&self) -> &Path {
+        &self.program
+    }
+}
+
+/// Parse and check the output of `--describe`
+fn parse_description(stdout: &[u8]) -> std::result::Result<PluginDescription, String> {
+    let description: PluginDescription = serde_json::from_slice(stdout)
+        .map_err(|e| format!("invalid description: {}", e))?;
+
+    if description.protocol > PROTOCOL_VERSION {
+        return Err(format!(
+            "needs protocol version {}, this build supports {}",
+            description.protocol, PROTOCOL_VERSION
+        ));
+    }
+    if description.name.is_empty() || description.name.contains(char::is_whitespace) {
+        return Err(format!("invalid strategy name '{}'", description.name));
+    }
+    Ok(description)
+}
+
+#[async_trait]
+impl CensoringStrategy for ExternalStrategy {
+    fn name(&self) -> &str {
+        &self.description.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description.description
+    }
+
+    fn parameters(&self) -> Vec<ParamSpec> {
+        self.description.parameters.clone()
+    }
+
+    async fn apply_censoring(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<()> {
+        let request = PluginRequest::new(input_path, output_path, segments, config);
+        let payload = serde_json::to_vec(&request).map_err(|e| BabymodeError::Processing {
+            message: format!("Failed to encode plugin request: {}", e),
+        })?;
+        debug!("Running plugin {} with {} segments", self.program.display(), segments.len());
+
+        let mut child = Command::new(&self.program)
+            .arg("--apply")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| BabymodeError::Processing {
+                message: format!("Failed to run plugin {}: {}", self.program.display(), e),
+            })?;
+
+        // Feed the request from its own task so a plugin that writes output
+        // before it has read all of stdin cannot fill a pipe and deadlock
+        let writer = child.stdin.take().map(|mut stdin| {
+            tokio::spawn(async move {
+                let result = stdin.write_all(&payload).await;
+                drop(stdin);
+                result
+            })
+        });
+
+        let output = child.wait_with_output().await.map_err(|e| BabymodeError::Processing {
+            message: format!("Plugin {} did not finish: {}", self.program.display(), e),
+        })?;
+
+        if let Some(writer) = writer {
+            // A plugin that exits without reading the request is reported below
+            match writer.await {
+                Ok(Err(e)) => warn!("Plugin {} did not read its request: {}", self.name(), e),
+                Err(e) => warn!("Plugin {} request writer failed: {}", self.name(), e),
+                Ok(Ok(())) => {}
+            }
+        }
+
+        if !output.status.success() {
+            return Err(BabymodeError::Processing {
+                message: format!(
+                    "Plugin strategy '{}' failed: {}",
+                    self.name(),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            });
+        }
+        if !output_path.exists() {
+            return Err(BabymodeError::Processing {
+                message: format!("Plugin strategy '{}' did not write {}", self.name(), output_path.display()),
+            });
+        }
+
+        info!("Applied plugin strategy '{}' to {} segments", self.name(), segments.len());
+        Ok(())
+    }
+}
+
+/// Directory searched for plugins when none is configured
+/// (`~/.config/babymode/plugins` on Linux)
+pub fn default_plugin_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("babymode").join("plugins"))
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata().map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0).unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// Load every plugin in `dir`. Plugins that fail to describe themselves are
+/// skipped with a warning, and a missing directory yields no plugins.
+pub async fn discover(dir: &Path) -> Vec<ExternalStrategy> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut programs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| !p.file_name().and_then(|n| n.to_str()).unwrap_or(".").starts_with('.'))
+        .filter(|p| is_executable(p))
+        .collect();
+    programs.sort();
+
+    let mut plugins = Vec::with_capacity(programs.len());
+    for program in programs {
+        match ExternalStrategy::load(&program).await {
+            Ok(plugin) => {
+                debug!("Found plugin strategy '{}' at {}", plugin.name(), program.display());
+                plugins.push(plugin);
+            }
+            Err(e) => warn!("Skipping plugin: {}", e),
+        }
+    }
+    plugins
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_description() {
+        let description = parse_description(br#"{
+            "name": "robot",
+            "description": "Vocoder effect",
+            "parameters": [
+                {"name": "depth", "type": "number", "min": 0, "max": 1, "default": 0.5},
+                {"name": "voice", "type": "choice", "options": ["low", "high"]}
+            ]
+        }"#).unwrap();
+        assert_eq!(description.protocol, PROTOCOL_VERSION);
+        assert_eq!(description.parameters.len(), 2);
+        assert_eq!(description.parameters[0].summary(), "number 0-1, default 0.5");
+
+        assert!(parse_description(br#"{"protocol": 99, "name": "robot"}"#).is_err());
+        assert!(parse_description(br#"{"name": "two words"}"#).is_err());
+        assert!(parse_description(b"not json").is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_plugin_round_trip() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let script = temp_dir.path().join("echo-plugin");
+        std::fs::write(&script, r#"#!/bin/sh
+if [ "$1" = "--describe" ]; then
+  echo '{"name": "echo", "description": "Copies the request", "parameters": [{"name": "gain", "type": "number", "min": 0, "max": 2}]}'
+  exit 0
+fi
+request=$(cat)
+output=$(printf '%s' "$request" | sed -n 's/.*"output":"\([^"]*\)".*/\1/p')
+printf '%s' "$request" > "$output"
+"#).unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(temp_dir.path().join("README"), "not a plugin").unwrap();
+
+        let plugins = discover(temp_dir.path()).await;
+        assert_eq!(plugins.len(), 1);
+        let plugin = &plugins[0];
+        assert_eq!(plugin.name(), "echo");
+
+        let output_path = temp_dir.path().join("out.json");
+        let mut config = CensoringConfig::default();
+        config.custom_params.insert("gain".to_string(), 1.5.into());
+        plugin.apply_censoring(
+            Path::new("/tmp/in.wav"),
+            &output_path,
+            &[AudioSegment::new(1.0, 1.5)],
+            &config,
+        ).await.unwrap();
+
+        let request: PluginRequest = serde_json::from_str(&std::fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert_eq!(request.segments.len(), 1);
+        assert_eq!(request.segments[0].end, 1.5);
+        assert_eq!(request.params["gain"], 1.5);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_plugin_that_streams_output_does_not_deadlock() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let script = temp_dir.path().join("chatty-plugin");
+        // Writes far more than a pipe buffer to stdout and stderr before
+        // reading a request that is itself larger than a pipe buffer
+        std::fs::write(&script, r#"#!/bin/sh
+if [ "$1" = "--describe" ]; then
+  echo '{"name": "chatty"}'
+  exit 0
+fi
+head -c 262144 /dev/zero
+head -c 262144 /dev/zero >&2
+request=$(cat)
+output=$(printf '%s' "$request" | sed -n 's/.*"output":"\([^"]*\)".*/\1/p')
+: > "$output"
+"#).unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let plugin = ExternalStrategy::load(&script).await.unwrap();
+        let segments: Vec<AudioSegment> = (0..5000)
+            .map(|i| AudioSegment::new(i as f64, i as f64 + 0.5))
+            .collect();
+        let output_path = temp_dir.path().join("out.wav");
+        let config = CensoringConfig::default();
+        let run = plugin.apply_censoring(Path::new("/tmp/in.wav"), &output_path, &segments, &config);
+        tokio::time::timeout(std::time::Duration::from_secs(30), run)
+            .await
+            .expect("plugin deadlocked")
+            .unwrap();
+        assert!(output_path.exists());
+    }
+}
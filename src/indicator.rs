@@ -0,0 +1,313 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::error::{config_error, BabymodeError, Result};
+
+/// Distance from the frame edge, in pixels
+const EDGE_MARGIN: u32 = 24;
+
+/// What is drawn while audio is censored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndicatorStyle {
+    /// An image, scaled to `size` pixels high
+    Icon,
+    /// Text such as `[bleep]`, `size` pixels high
+    Caption,
+    /// A full-width bar, `size` pixels thick
+    Bar,
+}
+
+impl IndicatorStyle {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndicatorStyle::Icon => "icon",
+            IndicatorStyle::Caption => "caption",
+            IndicatorStyle::Bar => "bar",
+        }
+    }
+
+    fn default_size(&self) -> u32 {
+        match self {
+            IndicatorStyle::Icon => 64,
+            IndicatorStyle::Caption => 36,
+            IndicatorStyle::Bar => 12,
+        }
+    }
+}
+
+impl std::str::FromStr for IndicatorStyle {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "icon" => Ok(IndicatorStyle::Icon),
+            "caption" | "text" => Ok(IndicatorStyle::Caption),
+            "bar" => Ok(IndicatorStyle::Bar),
+            _ => Err(config_error(
+                "indicator",
+                format!("Invalid indicator style '{}'. Valid options: icon, caption, bar", s)
+            )),
+        }
+    }
+}
+
+/// Where in the frame the indicator is drawn. Bars only use the top or
+/// bottom edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndicatorPosition {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Top,
+    Bottom,
+    Center,
+}
+
+impl IndicatorPosition {
+    /// x and y expressions for an item `w` by `h` in a `W` by `H` frame
+    fn expressions(&self, w: &str, h: &str, frame_w: &str, frame_h: &str) -> (String, String) {
+        let left = EDGE_MARGIN.to_string();
+        let right = format!("{}-{}-{}", frame_w, w, EDGE_MARGIN);
+        let centre_x = format!("({}-{})/2", frame_w, w);
+        let top = EDGE_MARGIN.to_string();
+        let bottom = format!("{}-{}-{}", frame_h, h, EDGE_MARGIN);
+        let centre_y = format!("({}-{})/2", frame_h, h);
+        match self {
+            IndicatorPosition::TopLeft => (left, top),
+            IndicatorPosition::TopRight => (right, top),
+            IndicatorPosition::BottomLeft => (left, bottom),
+            IndicatorPosition::BottomRight => (right, bottom),
+            IndicatorPosition::Top => (centre_x, top),
+            IndicatorPosition::Bottom => (centre_x, bottom),
+            IndicatorPosition::Center => (centre_x, centre_y),
+        }
+    }
+
+    fn is_bottom(&self) -> bool {
+        matches!(self, IndicatorPosition::BottomLeft | IndicatorPosition::BottomRight | IndicatorPosition::Bottom)
+    }
+}
+
+impl std::str::FromStr for IndicatorPosition {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "top_left" => Ok(IndicatorPosition::TopLeft),
+            "top_right" => Ok(IndicatorPosition::TopRight),
+            "bottom_left" => Ok(IndicatorPosition::BottomLeft),
+            "bottom_right" => Ok(IndicatorPosition::BottomRight),
+            "top" => Ok(IndicatorPosition::Top),
+            "bottom" => Ok(IndicatorPosition::Bottom),
+            "center" | "centre" => Ok(IndicatorPosition::Center),
+            _ => Err(config_error(
+                "indicator_position",
+                format!(
+                    "Invalid indicator position '{}'. Valid options: top_left, top_right, bottom_left, bottom_right, top, bottom, center",
+                    s
+                )
+            )),
+        }
+    }
+}
+
+/// On-screen indicator options. No style means no indicator; anything else
+/// unset takes the style's default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndicatorSettings {
+    pub style: Option<IndicatorStyle>,
+    pub position: Option<IndicatorPosition>,
+    /// ffmpeg colour, e.g. `red`, `#FFCC00` or `white@0.8`
+    pub color: Option<String>,
+    /// Caption text, `[bleep]` by default
+    pub text: Option<String>,
+    /// Image drawn by the icon style
+    pub asset: Option<PathBuf>,
+    /// Icon or text height, or bar thickness, in pixels
+    pub size: Option<u32>,
+}
+
+impl IndicatorSettings {
+    /// Take every setting `other` has
+    pub fn merge(&mut self, other: IndicatorSettings) {
+        self.style = other.style.or(self.style);
+        self.position = other.position.or(self.position);
+        self.color = other.color.or(self.color.take());
+        self.text = other.text.or(self.text.take());
+        self.asset = other.asset.or(self.asset.take());
+        self.size = other.size.or(self.size);
+    }
+
+    /// Check the options that can be checked without the style
+    pub fn check(&self) -> Result<()> {
+        if let Some(ref color) = self.color {
+            let valid = !color.is_empty()
+                && color.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '#' | '@' | '.' | '_'));
+            if !valid {
+                return Err(config_error("indicator_color", format!("Invalid indicator colour '{}'", color)));
+            }
+        }
+        if let Some(size) = self.size {
+            if !(4..=512).contains(&size) {
+                return Err(config_error(
+                    "indicator_size",
+                    format!("Indicator size must be between 4 and 512 pixels, got {}", size)
+                ));
+            }
+        }
+        if self.text.as_deref() == Some("") {
+            return Err(config_error("indicator_text", "Indicator text cannot be empty"));
+        }
+        Ok(())
+    }
+
+    /// The indicator to draw, or `None` when no style is set
+    pub fn indicator(&self) -> Result<Option<Indicator>> {
+        let style = match self.style {
+            Some(style) => style,
+            None => return Ok(None),
+        };
+        if style == IndicatorStyle::Icon && self.asset.is_none() {
+            return Err(config_error("indicator_asset", "The icon indicator needs an image asset"));
+        }
+        Ok(Some(Indicator {
+            style,
+            position: self.position.unwrap_or_default(),
+            color: self.color.clone().unwrap_or_else(|| match style {
+                IndicatorStyle::Bar => "red@0.8".to_string(),
+                _ => "white".to_string(),
+            }),
+            text: self.text.clone().unwrap_or_else(|| "[bleep]".to_string()),
+            asset: self.asset.clone(),
+            size: self.size.unwrap_or(style.default_size()),
+        }))
+    }
+}
+
+/// Indicator with every option settled
+#[derive(Debug, Clone, PartialEq)]
+pub struct Indicator {
+    pub style: IndicatorStyle,
+    pub position: IndicatorPosition,
+    pub color: String,
+    pub text: String,
+    pub asset: Option<PathBuf>,
+    pub size: u32,
+}
+
+/// Quote `value` as a filter option inside a filter graph. The graph
+/// parser and then the option parser each strip a level of escaping.
+fn quote(value: &str) -> String {
+    let option_level = value.replace('\\', "\\\\").replace('\'', "\\'").replace(':', "\\:");
+    format!("'{}'", option_level.replace('\'', "'\\''"))
+}
+
+/// Quote caption text, which drawtext also expands `%{...}` and escapes in
+fn quote_text(text: &str) -> String {
+    quote(&text.replace('\\', "\\\\").replace('%', "\\%"))
+}
+
+impl Indicator {
+    /// Filter graph from `[input]` to `[output]` that draws the indicator
+    /// during each of `windows` overlapping `chunk_start..chunk_end`, with
+    /// times relative to the chunk
+    pub fn filter(&self, windows: &[(f64, f64)], chunk_start: f64, chunk_end: f64, input: &str, output: &str) -> String {
+        let enable = windows.iter()
+            .filter(|(start, end)| *end > chunk_start && *start < chunk_end)
+            .map(|(start, end)| format!("between(t,{:.3},{:.3})", (start - chunk_start).max(0.0), end - chunk_start))
+            .collect::<Vec<_>>()
+            .join("+");
+        if enable.is_empty() {
+            return format!("[{}]null[{}]", input, output);
+        }
+
+        match self.style {
+            IndicatorStyle::Bar => {
+                let y = if self.position.is_bottom() { format!("ih-{}", self.size) } else { "0".to_string() };
+                format!(
+                    "[{}]drawbox=x=0:y={}:w=iw:h={}:color={}:t=fill:enable='{}'[{}]",
+                    input, y, self.size, self.color, enable, output
+                )
+            }
+            IndicatorStyle::Caption => {
+                let (x, y) = self.position.expressions("tw", "th", "w", "h");
+                format!(
+                    "[{}]drawtext=text={}:fontsize={}:fontcolor={}:box=1:boxcolor=black@0.5:boxborderw=8:x={}:y={}:enable='{}'[{}]",
+                    input, quote_text(&self.text), self.size, self.color, x, y, enable, output
+                )
+            }
+            IndicatorStyle::Icon => {
+                let asset = self.asset.as_deref().unwrap_or(Path::new(""));
+                let (x, y) = self.position.expressions("overlay_w", "overlay_h", "main_w", "main_h");
+                format!(
+                    "movie={},scale=-1:{}[icon];[{}][icon]overlay=x={}:y={}:enable='{}'[{}]",
+                    quote(&asset.to_string_lossy()), self.size, input, x, y, enable, output
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings() {
+        let mut settings = IndicatorSettings {
+            style: Some(IndicatorStyle::Bar),
+            ..Default::default()
+        };
+        settings.merge(IndicatorSettings {
+            position: Some("bottom-left".parse().unwrap()),
+            ..Default::default()
+        });
+        let indicator = settings.indicator().unwrap().unwrap();
+        assert_eq!(indicator.style, IndicatorStyle::Bar);
+        assert_eq!(indicator.position, IndicatorPosition::BottomLeft);
+        assert_eq!(indicator.color, "red@0.8");
+        assert_eq!(indicator.size, 12);
+
+        assert!(IndicatorSettings::default().indicator().unwrap().is_none());
+        let icon = IndicatorSettings { style: Some(IndicatorStyle::Icon), ..Default::default() };
+        assert!(icon.indicator().is_err());
+        let bad_color = IndicatorSettings { color: Some("red;drawbox".to_string()), ..Default::default() };
+        assert!(bad_color.check().is_err());
+    }
+
+    #[test]
+    fn test_filters() {
+        let windows = [(10.0, 11.0), (12.0, 12.5), (40.0, 41.0)];
+        let caption = IndicatorSettings {
+            style: Some(IndicatorStyle::Caption),
+            text: Some("[bleep: 100%]".to_string()),
+            ..Default::default()
+        }.indicator().unwrap().unwrap();
+        assert_eq!(
+            caption.filter(&windows, 8.0, 16.0, "0:v", "v"),
+            "[0:v]drawtext=text='[bleep\\: 100\\\\%]':fontsize=36:fontcolor=white:box=1:boxcolor=black@0.5:boxborderw=8\
+             :x=w-tw-24:y=24:enable='between(t,2.000,3.000)+between(t,4.000,4.500)'[v]"
+        );
+
+        let bar = IndicatorSettings { style: Some(IndicatorStyle::Bar), ..Default::default() }.indicator().unwrap().unwrap();
+        assert_eq!(
+            bar.filter(&windows, 39.0, 45.0, "blurred", "v"),
+            "[blurred]drawbox=x=0:y=0:w=iw:h=12:color=red@0.8:t=fill:enable='between(t,1.000,2.000)'[v]"
+        );
+        assert_eq!(bar.filter(&windows, 20.0, 30.0, "0:v", "v"), "[0:v]null[v]");
+
+        let icon = IndicatorSettings {
+            style: Some(IndicatorStyle::Icon),
+            position: Some(IndicatorPosition::BottomRight),
+            asset: Some(PathBuf::from("C:/icons/mute.png")),
+            ..Default::default()
+        }.indicator().unwrap().unwrap();
+        assert!(icon.filter(&windows, 8.0, 16.0, "0:v", "v").starts_with(
+            "movie='C\\:/icons/mute.png',scale=-1:64[icon];[0:v][icon]overlay=x=main_w-overlay_w-24:y=main_h-overlay_h-24"
+        ));
+    }
+}
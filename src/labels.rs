@@ -0,0 +1,171 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::config::Config;
+use crate::export::ExportSegment;
+use crate::whisper::{self, WordDetection};
+
+/// Confidence given to labels read back from a file, which are taken as is
+const LABEL_CONFIDENCE: f64 = 1.0;
+
+/// `secs` as `m:ss.mmm`, the minutes running past 59
+fn minutes(secs: f64) -> String {
+    let millis = (secs.max(0.0) * 1000.0).round() as u64;
+    format!("{}:{:02}.{:03}", millis / 60_000, millis / 1000 % 60, millis % 1000)
+}
+
+/// Quote a CSV field if it needs it
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Audacity label track: `start\tend\twords` per merged and padded
+/// segment, so the labels read back mark exactly the audio censored
+pub fn render_audacity(segments: &[ExportSegment]) -> String {
+    segments.iter()
+        .map(|s| format!("{:.6}\t{:.6}\t{}\n", s.start, s.end, s.words.join(", ")))
+        .collect()
+}
+
+/// Reaper region list CSV, as the region/marker manager imports it
+pub fn render_reaper(detections: &[WordDetection]) -> String {
+    let mut csv = String::from("#,Name,Start,End,Length\n");
+    for (i, d) in detections.iter().enumerate() {
+        csv.push_str(&format!(
+            "R{},{},{},{},{}\n",
+            i + 1,
+            csv_field(&d.word),
+            minutes(d.start_time),
+            minutes(d.end_time),
+            minutes(d.end_time - d.start_time)
+        ));
+    }
+    csv
+}
+
+/// Adobe Audition marker list, tab-separated as Audition writes it
+pub fn render_audition(detections: &[WordDetection]) -> String {
+    let mut csv = String::from("Name\tStart\tDuration\tTime Format\tType\tDescription\n");
+    for d in detections {
+        csv.push_str(&format!(
+            "{}\t{}\t{}\tdecimal\tCue\t{}\n",
+            d.word,
+            minutes(d.start_time),
+            minutes(d.end_time - d.start_time),
+            d.category.as_deref().unwrap_or("")
+        ));
+    }
+    csv
+}
+
+/// Parse an Audacity label track into detections, one per label. Point
+/// labels and the frequency lines of spectral labels are skipped.
+pub fn parse_audacity(contents: &str, config: &Config) -> Result<Vec<WordDetection>> {
+    let mut detections = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('\\') {
+            continue;
+        }
+        let mut fields = line.splitn(3, '\t');
+        let mut time = |name: &str| -> Result<f64> {
+            fields.next()
+                .and_then(|f| f.trim().parse::<f64>().ok())
+                .with_context(|| format!("Label line {}: invalid {} time in {:?}", i + 1, name, line))
+        };
+        let start = time("start")?;
+        let end = time("end")?;
+        let text = fields.next().unwrap_or("").trim().to_string();
+        if end <= start {
+            continue;
+        }
+
+        // A segment label lists its words, classified by the first swear word
+        let word = whisper::clean_word(&text);
+        let matched = text.split(',')
+            .find_map(|part| whisper::matched_swear_word(&whisper::clean_word(part), &config.swear_words));
+        detections.push(WordDetection {
+            word,
+            start_time: start,
+            end_time: end,
+            confidence: LABEL_CONFIDENCE,
+            is_swear: true,
+            text,
+            ..Default::default()
+        }.classified(matched, config));
+    }
+    detections.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    Ok(detections)
+}
+
+/// Read an edited Audacity label file as the detections to censor
+pub async fn read_audacity(path: &Path, config: &Config) -> Result<Vec<WordDetection>> {
+    let contents = tokio::fs::read_to_string(path).await
+        .with_context(|| format!("Failed to read label file {:?}", path))?;
+    parse_audacity(&contents, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_round_trip() {
+        let config = Config::default();
+        let contents = "62.250000\t63.000000\tShit!\n\\\t100.0\t2000.0\n1.5\t1.5\tpoint\n1.000000\t1.500000\tdamn, really\n";
+        let detections = parse_audacity(contents, &config).unwrap();
+        assert_eq!(detections.len(), 2);
+        assert_eq!(detections[0].word, "damn, really");
+        assert_eq!(detections[1].word, "shit");
+        assert!(detections[1].category.is_some());
+        let segment = ExportSegment {
+            start: 62.15,
+            end: 63.1,
+            strategy: "beep".to_string(),
+            words: vec!["shit".to_string(), "damn".to_string()],
+        };
+        assert_eq!(render_audacity(&[segment]), "62.150000\t63.100000\tshit, damn\n");
+
+        assert_eq!(
+            render_reaper(&detections),
+            "#,Name,Start,End,Length\nR1,\"damn, really\",0:01.000,0:01.500,0:00.500\nR2,shit,1:02.250,1:03.000,0:00.750\n"
+        );
+        assert!(render_audition(&detections).starts_with("Name\tStart\tDuration\tTime Format\tType\tDescription\ndamn"));
+        assert!(parse_audacity("1.0\tabc\tdamn\n", &config).is_err());
+    }
+
+    #[test]
+    fn test_reimported_labels_censor_the_same_audio() {
+        use crate::censoring::{prepare_assignments, SegmentOptions};
+        use crate::export::export_segments;
+        use crate::rules::assign_strategies;
+
+        let detected = vec![
+            WordDetection { word: "damn".to_string(), start_time: 1.0, end_time: 1.4, is_swear: true, ..Default::default() },
+            WordDetection { word: "hell".to_string(), start_time: 1.6, end_time: 1.8, is_swear: true, ..Default::default() },
+            WordDetection { word: "shit".to_string(), start_time: 5.0, end_time: 5.3, is_swear: true, ..Default::default() },
+        ];
+        let segments = |detections: &[WordDetection], config: &Config| -> Vec<ExportSegment> {
+            let mut assignments = assign_strategies(detections, config);
+            prepare_assignments(&mut assignments, &SegmentOptions::from(config), None);
+            export_segments(&assignments, detections)
+        };
+
+        // Merged and padded once on export, then censored exactly as labelled
+        let config = Config::default();
+        let exported = segments(&detected, &config);
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[0].words, vec!["damn", "hell"]);
+
+        let imported_config = Config { label_file: Some("labels.txt".into()), ..Default::default() };
+        let imported = parse_audacity(&render_audacity(&exported), &imported_config).unwrap();
+        let reimported = segments(&imported, &imported_config);
+        assert_eq!(reimported.len(), exported.len());
+        for (again, once) in reimported.iter().zip(&exported) {
+            assert!((again.start - once.start).abs() < 1e-6 && (again.end - once.end).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::error::{config_error, BabymodeError, Result};
+
+/// How offensive a word is, from mildest to strongest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Mild,
+    #[default]
+    Moderate,
+    Strong,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Mild => "mild",
+            Severity::Moderate => "moderate",
+            Severity::Strong => "strong",
+        }
+    }
+}
+
+impl std::str::FromStr for Severity {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "mild" => Ok(Severity::Mild),
+            "moderate" => Ok(Severity::Moderate),
+            "strong" | "severe" => Ok(Severity::Strong),
+            _ => Err(config_error(
+                "severity",
+                format!("Invalid severity '{}'. Valid options: mild, moderate, strong", s)
+            )),
+        }
+    }
+}
+
+/// Classification of one word in the lexicon
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LexiconEntry {
+    #[serde(default = "default_category")]
+    pub category: String,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+fn default_category() -> String {
+    Lexicon::DEFAULT_CATEGORY.to_string()
+}
+
+impl LexiconEntry {
+    pub fn new(category: &str, severity: Severity) -> Self {
+        Self {
+            category: category.to_lowercase(),
+            severity,
+        }
+    }
+}
+
+/// Starting value for [`fnv1a`]
+pub(crate) const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+
+/// Fold `bytes` into the FNV-1a hash `hash`. Stable across builds and
+/// toolchains, unlike the std hasher.
+pub(crate) fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Category and severity of each censored word, used by strategy rules.
+/// Words without an entry are treated as moderate profanity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lexicon {
+    entries: HashMap<String, LexiconEntry>,
+}
+
+impl Lexicon {
+    pub const DEFAULT_CATEGORY: &'static str = "profanity";
+
+    /// Empty lexicon, every word gets the default classification
+    pub fn empty() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Add or replace the classification of `word`
+    pub fn insert(&mut self, word: &str, entry: LexiconEntry) {
+        self.entries.insert(word.trim().to_lowercase(), entry);
+    }
+
+    pub fn get(&self, word: &str) -> Option<&LexiconEntry> {
+        self.entries.get(&word.to_lowercase())
+    }
+
+    /// Short hash identifying this lexicon together with `words`, so runs
+    /// can tell whether they censored with the same word list
+    pub fn fingerprint(&self, words: &[String]) -> String {
+        let mut words: Vec<String> = words.iter().map(|w| w.trim().to_lowercase()).collect();
+        words.sort();
+        words.dedup();
+        let mut entries: Vec<String> = self.entries.iter()
+            .map(|(word, entry)| format!("{}={}:{}", word, entry.category, entry.severity.as_str()))
+            .collect();
+        entries.sort();
+
+        let mut hash = FNV_OFFSET_BASIS;
+        for item in words.iter().chain(std::iter::once(&String::new())).chain(entries.iter()) {
+            hash = fnv1a(hash, item.as_bytes());
+            hash = fnv1a(hash, b"\n");
+        }
+        format!("{:016x}", hash)
+    }
+
+    /// Classification of `word`, falling back to moderate profanity
+    pub fn classify(&self, word: &str) -> LexiconEntry {
+        self.get(word)
+            .cloned()
+            .unwrap_or_else(|| LexiconEntry::new(Self::DEFAULT_CATEGORY, Severity::default()))
+    }
+}
+
+impl Default for Lexicon {
+    fn default() -> Self {
+        let mut lexicon = Self::empty();
+        for (word, category, severity) in [
+            ("fuck", "profanity", Severity::Strong),
+            ("shit", "profanity", Severity::Moderate),
+            ("crap", "profanity", Severity::Mild),
+            ("piss", "profanity", Severity::Mild),
+            ("damn", "blasphemy", Severity::Mild),
+            ("hell", "blasphemy", Severity::Mild),
+            ("ass", "insult", Severity::Mild),
+            ("bitch", "insult", Severity::Moderate),
+            ("bastard", "insult", Severity::Moderate),
+        ] {
+            lexicon.insert(word, LexiconEntry::new(category, severity));
+        }
+        lexicon
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classification() {
+        let mut lexicon = Lexicon::default();
+        assert_eq!(lexicon.classify("FUCK").severity, Severity::Strong);
+        assert_eq!(lexicon.classify("damn").category, "blasphemy");
+
+        // Unknown words fall back to moderate profanity
+        let unknown = lexicon.classify("frak");
+        assert_eq!(unknown, LexiconEntry::new("profanity", Severity::Moderate));
+
+        lexicon.insert("frak", LexiconEntry::new("Sci-Fi", Severity::Mild));
+        assert_eq!(lexicon.classify("frak").category, "sci-fi");
+        assert!(Severity::Mild < Severity::Strong);
+        assert_eq!("severe".parse::<Severity>().unwrap(), Severity::Strong);
+
+        let words = vec!["damn".to_string(), "Hell".to_string()];
+        let reordered = vec!["hell".to_string(), "damn".to_string()];
+        assert_eq!(lexicon.fingerprint(&words), lexicon.fingerprint(&reordered));
+        assert_ne!(lexicon.fingerprint(&words), Lexicon::default().fingerprint(&words));
+    }
+}
@@ -1,24 +1,56 @@
 // Core modules
 pub mod audio;
+pub mod captions;
 pub mod censoring;
 pub mod config;
 pub mod config_file;
 pub mod dependencies;
+pub mod encoding;
+pub mod envelope;
 pub mod error;
+pub mod export;
+pub mod external;
+pub mod indicator;
+pub mod labels;
+pub mod lexicon;
+pub mod loudness;
+pub mod overlay;
+pub mod player;
 pub mod plugins;
 pub mod progress;
+pub mod refine;
+pub mod report;
 pub mod resources;
+pub mod rules;
+pub mod subtitles;
+pub mod tones;
+pub mod transcript;
 pub mod video;
+pub mod visual;
 pub mod whisper;
 
 // Re-export commonly used types
-pub use audio::{AudioConfig, AudioSegment};
-pub use censoring::{CensorConfig, CensorStrategy, CensoringStats};
+pub use audio::{AudioConfig, AudioSegment, AudioStreamInfo};
+pub use captions::{CaptionFormat, CaptionOptions};
+pub use censoring::{CensorConfig, CensorStrategy, CensoringStats, SegmentOptions};
 pub use config::{Config, ConfigBuilder, WhisperModel};
 pub use config_file::{ConfigFile, ProfileConfig};
+pub use encoding::{AudioCodec, AudioEncoding, EncodingSettings};
+pub use envelope::{Duck, FadeCurve};
 pub use error::{BabymodeError, Result};
-pub use plugins::{CensoringStrategy, StrategyRegistry, CensoringConfig};
-pub use progress::{ProgressTracker, ProgressOperation};
+pub use export::{ExportFormat, ExportSegment, FrameRate};
+pub use indicator::{IndicatorPosition, IndicatorSettings, IndicatorStyle};
+pub use lexicon::{Lexicon, LexiconEntry, Severity};
+pub use loudness::{LoudnessMeasurement, LoudnessReport, LoudnessTarget};
+pub use plugins::{CensoringStrategy, StrategyRegistry, StrategyAssignment, CensoringConfig, ParamKind, ParamSpec};
+pub use player::PlayerFormat;
+pub use progress::{ProgressTracker, ProgressOperation, StageTiming};
+pub use report::RunReport;
 pub use resources::TempFile;
-pub use video::VideoMetadata;
+pub use rules::StrategyRule;
+pub use subtitles::{SubtitleMask, SubtitleReport};
+pub use tones::BeepPreset;
+pub use transcript::{CueLocalizer, TranscriptSource};
+pub use video::{VideoFormat, VideoMetadata};
+pub use visual::{BlurRegion, BlurStyle, VideoEdits};
 pub use whisper::{WordDetection, merge_detections};
@@ -0,0 +1,276 @@
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::process::Command;
+
+use crate::audio;
+use crate::error::{config_error, BabymodeError};
+
+/// Loudness a programme is normalised to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LoudnessTarget {
+    /// Integrated loudness, in LUFS
+    pub integrated: f64,
+    /// Maximum true peak, in dBTP
+    pub true_peak: f64,
+    /// Loudness range, in LU
+    pub lra: f64,
+    /// How far the integrated loudness may be from the target, in LU
+    pub tolerance: f64,
+}
+
+impl LoudnessTarget {
+    /// EBU R128 broadcast delivery
+    pub const EBU_R128: LoudnessTarget = LoudnessTarget {
+        integrated: -23.0,
+        true_peak: -1.0,
+        lra: 18.0,
+        tolerance: 0.5,
+    };
+
+    /// ATSC A/85 broadcast delivery
+    pub const ATSC_A85: LoudnessTarget = LoudnessTarget {
+        integrated: -24.0,
+        true_peak: -2.0,
+        lra: 18.0,
+        tolerance: 2.0,
+    };
+
+    /// Streaming and podcast platforms
+    pub const STREAMING: LoudnessTarget = LoudnessTarget {
+        integrated: -16.0,
+        true_peak: -1.0,
+        lra: 11.0,
+        tolerance: 1.0,
+    };
+
+    /// Target of `integrated` LUFS with the streaming peak and range limits
+    pub fn lufs(integrated: f64) -> Result<Self, BabymodeError> {
+        if !(-70.0..=-5.0).contains(&integrated) {
+            return Err(config_error(
+                "loudness",
+                format!("Loudness target must be between -70 and -5 LUFS, got {}", integrated)
+            ));
+        }
+        Ok(Self {
+            integrated,
+            ..Self::STREAMING
+        })
+    }
+
+    /// Whether `measurement` is within tolerance of this target
+    pub fn is_met_by(&self, measurement: &LoudnessMeasurement) -> bool {
+        (measurement.integrated - self.integrated).abs() <= self.tolerance
+            && measurement.true_peak <= self.true_peak
+    }
+}
+
+impl std::str::FromStr for LoudnessTarget {
+    type Err = BabymodeError;
+
+    /// A preset name, or an integrated loudness such as `-16` or `-16LUFS`
+    fn from_str(s: &str) -> Result<Self, BabymodeError> {
+        let lower = s.trim().to_lowercase();
+        match lower.as_str() {
+            "ebu_r128" | "r128" | "ebu" => Ok(Self::EBU_R128),
+            "atsc_a85" | "a85" | "atsc" => Ok(Self::ATSC_A85),
+            "streaming" | "podcast" => Ok(Self::STREAMING),
+            _ => lower.trim_end_matches("lufs").trim().parse::<f64>()
+                .map_err(|_| config_error(
+                    "loudness",
+                    format!("Invalid loudness target '{}'. Use ebu_r128, atsc_a85, streaming or a LUFS value", s)
+                ))
+                .and_then(Self::lufs),
+        }
+    }
+}
+
+/// Loudness of a programme as measured by ffmpeg's `loudnorm` filter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LoudnessMeasurement {
+    /// Integrated loudness, in LUFS
+    pub integrated: f64,
+    /// True peak, in dBTP
+    pub true_peak: f64,
+    /// Loudness range, in LU
+    pub lra: f64,
+    /// Gating threshold, in LUFS
+    pub threshold: f64,
+}
+
+/// Loudness before and after censoring, for the run report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoudnessReport {
+    pub target: LoudnessTarget,
+    pub before: LoudnessMeasurement,
+    pub after: LoudnessMeasurement,
+    pub compliant: bool,
+}
+
+/// `loudnorm` filter for `target`. With a first-pass `measured`, the filter
+/// applies a single linear gain instead of dynamic compression where it can.
+pub fn loudnorm_filter(target: &LoudnessTarget, measured: Option<&LoudnessMeasurement>) -> String {
+    let mut filter = format!(
+        "loudnorm=I={:.1}:TP={:.1}:LRA={:.1}",
+        target.integrated, target.true_peak, target.lra
+    );
+    match measured {
+        Some(m) => filter.push_str(&format!(
+            ":measured_I={:.2}:measured_TP={:.2}:measured_LRA={:.2}:measured_thresh={:.2}:linear=true:print_format=summary",
+            m.integrated, m.true_peak, m.lra, m.threshold
+        )),
+        None => filter.push_str(":print_format=json"),
+    }
+    filter
+}
+
+/// Pull the measurement out of the JSON block `loudnorm` prints to stderr
+pub fn parse_loudnorm_output(stderr: &str) -> Result<LoudnessMeasurement> {
+    let start = stderr.rfind('{').context("No loudness measurement in ffmpeg output")?;
+    let end = stderr[start..].find('}').context("Truncated loudness measurement")? + start;
+    let json: serde_json::Value = serde_json::from_str(&stderr[start..=end])
+        .context("Failed to parse loudness measurement")?;
+
+    // Values are strings, and silence reports "-inf"
+    let field = |name: &str| -> Result<f64> {
+        json.get(name)
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.trim().parse::<f64>().ok())
+            .map(|v| v.max(-99.0))
+            .with_context(|| format!("Loudness measurement has no {}", name))
+    };
+
+    Ok(LoudnessMeasurement {
+        integrated: field("input_i")?,
+        true_peak: field("input_tp")?,
+        lra: field("input_lra")?,
+        threshold: field("input_thresh")?,
+    })
+}
+
+/// Measure the loudness of the first audio stream of `path`
+pub async fn measure(path: &Path, target: &LoudnessTarget) -> Result<LoudnessMeasurement> {
+    let output = Command::new("ffmpeg")
+        .args([
+            "-hide_banner",
+            "-i", path.to_str().context("Invalid audio path")?,
+            "-map", "0:a:0",
+            "-af", &loudnorm_filter(target, None),
+            "-f", "null",
+            "-",
+        ])
+        .output()
+        .await
+        .context("Failed to execute ffmpeg")?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        anyhow::bail!("ffmpeg failed to measure loudness: {}", stderr);
+    }
+
+    let measurement = parse_loudnorm_output(&stderr)?;
+    debug!("Loudness of {:?}: {:?}", path, measurement);
+    Ok(measurement)
+}
+
+/// Normalise `input_path` to `target` in two passes, keeping its sample rate.
+/// Returns the first-pass measurement.
+pub async fn normalize(input_path: &Path, output_path: &Path, target: &LoudnessTarget) -> Result<LoudnessMeasurement> {
+    let measured = measure(input_path, target).await?;
+    let stream = audio::probe_audio_stream(input_path).await?;
+    info!(
+        "Normalising from {:.1} LUFS to {:.1} LUFS",
+        measured.integrated, target.integrated
+    );
+
+    // loudnorm upsamples to 192 kHz internally, so resample back afterwards
+    let filter = format!("{},aresample={}", loudnorm_filter(target, Some(&measured)), stream.sample_rate);
+    let output = Command::new("ffmpeg")
+        .args([
+            "-i", input_path.to_str().context("Invalid input path")?,
+            "-map", "0:a:0",
+            "-af", &filter,
+            "-acodec", "pcm_s16le",
+            "-y",
+            output_path.to_str().context("Invalid output path")?,
+        ])
+        .output()
+        .await
+        .context("Failed to execute ffmpeg")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg failed to normalise loudness: {}", error);
+    }
+
+    Ok(measured)
+}
+
+/// Compare the measurement of the finished output against `target`
+pub fn report(target: LoudnessTarget, before: LoudnessMeasurement, after: LoudnessMeasurement) -> LoudnessReport {
+    let compliant = target.is_met_by(&after);
+    if compliant {
+        info!(
+            "Loudness {:.1} LUFS, true peak {:.1} dBTP, range {:.1} LU (target {:.1} LUFS)",
+            after.integrated, after.true_peak, after.lra, target.integrated
+        );
+    } else {
+        warn!(
+            "Output is out of loudness tolerance: {:.1} LUFS, true peak {:.1} dBTP (target {:.1} ±{:.1} LUFS, peak {:.1} dBTP)",
+            after.integrated, after.true_peak, target.integrated, target.tolerance, target.true_peak
+        );
+    }
+    LoudnessReport {
+        target,
+        before,
+        after,
+        compliant,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_parsing() {
+        assert_eq!("ebu_r128".parse::<LoudnessTarget>().unwrap(), LoudnessTarget::EBU_R128);
+        assert_eq!("-16".parse::<LoudnessTarget>().unwrap().integrated, -16.0);
+        assert_eq!("-14 LUFS".parse::<LoudnessTarget>().unwrap().integrated, -14.0);
+        assert!("loud".parse::<LoudnessTarget>().is_err());
+        assert!("3".parse::<LoudnessTarget>().is_err());
+    }
+
+    #[test]
+    fn test_loudnorm_measurement() {
+        let stderr = r#"
+size=N/A time=00:00:10.00 bitrate=N/A speed= 400x
+[Parsed_loudnorm_0 @ 0x55d4c]
+{
+	"input_i" : "-27.61",
+	"input_tp" : "-4.47",
+	"input_lra" : "18.06",
+	"input_thresh" : "-39.20",
+	"output_i" : "-24.58",
+	"output_tp" : "-2.00",
+	"output_lra" : "12.70",
+	"output_thresh" : "-35.93",
+	"normalization_type" : "dynamic",
+	"target_offset" : "1.58"
+}
+"#;
+        let measured = parse_loudnorm_output(stderr).unwrap();
+        assert_eq!(measured.integrated, -27.61);
+        assert_eq!(measured.true_peak, -4.47);
+        assert!(parse_loudnorm_output("no json here").is_err());
+
+        let filter = loudnorm_filter(&LoudnessTarget::EBU_R128, Some(&measured));
+        assert!(filter.starts_with("loudnorm=I=-23.0:TP=-1.0:LRA=18.0:measured_I=-27.61"));
+        assert!(filter.contains("linear=true"));
+
+        assert!(!LoudnessTarget::EBU_R128.is_met_by(&measured));
+        let on_target = LoudnessMeasurement { integrated: -23.3, true_peak: -1.5, ..measured };
+        assert!(LoudnessTarget::EBU_R128.is_met_by(&on_target));
+    }
+}
@@ -1,21 +1,21 @@
 use clap::{Arg, Command};
-use log::{info};
+use log::{info, warn};
 use std::path::PathBuf;
 
-use babymode::{Config, ConfigBuilder, ConfigFile, Result, WhisperModel};
-use babymode::{dependencies, video, audio, whisper, plugins};
+use babymode::{Config, ConfigBuilder, ConfigFile, EncodingSettings, FadeCurve, IndicatorSettings, Result, TranscriptSource, WhisperModel};
+use babymode::{dependencies, video, visual, audio, whisper, refine, rules, captions, censoring, loudness, encoding, export, labels, player, transcript};
 use babymode::{StrategyRegistry, ProgressOperation};
 
 fn build_cli() -> Command {
     Command::new("babymode")
-        .about("A multimedia application that automatically censors swearing in video files")
+        .about("A multimedia application that automatically censors swearing in video and audio files")
         .version("0.1.0")
         .arg(
             Arg::new("input")
                 .short('i')
                 .long("input")
                 .value_name("FILE")
-                .help("Input video file to process")
+                .help("Input video or audio file to process")
                 .required(false) // Will be validated in parse_config
                 .value_parser(clap::value_parser!(PathBuf)),
         )
@@ -24,7 +24,7 @@ fn build_cli() -> Command {
                 .short('o')
                 .long("output")
                 .value_name("FILE")
-                .help("Output video file (optional, defaults to input_censored.ext)")
+                .help("Output video or audio file (optional, defaults to input_censored.ext)")
                 .value_parser(clap::value_parser!(PathBuf)),
         )
         .arg(
@@ -54,6 +54,257 @@ fn build_cli() -> Command {
                 .default_value("0.2")
                 .value_parser(clap::value_parser!(f32)),
         )
+        .arg(
+            Arg::new("fade-curve")
+                .long("fade-curve")
+                .value_name("CURVE")
+                .help("Shape of the fade in/out around censored segments")
+                .value_parser(["linear", "logarithmic", "equal_power", "s_curve"]),
+        )
+        .arg(
+            Arg::new("refine")
+                .long("refine")
+                .help("Snap word edges to nearby quiet points so no phonemes leak through")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("refine-window")
+                .long("refine-window")
+                .value_name("SECONDS")
+                .help("Furthest refinement may move an edge (0.01-0.5, default 0.15)")
+                .value_parser(clap::value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("merge-gap")
+                .long("merge-gap")
+                .value_name("SECONDS")
+                .help("Censor detections closer than this as one segment (0.0-5.0, default 0.5)")
+                .value_parser(clap::value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("padding")
+                .long("padding")
+                .value_name("SECONDS")
+                .help("Extra time censored on both sides of each segment (0.0-2.0, default 0.1)")
+                .value_parser(clap::value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("pre-padding")
+                .long("pre-padding")
+                .value_name("SECONDS")
+                .help("Extra time censored before each segment, overrides --padding")
+                .value_parser(clap::value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("post-padding")
+                .long("post-padding")
+                .value_name("SECONDS")
+                .help("Extra time censored after each segment, overrides --padding")
+                .value_parser(clap::value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("loudness")
+                .long("loudness")
+                .value_name("TARGET")
+                .help("Normalise loudness: ebu_r128, atsc_a85, streaming or a LUFS value such as -16"),
+        )
+        .arg(
+            Arg::new("encoding-preset")
+                .long("encoding-preset")
+                .value_name("PRESET")
+                .help("Output encoding preset: web, archive, broadcast or one from the config file"),
+        )
+        .arg(
+            Arg::new("audio-codec")
+                .long("audio-codec")
+                .value_name("CODEC")
+                .help("Output audio codec (default: same as the source)")
+                .value_parser(["aac", "opus", "ac3", "flac", "mp3", "pcm"]),
+        )
+        .arg(
+            Arg::new("audio-bitrate")
+                .long("audio-bitrate")
+                .value_name("BITRATE")
+                .help("Output audio bitrate for lossy codecs, e.g. 192k"),
+        )
+        .arg(
+            Arg::new("sample-format")
+                .long("sample-format")
+                .value_name("FORMAT")
+                .help("Output sample format, e.g. s16, s24 or fltp"),
+        )
+        .arg(
+            Arg::new("sample-rate")
+                .long("sample-rate")
+                .value_name("HZ")
+                .help("Output sample rate")
+                .value_parser(clap::value_parser!(u32)),
+        )
+        .arg(
+            Arg::new("container")
+                .long("container")
+                .value_name("EXT")
+                .help("Output container when no output file is given, e.g. mkv. With -o the \
+                       file extension decides, and a different --container is an error"),
+        )
+        .arg(
+            Arg::new("subtitle-mask")
+                .long("subtitle-mask")
+                .value_name("MASK")
+                .help("Replacement for swear words in subtitles: asterisks, grawlix or any text (default asterisks)"),
+        )
+        .arg(
+            Arg::new("no-subtitles")
+                .long("no-subtitles")
+                .help("Leave soft subtitle tracks out of the output instead of censoring them")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("transcript-source")
+                .long("transcript-source")
+                .value_name("SOURCE")
+                .help("Where swear words are read from: whisper or subtitles (default whisper)"),
+        )
+        .arg(
+            Arg::new("subtitle-file")
+                .long("subtitle-file")
+                .value_name("FILE")
+                .help("Subtitle file (srt, vtt, ass) to read swear words from instead of the input's own tracks")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("import-labels")
+                .long("import-labels")
+                .value_name("FILE")
+                .help("Censor the labels in an Audacity label file instead of detecting swear words")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("report")
+                .long("report")
+                .value_name("FILE")
+                .help("Write a JSON report of the run: detections, strategies, statistics and timings")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("localize")
+                .long("localize")
+                .value_name("METHOD")
+                .help("How words found in subtitles are timed: whisper or energy (default whisper)"),
+        )
+        .arg(
+            Arg::new("mouth-blur")
+                .long("mouth-blur")
+                .value_name("STYLE")
+                .help("Hide the speaker's mouth while words are censored: blur or pixelate (default blur)")
+                .num_args(0..=1)
+                .default_missing_value("blur"),
+        )
+        .arg(
+            Arg::new("blur-region")
+                .long("blur-region")
+                .value_name("REGION")
+                .help("Area to hide: face, or x,y,width,height in pixels or fractions of the frame (default face)"),
+        )
+        .arg(
+            Arg::new("blur-margin")
+                .long("blur-margin")
+                .value_name("SECONDS")
+                .help("Extra time the mouth stays hidden around each censored segment (default 0.25)")
+                .value_parser(clap::value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("indicator")
+                .long("indicator")
+                .value_name("STYLE")
+                .help("Draw an indicator while words are censored: icon, caption or bar"),
+        )
+        .arg(
+            Arg::new("indicator-position")
+                .long("indicator-position")
+                .value_name("POSITION")
+                .help("Where the indicator goes: top-left, top-right, bottom-left, bottom-right, top, bottom or center (default top-right)"),
+        )
+        .arg(
+            Arg::new("indicator-color")
+                .long("indicator-color")
+                .value_name("COLOR")
+                .help("Indicator colour, e.g. red, #FFCC00 or white@0.8"),
+        )
+        .arg(
+            Arg::new("indicator-text")
+                .long("indicator-text")
+                .value_name("TEXT")
+                .help("Text drawn by the caption indicator (default [bleep])"),
+        )
+        .arg(
+            Arg::new("indicator-asset")
+                .long("indicator-asset")
+                .value_name("IMAGE")
+                .help("Image drawn by the icon indicator")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("indicator-size")
+                .long("indicator-size")
+                .value_name("PIXELS")
+                .help("Icon or text height, or bar thickness, in pixels")
+                .value_parser(clap::value_parser!(u32)),
+        )
+        .arg(
+            Arg::new("export")
+                .long("export")
+                .value_name("FORMAT,...")
+                .help("Write the censor segments for an editor next to the output: edl, fcpxml, mlt, audacity, reaper, audition")
+                .value_delimiter(','),
+        )
+        .arg(
+            Arg::new("player-sidecars")
+                .long("player-sidecars")
+                .value_name("FORMAT,...")
+                .help("Write mute files for media players next to the input: kodi, mpv, segments")
+                .value_delimiter(','),
+        )
+        .arg(
+            Arg::new("detect-only")
+                .long("detect-only")
+                .visible_alias("export-only")
+                .help("Only detect swear words and write the --export and --player-sidecars files, without rendering (all player sidecars if none are chosen)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("captions")
+                .long("captions")
+                .value_name("FORMAT,...")
+                .help("Write censored captions from the transcript next to the output: srt, vtt")
+                .value_delimiter(','),
+        )
+        .arg(
+            Arg::new("caption-line-length")
+                .long("caption-line-length")
+                .value_name("CHARS")
+                .help("Longest caption line (default 42)")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("caption-max-duration")
+                .long("caption-max-duration")
+                .value_name("SECONDS")
+                .help("Longest a caption stays on screen (default 6)")
+                .value_parser(clap::value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("karaoke")
+                .long("karaoke")
+                .help("Time captions word by word")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("mux-captions")
+                .long("mux-captions")
+                .help("Add the transcript captions to the output as a soft subtitle stream")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("words")
                 .short('w')
@@ -88,9 +339,28 @@ fn build_cli() -> Command {
                 .short('s')
                 .long("strategy")
                 .value_name("STRATEGY")
-                .help("Censoring strategy to use")
-                .default_value("silence")
-                .value_parser(["silence", "volume_reduction", "beep", "reverse"]),
+                .help("Censoring strategy to use (default: silence, see --list-strategies)"),
+        )
+        .arg(
+            Arg::new("plugins-dir")
+                .long("plugins-dir")
+                .value_name("DIR")
+                .help("Directory of external strategy plugins")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("strategy-param")
+                .long("strategy-param")
+                .value_name("KEY=VALUE")
+                .help("Set a parameter of the selected strategy (see --list-strategies)")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("replacement-audio")
+                .long("replacement-audio")
+                .value_name("PATH")
+                .help("Clip, or folder of clips, used by the replacement strategy")
+                .value_parser(clap::value_parser!(PathBuf)),
         )
         .arg(
             Arg::new("no-progress")
@@ -112,24 +382,54 @@ fn build_cli() -> Command {
         )
 }
 
-async fn parse_config(matches: &clap::ArgMatches) -> Result<Config> {
+/// Built-in strategies, plus plugins when `with_plugins` is set. Loading a
+/// plugin runs it, so they only come from `--plugins-dir`, `plugins_dir` in
+/// the config file or the user's config directory.
+async fn load_strategies(
+    matches: &clap::ArgMatches,
+    config_file: Option<&ConfigFile>,
+    with_plugins: bool,
+) -> StrategyRegistry {
+    let mut registry = StrategyRegistry::new();
+    if !with_plugins {
+        return registry;
+    }
+    let dir = matches.get_one::<PathBuf>("plugins-dir")
+        .or_else(|| config_file.and_then(|cf| cf.plugins_dir.as_ref()))
+        .cloned()
+        .or_else(babymode::external::default_plugin_dir);
+    if let Some(dir) = dir {
+        let added = registry.load_plugins(&dir).await;
+        if added > 0 {
+            info!("Loaded {} plugin strategies from {}", added, dir.display());
+        }
+    }
+    registry
+}
+
+async fn load_config_file(matches: &clap::ArgMatches) -> Result<Option<ConfigFile>> {
+    match matches.get_one::<PathBuf>("config") {
+        Some(config_path) => Ok(Some(ConfigFile::load(config_path).await?)),
+        None => Ok(ConfigFile::load_from_default_locations().await),
+    }
+}
+
+async fn parse_config(matches: &clap::ArgMatches, config_file: Option<&ConfigFile>) -> Result<Config> {
     // Handle special listing commands first
     if matches.get_flag("list-strategies") {
-        let registry = StrategyRegistry::new();
+        let registry = load_strategies(matches, config_file, true).await;
         println!("Available censoring strategies:");
         for (name, description) in registry.list_strategies() {
             println!("  {}: {}", name, description);
+            for param in registry.get_strategy(name).map(|s| s.parameters()).unwrap_or_default() {
+                println!("      {} ({}): {}", param.name, param.summary(), param.description);
+            }
         }
         std::process::exit(0);
     }
 
     if matches.get_flag("list-profiles") {
-        // Try to load config file to show profiles
-        let config_file = if let Some(config_path) = matches.get_one::<PathBuf>("config") {
-            ConfigFile::load(config_path).await.ok()
-        } else {
-            ConfigFile::load_from_default_locations().await
-        }.unwrap_or_default();
+        let config_file = config_file.cloned().unwrap_or_default();
         
         println!("Available configuration profiles:");
         for profile_name in config_file.list_profiles() {
@@ -154,15 +454,8 @@ async fn parse_config(matches: &clap::ArgMatches) -> Result<Config> {
 
     let mut builder = ConfigBuilder::new().input_file(input_file);
     
-    // Load config file if specified or from default locations
-    let config_file = if let Some(config_path) = matches.get_one::<PathBuf>("config") {
-        Some(ConfigFile::load(config_path).await?)
-    } else {
-        ConfigFile::load_from_default_locations().await
-    };
-    
     // Apply config file settings
-    if let Some(ref cf) = config_file {
+    if let Some(cf) = config_file {
         if let Some(profile_name) = matches.get_one::<String>("profile") {
             // Apply specific profile
             builder = cf.apply_profile_to_builder(profile_name, builder)?;
@@ -189,14 +482,240 @@ async fn parse_config(matches: &clap::ArgMatches) -> Result<Config> {
         builder = builder.fade_duration(fade)?;
     }
 
+    if let Some(curve_str) = matches.get_one::<String>("fade-curve") {
+        let curve: FadeCurve = curve_str.parse()?;
+        builder = builder.fade_curve(curve);
+    }
+
+    if matches.get_flag("refine") {
+        builder = builder.refine_segments(true);
+    }
+
+    if let Some(&window) = matches.get_one::<f32>("refine-window") {
+        builder = builder.refine_window(window)?;
+    }
+
+    if let Some(&gap) = matches.get_one::<f32>("merge-gap") {
+        builder = builder.merge_gap(gap)?;
+    }
+
+    if let Some(&padding) = matches.get_one::<f32>("padding") {
+        builder = builder.pre_padding(padding)?.post_padding(padding)?;
+    }
+
+    if let Some(&padding) = matches.get_one::<f32>("pre-padding") {
+        builder = builder.pre_padding(padding)?;
+    }
+
+    if let Some(&padding) = matches.get_one::<f32>("post-padding") {
+        builder = builder.post_padding(padding)?;
+    }
+
+    if matches.get_flag("no-subtitles") {
+        builder = builder.censor_subtitles(false);
+    }
+
+    if let Some(mask) = matches.get_one::<String>("subtitle-mask") {
+        builder = builder.subtitle_mask(mask.parse().unwrap_or_default())?;
+    }
+
+    if let Some(source) = matches.get_one::<String>("transcript-source") {
+        builder = builder.transcript_source(source.parse()?);
+    }
+
+    if let Some(path) = matches.get_one::<PathBuf>("subtitle-file") {
+        builder = builder.subtitle_file(path.clone());
+    }
+
+    if let Some(path) = matches.get_one::<PathBuf>("import-labels") {
+        builder = builder.label_file(path.clone());
+    }
+
+    if let Some(path) = matches.get_one::<PathBuf>("report") {
+        builder = builder.report_file(path.clone());
+    }
+
+    if let Some(localizer) = matches.get_one::<String>("localize") {
+        builder = builder.cue_localizer(localizer.parse()?);
+    }
+
+    if let Some(style) = matches.get_one::<String>("mouth-blur") {
+        builder = builder.mouth_blur(style.parse()?);
+    }
+
+    if let Some(region) = matches.get_one::<String>("blur-region") {
+        builder = builder.blur_region(region.parse()?);
+    }
+
+    if let Some(&margin) = matches.get_one::<f32>("blur-margin") {
+        builder = builder.blur_margin(margin)?;
+    }
+
+    builder = builder.indicator(IndicatorSettings {
+        style: matches.get_one::<String>("indicator").map(|s| s.parse()).transpose()?,
+        position: matches.get_one::<String>("indicator-position").map(|s| s.parse()).transpose()?,
+        color: matches.get_one::<String>("indicator-color").cloned(),
+        text: matches.get_one::<String>("indicator-text").cloned(),
+        asset: matches.get_one::<PathBuf>("indicator-asset").cloned(),
+        size: matches.get_one::<u32>("indicator-size").copied(),
+    })?;
+
+    if let Some(formats) = matches.get_many::<String>("export") {
+        let formats = formats.map(|f| f.parse()).collect::<babymode::Result<Vec<_>>>()?;
+        builder = builder.export_formats(formats);
+    }
+
+    if let Some(formats) = matches.get_many::<String>("player-sidecars") {
+        let formats = formats.map(|f| f.parse()).collect::<babymode::Result<Vec<_>>>()?;
+        builder = builder.player_sidecars(formats);
+    }
+
+    if matches.get_flag("detect-only") {
+        builder = builder.detect_only(true);
+    }
+
+    if let Some(formats) = matches.get_many::<String>("captions") {
+        let formats = formats.map(|f| f.parse()).collect::<babymode::Result<Vec<_>>>()?;
+        builder = builder.caption_formats(formats);
+    }
+
+    if let Some(&length) = matches.get_one::<usize>("caption-line-length") {
+        builder = builder.caption_line_length(length)?;
+    }
+
+    if let Some(&duration) = matches.get_one::<f32>("caption-max-duration") {
+        builder = builder.caption_max_duration(duration)?;
+    }
+
+    if matches.get_flag("karaoke") {
+        builder = builder.caption_karaoke(true);
+    }
+
+    if matches.get_flag("mux-captions") {
+        builder = builder.mux_captions(true);
+    }
+
+    if let Some(target) = matches.get_one::<String>("loudness") {
+        builder = builder.loudness(target.parse()?);
+    }
+
+    if let Some(preset) = matches.get_one::<String>("encoding-preset") {
+        let preset = match config_file {
+            Some(file) => file.encoding_preset(preset)?,
+            None => ConfigFile::default().encoding_preset(preset)?,
+        };
+        builder = builder.encoding(preset)?;
+    }
+
+    let container = matches.get_one::<String>("container").map(|c| c.trim_start_matches('.').to_lowercase());
+    if let (Some(container), Some(output)) = (&container, matches.get_one::<PathBuf>("output")) {
+        let extension = output.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase());
+        if extension.as_deref() != Some(container.as_str()) {
+            return Err(babymode::error::config_error(
+                "container",
+                format!("--container {} conflicts with output file {:?}; the output extension sets the container", container, output),
+            ));
+        }
+    }
+
+    builder = builder.encoding(EncodingSettings {
+        codec: matches.get_one::<String>("audio-codec").map(|c| c.parse()).transpose()?,
+        bitrate: matches.get_one::<String>("audio-bitrate").cloned(),
+        sample_format: matches.get_one::<String>("sample-format").cloned(),
+        sample_rate: matches.get_one::<u32>("sample-rate").copied(),
+        container,
+    })?;
+
     if let Some(words) = matches.get_many::<String>("words") {
         let word_list: Vec<String> = words.cloned().collect();
         builder = builder.swear_words(word_list)?;
     }
 
+    if let Some(clip) = matches.get_one::<PathBuf>("replacement-audio") {
+        builder = builder.replacement_audio(clip.clone());
+    }
+
+    if let Some(strategy) = matches.get_one::<String>("strategy") {
+        builder = builder.strategy(strategy.clone());
+    }
+
+    // Values stay strings here; the strategy's schema converts them
+    for param in matches.get_many::<String>("strategy-param").into_iter().flatten() {
+        let (key, value) = param.split_once('=').ok_or_else(|| {
+            babymode::error::config_error("strategy-param", format!("Expected KEY=VALUE, got '{}'", param))
+        })?;
+        builder = builder.strategy_param(key.trim(), value.into());
+    }
+
     builder.build()
 }
 
+/// Empty file with `suffix` for an intermediate render. ffmpeg picks the
+/// muxer from the extension, so the file needs one; TempFile takes over
+/// cleanup so the file survives until muxing.
+fn temp_file(suffix: &str) -> Result<babymode::TempFile> {
+    let temp_path = tempfile::Builder::new()
+        .suffix(suffix)
+        .tempfile()
+        .and_then(|f| f.into_temp_path().keep().map_err(|e| e.error))
+        .map_err(|e| babymode::BabymodeError::Processing {
+            message: format!("Failed to create temp file: {}", e)
+        })?;
+    Ok(babymode::TempFile::new(temp_path))
+}
+
+/// Write the censor segments in each of the configured export formats next
+/// to `output_file`, and as player sidecars next to the input
+async fn write_segment_files(
+    progress: &ProgressOperation,
+    config: &Config,
+    output_file: &std::path::Path,
+    assignments: &[babymode::StrategyAssignment],
+    detections: &[babymode::WordDetection],
+) -> Result<()> {
+    progress.with_spinner("Writing censor segment files", |_pb| {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let segments = export::export_segments(assignments, detections);
+                if !config.export_formats.is_empty() {
+                    let source = export::ExportSource::probe(&config.input_file).await?;
+                    export::write_exports(&segments, detections, &source, output_file, &config.export_formats).await?;
+                }
+                player::write_sidecars(&segments, &config.input_file, &config.player_sidecars).await?;
+                Ok::<_, babymode::BabymodeError>(())
+            })
+        })
+    }).await
+}
+
+/// Measure the finished `output_file` against `target`, with the loudness
+/// measured `before` censoring
+async fn report_loudness(
+    target: babymode::LoudnessTarget,
+    before: babymode::LoudnessMeasurement,
+    output_file: &std::path::Path,
+) -> Result<babymode::LoudnessReport> {
+    let after = loudness::measure(output_file, &target).await?;
+    let report = loudness::report(target, before, after);
+    info!(
+        "Loudness before censoring: {:.1} LUFS, true peak {:.1} dBTP, range {:.1} LU",
+        report.before.integrated, report.before.true_peak, report.before.lra
+    );
+    Ok(report)
+}
+
+/// Add the stage timings and tool versions to `report` and write it, when
+/// a report was asked for
+async fn finish_report(mut report: babymode::RunReport, progress: &ProgressOperation, config: &Config) -> Result<()> {
+    let Some(ref path) = config.report_file else {
+        return Ok(());
+    };
+    report.timings = progress.timings();
+    report.tools = dependencies::tool_versions().await;
+    report.write(path).await?;
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let app = build_cli();
@@ -209,9 +728,22 @@ async fn main() -> Result<()> {
         env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
     }
 
-    let config = parse_config(&matches).await?;
+    let config_file = load_config_file(&matches).await?;
+    let config = parse_config(&matches, config_file.as_ref()).await?;
     let show_progress = !matches.get_flag("no-progress");
-    let strategy_name = matches.get_one::<String>("strategy").unwrap();
+    let strategy_name = config.strategy.as_str();
+
+    // Check the default strategy and every rule up front rather than after
+    // transcription. Plugins are only run when a strategy isn't built in.
+    let strategy_configs: Vec<_> = config.rules.iter().map(Some).chain([None])
+        .map(|rule| rules::rule_config(&config, rule))
+        .collect();
+    let builtins = StrategyRegistry::new();
+    let needs_plugins = strategy_configs.iter().any(|(name, _)| builtins.get_strategy(name).is_none());
+    let registry = load_strategies(&matches, config_file.as_ref(), needs_plugins).await;
+    for (name, censoring_config) in &strategy_configs {
+        registry.resolve_config(name, censoring_config)?;
+    }
     
     let progress = ProgressOperation::new(show_progress);
     
@@ -221,75 +753,304 @@ async fn main() -> Result<()> {
     progress.with_spinner("Validating system dependencies", |_pb| {
         tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current().block_on(async {
-                dependencies::validate_dependencies().await
+                dependencies::validate_dependencies(&config).await
             })
         })
     }).await?;
 
-    // Validate input file is a video file
-    progress.with_spinner("Validating input video file", |_pb| {
-        video::validate_video_file(&config.input_file)
-    }).await?;
-
-    // Extract audio from video
-    let temp_audio = progress.with_spinner("Extracting audio from video", |_pb| {
+    // Check the input is readable media with decodable audio
+    let probe = progress.with_spinner("Validating input file", |_pb| {
         tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current().block_on(async {
-                audio::extract_audio(&config.input_file).await
+                video::validate_media_file(&config.input_file).await
             })
         })
     }).await?;
 
-    // Detect swear words using faster-whisper
-    let detections = progress.with_spinner("Analyzing audio for swear words", |_pb| {
+    // Audio-only inputs and outputs skip the video mux. Cover art only comes
+    // from audio-only inputs; a video stream is the programme itself
+    let output_file = config.output_file.clone().unwrap();
+    let audio_output = video::is_audio_file(&output_file);
+    let keep_cover_art = probe.is_audio_only() && probe.has_cover_art;
+    if probe.is_audio_only() && !audio_output {
+        return Err(babymode::error::config_error(
+            "output_file",
+            format!(
+                "Audio-only input needs an audio output format ({}), got {:?}",
+                video::AUDIO_EXTENSIONS.join(", "),
+                output_file
+            ),
+        ));
+    }
+
+    // Settle the output encoding now so a bad combination fails before transcription
+    let source_stream = match audio::probe_audio_stream(&config.input_file).await {
+        Ok(stream) => Some(stream),
+        Err(e) => {
+            warn!("Could not probe the source audio, encoding with container defaults: {}", e);
+            None
+        }
+    };
+    let output_encoding = encoding::resolve(&config.encoding, source_stream.as_ref(), &output_file)?;
+    info!("Output audio encoding: {}", output_encoding.args().join(" "));
+
+    let video_metadata = match probe.is_audio_only() {
+        true => None,
+        false => video::get_video_metadata(&config.input_file).await.ok(),
+    };
+    let mut run_report = babymode::RunReport::new(
+        &config,
+        babymode::report::InputReport::new(&config.input_file, &probe, video_metadata.as_ref()),
+    );
+
+    // Extract audio from video
+    let temp_audio = progress.with_spinner("Extracting audio from video", |_pb| {
         tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current().block_on(async {
-                whisper::detect_swear_words(temp_audio.path(), &config).await
+                audio::extract_audio(&config.input_file).await
             })
         })
     }).await?;
 
+    // Subtitles only give swear words, so captions need the full transcript
+    let want_captions = !config.caption_formats.is_empty() || config.mux_captions;
+    let from_labels = match config.label_file {
+        Some(ref path) => {
+            let labels = labels::read_audacity(path, &config).await?;
+            info!("Read {} labels from {:?}", labels.len(), path);
+            Some(labels)
+        }
+        None => None,
+    };
+    let from_subtitles = match config.transcript_source {
+        _ if from_labels.is_some() => None,
+        TranscriptSource::Subtitles if want_captions => {
+            warn!("Captions need the full Whisper transcript, ignoring the subtitles transcript source");
+            None
+        }
+        TranscriptSource::Subtitles => {
+            let found = progress.with_spinner("Reading swear words from subtitles", |_pb| {
+                tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(async {
+                        transcript::detect_from_subtitles(temp_audio.path(), &config, &probe.subtitle_streams).await
+                    })
+                })
+            }).await?;
+            if found.is_none() {
+                warn!("No text subtitles to read, transcribing with Whisper instead");
+            }
+            found
+        }
+        TranscriptSource::Whisper => None,
+    };
+
+    // Detect swear words using faster-whisper, keeping every word for captions
+    if from_labels.is_some() {
+        run_report.transcript_source = "labels".to_string();
+    } else if from_subtitles.is_none() {
+        run_report.transcript_source = TranscriptSource::Whisper.as_str().to_string();
+    }
+    let (transcript, mut detections) = match from_labels.or(from_subtitles) {
+        Some(detections) if want_captions => {
+            let transcript = progress.with_spinner("Transcribing for captions", |_pb| {
+                tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(async {
+                        whisper::transcribe(temp_audio.path(), &config).await
+                    })
+                })
+            }).await?;
+            (transcript, detections)
+        }
+        Some(detections) => (Vec::new(), detections),
+        None => {
+            let transcript = progress.with_spinner("Analyzing audio for swear words", |_pb| {
+                tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(async {
+                        whisper::transcribe(temp_audio.path(), &config).await
+                    })
+                })
+            }).await?;
+            let detections: Vec<_> = transcript.iter().filter(|d| d.is_swear).cloned().collect();
+            (transcript, detections)
+        }
+    };
+
     info!("Found {} swear word segments", detections.len());
 
+    let detected = detections.clone();
+    if config.refine_segments && config.label_file.is_none() && !detections.is_empty() {
+        progress.with_spinner("Refining segment boundaries", |_pb| {
+            tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(async {
+                    refine::refine_detections(temp_audio.path(), &mut detections, config.refine_window as f64).await
+                })
+            })
+        }).await?;
+    }
+
+    // Mask the same words in soft subtitles, which are remuxed with the new audio
+    let render_subtitles = config.censor_subtitles && !config.detect_only && !audio_output;
+    let mut subtitle_tracks = if render_subtitles && !probe.subtitle_streams.is_empty() {
+        progress.with_spinner("Censoring subtitle tracks", |_pb| {
+            tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(async {
+                    babymode::subtitles::censor_tracks(
+                        &config.input_file,
+                        &probe.subtitle_streams,
+                        &config.swear_words,
+                        &config.subtitle_mask,
+                    ).await
+                })
+            })
+        }).await?
+    } else {
+        Vec::new()
+    };
+    let mut subtitles_changed = subtitle_tracks.iter().any(|t| !t.changes.is_empty());
+    run_report.subtitles = subtitle_tracks.iter().map(|t| t.report()).collect();
+
+    // Captions from the transcript, masked like the subtitle tracks
+    if want_captions {
+        let options = babymode::CaptionOptions::from(&config);
+        let captions = captions::build_captions(&transcript, &config.swear_words, &config.subtitle_mask, &options);
+        run_report.captions = captions::changes(&captions);
+        let mux_track = progress.with_spinner("Writing captions", |_pb| {
+            tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(async {
+                    captions::write_sidecars(&captions, &output_file, &config.caption_formats, &options).await?;
+                    if !config.mux_captions {
+                        return Ok(None);
+                    }
+                    if audio_output {
+                        warn!("Captions can't be added to audio output {:?}", output_file);
+                        return Ok(None);
+                    }
+                    captions::mux_track(&captions, &options).await.map(Some)
+                })
+            })
+        }).await?;
+        if let Some(track) = mux_track {
+            subtitle_tracks.push(track);
+            subtitles_changed = true;
+        }
+    }
+
+    if config.report_file.is_some() {
+        match censoring::get_censoring_stats(temp_audio.path(), &detections, &config).await {
+            Ok(stats) => run_report.stats = Some(stats),
+            Err(e) => warn!("Could not work out censoring statistics for the report: {}", e),
+        }
+    }
+
+    // Detect-only runs hand the segments to an editor or player instead of
+    // rendering, so the full-quality audio is never extracted
+    if config.detect_only {
+        let audio_duration = audio::get_audio_duration(temp_audio.path()).await.ok();
+        let mut assignments = rules::assign_strategies(&detections, &config);
+        censoring::prepare_assignments(&mut assignments, &babymode::SegmentOptions::from(&config), audio_duration);
+        write_segment_files(&progress, &config, &output_file, &assignments, &detections).await?;
+        info!("Detect only, skipped rendering {:?}", output_file);
+        run_report.set_detections(&detected, &detections, &assignments, &config);
+        return finish_report(run_report, &progress, &config).await;
+    }
+
     if detections.is_empty() {
+        // Empty exports and sidecars replace any left from an earlier run
+        if !config.export_formats.is_empty() || !config.player_sidecars.is_empty() {
+            write_segment_files(&progress, &config, &output_file, &[], &detections).await?;
+        }
+
+        // Nothing to censor, but the loudness target still applies
+        let loudness_stage = match config.loudness {
+            Some(target) => Some(progress.with_spinner("Normalising loudness", |_pb| {
+                tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(async {
+                        let normalized = temp_file(".wav")?;
+                        let before = loudness::normalize(&config.input_file, normalized.path(), &target).await?;
+                        Ok::<_, babymode::BabymodeError>((target, before, normalized))
+                    })
+                })
+            }).await?),
+            None => None,
+        };
+        let audio_source = loudness_stage.as_ref()
+            .map(|(_, _, normalized)| normalized.path())
+            .unwrap_or(&config.input_file);
+
         progress.with_spinner("No swear words detected, creating clean copy", |_pb| {
-            std::fs::copy(&config.input_file, config.output_file.as_ref().unwrap())
+            // Re-encode only when the output has to differ from the input
+            if config.clean_copy_needs_encode(subtitles_changed) {
+                tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(async {
+                        if audio_output {
+                            audio::write_audio_file(
+                                audio_source, &config.input_file, &output_file, keep_cover_art, &output_encoding
+                            ).await
+                        } else {
+                            video::combine_video_audio(
+                                &config.input_file, audio_source, &output_file, &output_encoding, &subtitle_tracks
+                            ).await
+                        }
+                    })
+                })?;
+                return Ok(());
+            }
+            std::fs::copy(&config.input_file, &output_file)
+                .map(|_| ())
                 .map_err(|e| babymode::error::fs_error(e, config.input_file.clone()))
         }).await?;
-        info!("Clean copy created at: {:?}", config.output_file.unwrap());
-        return Ok(());
+        if let Some((target, before, _)) = loudness_stage {
+            run_report.loudness = Some(report_loudness(target, before, &output_file).await?);
+        }
+        info!("Clean copy created at: {:?}", output_file);
+        run_report.output = Some(output_file);
+        return finish_report(run_report, &progress, &config).await;
     }
 
-    // Apply censoring using selected strategy
-    let registry = StrategyRegistry::new();
-    let censoring_config = plugins::CensoringConfig {
-        volume: config.censor_volume,
-        fade_duration: config.fade_duration,
-        ..Default::default()
+    // Strategies work on the full-quality track, not the 16kHz mono copy
+    // used for analysis, so stereo content and the output quality survive
+    let programme_audio = progress.with_spinner("Extracting programme audio", |_pb| {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                audio::extract_programme_audio(&config.input_file).await
+            })
+        })
+    }).await?;
+
+    // Pick a strategy for each detection, then merge and pad each group
+    // the same way whichever strategy censors it
+    let audio_duration = match audio::get_audio_duration(programme_audio.path()).await {
+        Ok(duration) => Some(duration),
+        Err(e) => {
+            warn!("Could not read audio duration, segments will not be clamped: {}", e);
+            None
+        }
+    };
+    let mut assignments = rules::assign_strategies(&detections, &config);
+    censoring::prepare_assignments(&mut assignments, &babymode::SegmentOptions::from(&config), audio_duration);
+    for assignment in &assignments {
+        info!("Strategy {}: {} segments", assignment.strategy, assignment.segments.len());
+    }
+    if !config.export_formats.is_empty() || !config.player_sidecars.is_empty() {
+        write_segment_files(&progress, &config, &output_file, &assignments, &detections).await?;
+    }
+    run_report.set_detections(&detected, &detections, &assignments, &config);
+
+    let applying = match assignments.len() {
+        1 => format!("Applying {} censoring strategy", assignments[0].strategy),
+        n => format!("Applying {} censoring strategies", n),
     };
-    
     let temp_censored_audio = progress.with_spinner(
-        &format!("Applying {} censoring strategy", strategy_name), 
+        &applying, 
         |_pb| {
             tokio::task::block_in_place(|| {
                 tokio::runtime::Handle::current().block_on(async {
-                    let temp_file = tempfile::NamedTempFile::new()
-                        .map_err(|e| babymode::BabymodeError::Processing {
-                            message: format!("Failed to create temp file: {}", e)
-                        })?;
-                    let temp_path = temp_file.path().to_path_buf();
-                    let temp_output = babymode::TempFile::new(temp_path);
+                    let temp_output = temp_file(".wav")?;
                     
-                    let segments: Vec<_> = detections.iter()
-                        .map(|d| d.to_audio_segment())
-                        .collect();
-                    
-                    registry.apply_strategy(
-                        strategy_name,
-                        temp_audio.path(),
+                    registry.apply_mixed(
+                        programme_audio.path(),
                         temp_output.path(),
-                        &segments,
-                        &censoring_config,
+                        &assignments,
                     ).await?;
                     
                     Ok::<_, babymode::BabymodeError>(temp_output)
@@ -298,24 +1059,93 @@ async fn main() -> Result<()> {
         }
     ).await?;
 
+    // Measure before censoring, normalise the censored mix, and measure the
+    // finished file once it has been through the final encode
+    let loudness_stage = match config.loudness {
+        Some(target) => Some(progress.with_spinner("Normalising loudness", |_pb| {
+            tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(async {
+                    let before = loudness::measure(programme_audio.path(), &target).await?;
+                    let normalized = temp_file(".wav")?;
+                    loudness::normalize(temp_censored_audio.path(), normalized.path(), &target).await?;
+                    Ok::<_, babymode::BabymodeError>((target, before, normalized))
+                })
+            })
+        }).await?),
+        None => None,
+    };
+    let final_audio = loudness_stage.as_ref()
+        .map(|(_, _, normalized)| normalized)
+        .unwrap_or(&temp_censored_audio);
+
+    // Hide mouths and draw the indicator during the censored segments,
+    // re-encoding only those GOPs
+    let indicator = config.indicator.indicator()?;
+    let edited_video = if config.mouth_blur.is_none() && indicator.is_none() {
+        None
+    } else if audio_output {
+        warn!("Mouth blur and indicators need a video output, skipping them for {:?}", output_file);
+        None
+    } else {
+        let message = if config.mouth_blur.is_some() { "Hiding mouths in censored segments" } else { "Marking censored segments" };
+        Some(progress.with_spinner(message, |_pb| {
+            tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(async {
+                    let segments: Vec<_> = assignments.iter()
+                        .flat_map(|a| a.segments.iter().cloned())
+                        .collect();
+                    let mut edits = visual::VideoEdits::default();
+                    if let Some(style) = config.mouth_blur {
+                        let windows = visual::blur_windows(
+                            &config.input_file,
+                            style,
+                            &config.blur_region,
+                            &segments,
+                            config.blur_margin as f64,
+                        ).await?;
+                        edits.blur = Some((style, windows));
+                    }
+                    if let Some(ref indicator) = indicator {
+                        let windows = segments.iter().map(|s| (s.start_time, s.end_time)).collect();
+                        edits.indicator = Some((indicator.clone(), windows));
+                    }
+                    let edited = temp_file(".mkv")?;
+                    edits.apply(&config.input_file, edited.path()).await?;
+                    Ok::<_, babymode::BabymodeError>(edited)
+                })
+            })
+        }).await?)
+    };
+    let video_source = edited_video.as_ref()
+        .map(|v| v.path())
+        .unwrap_or(&config.input_file);
+
     // Combine censored audio with original video
-    progress.with_spinner("Creating final censored video", |_pb| {
+    let finishing = if audio_output { "Writing censored audio" } else { "Creating final censored video" };
+    progress.with_spinner(finishing, |_pb| {
         tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current().block_on(async {
-                video::combine_video_audio(
-                    &config.input_file,
-                    temp_censored_audio.path(),
-                    config.output_file.as_ref().unwrap()
-                ).await
+                if audio_output {
+                    audio::write_audio_file(final_audio.path(), &config.input_file, &output_file, keep_cover_art, &output_encoding).await
+                } else {
+                    video::combine_video_audio(
+                        video_source, final_audio.path(), &output_file, &output_encoding, &subtitle_tracks
+                    ).await
+                }
             })
         })
     }).await?;
+
+    if let Some((target, before, _)) = loudness_stage {
+        run_report.loudness = Some(report_loudness(target, before, &output_file).await?);
+    }
     
-    info!("✓ Successfully created censored video: {:?}", config.output_file.unwrap());
-    info!("Strategy used: {}", strategy_name);
+    info!("✓ Successfully created censored {}: {:?}", if audio_output { "audio" } else { "video" }, output_file);
+    info!("Default strategy: {}", strategy_name);
     info!("Censored {} segments", detections.len());
     
     // Temporary files will be automatically cleaned up when temp_audio and temp_censored_audio go out of scope
 
-    Ok(())
+    run_report.output = Some(output_file);
+    finish_report(run_report, &progress, &config).await
 }
\ No newline at end of file
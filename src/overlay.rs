@@ -0,0 +1,468 @@
+use log::debug;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::process::Command;
+
+use crate::audio::{self, AudioStreamInfo};
+use crate::envelope::{self, Duck, FadeCurve};
+use crate::error::{ffmpeg_error, BabymodeError, Result};
+use crate::resources::TempFile;
+use crate::tones::NoiseColor;
+
+/// How a clip is made to fit the segment it covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClipFit {
+    /// Pick trim, loop or stretch depending on how close the lengths are
+    #[default]
+    Auto,
+    /// Cut the clip off at the end of the segment
+    Trim,
+    /// Repeat the clip until the segment is covered
+    Loop,
+    /// Change the clip's tempo so it lasts exactly as long as the segment
+    Stretch,
+}
+
+impl ClipFit {
+    /// Largest tempo change `Auto` will apply before falling back to trim/loop
+    const AUTO_STRETCH_LIMIT: f64 = 1.25;
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClipFit::Auto => "auto",
+            ClipFit::Trim => "trim",
+            ClipFit::Loop => "loop",
+            ClipFit::Stretch => "stretch",
+        }
+    }
+
+    /// Resolve `Auto` for a clip of `clip_duration` over a segment of `duration`
+    pub fn resolve(&self, clip_duration: f64, duration: f64) -> ClipFit {
+        if *self != ClipFit::Auto {
+            return *self;
+        }
+        let ratio = clip_duration / duration.max(1e-3);
+        if (1.0 / Self::AUTO_STRETCH_LIMIT..=Self::AUTO_STRETCH_LIMIT).contains(&ratio) {
+            ClipFit::Stretch
+        } else if ratio < 1.0 {
+            ClipFit::Loop
+        } else {
+            ClipFit::Trim
+        }
+    }
+}
+
+impl std::str::FromStr for ClipFit {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(ClipFit::Auto),
+            "trim" => Ok(ClipFit::Trim),
+            "loop" => Ok(ClipFit::Loop),
+            "stretch" => Ok(ClipFit::Stretch),
+            _ => Err(crate::error::config_error(
+                "fit",
+                format!("Invalid clip fit '{}'. Valid options: auto, trim, loop, stretch", s)
+            )),
+        }
+    }
+}
+
+/// Where the audio of an overlay comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum OverlaySource {
+    /// `aevalsrc` expression over `t`, applied to every channel
+    Expression(String),
+    /// Full-scale `anoisesrc` noise
+    Noise(NoiseColor),
+    /// An audio file, fitted to the overlay duration
+    Clip {
+        path: PathBuf,
+        /// Seconds skipped at the start of the file
+        offset: f64,
+        /// Length of the clip after `offset`
+        clip_duration: f64,
+        fit: ClipFit,
+    },
+    /// The programme audio under the overlay itself, run through `filters`
+    /// (an ffmpeg filter chain such as `areverse`). The span is read as its
+    /// own seeked input, so only its own samples are ever buffered.
+    Span { filters: String },
+}
+
+/// `atempo` chain changing speed by `factor`, split into steps that stay
+/// inside the filter's supported 0.5-100 range
+pub(crate) fn atempo_chain(factor: f64) -> Vec<String> {
+    let mut remaining = factor;
+    let mut chain = Vec::new();
+    while remaining < 0.5 {
+        chain.push("atempo=0.5".to_string());
+        remaining /= 0.5;
+    }
+    while remaining > 100.0 {
+        chain.push("atempo=100".to_string());
+        remaining /= 100.0;
+    }
+    chain.push(format!("atempo={:.6}", remaining));
+    chain
+}
+
+/// Audio placed on top of the programme during a censored segment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlay {
+    pub source: OverlaySource,
+    pub start: f64,
+    pub duration: f64,
+    /// Linear gain applied to the source
+    pub gain: f32,
+    /// Fade length at both ends of the overlay, in seconds
+    pub edge: f32,
+    pub curve: FadeCurve,
+}
+
+impl Overlay {
+    /// Filter chain producing this overlay, delayed to its start time.
+    /// `input` is the ffmpeg input index for clip and span sources.
+    fn filter_chain(&self, format: &AudioStreamInfo, input: Option<usize>) -> String {
+        let mut chain = match &self.source {
+            OverlaySource::Expression(expr) => vec![format!(
+                "aevalsrc=exprs='{}':c={}:s={}:d={:.4}",
+                expr, format.layout(), format.sample_rate, self.duration
+            )],
+            OverlaySource::Noise(color) => vec![format!(
+                "anoisesrc=color={}:amplitude=1:sample_rate={}:duration={:.4},aformat=channel_layouts={}",
+                color.as_str(), format.sample_rate, self.duration, format.layout()
+            )],
+            OverlaySource::Clip { clip_duration, fit, .. } => {
+                let mut chain = vec![format!(
+                    "[{}:a]aformat=sample_fmts=fltp:sample_rates={}:channel_layouts={}",
+                    input.unwrap_or(1), format.sample_rate, format.layout()
+                )];
+                if fit.resolve(*clip_duration, self.duration) == ClipFit::Stretch {
+                    chain.extend(atempo_chain(clip_duration / self.duration.max(1e-3)));
+                }
+                // Looped inputs are endless, short ones are padded; either way
+                // the result is cut to exactly the overlay duration
+                chain.push(format!("apad,atrim=0:{:.4},asetpts=PTS-STARTPTS", self.duration));
+                chain
+            }
+            OverlaySource::Span { filters } => vec![
+                format!(
+                    "[{}:a]atrim=0:{:.4},asetpts=PTS-STARTPTS",
+                    input.unwrap_or(1), self.duration
+                ),
+                filters.clone(),
+            ],
+        };
+
+        let edge = (self.edge.max(0.0) as f64).min(self.duration / 2.0);
+        if edge > 0.0 {
+            chain.push(format!(
+                "afade=t=in:st=0:d={:.4}:curve={}",
+                edge, self.curve.afade_name()
+            ));
+            chain.push(format!(
+                "afade=t=out:st={:.4}:d={:.4}:curve={}",
+                self.duration - edge, edge, self.curve.afade_name()
+            ));
+        }
+
+        chain.push(format!("volume={:.6}", self.gain));
+        let delay = (self.start.max(0.0) * format.sample_rate as f64).round() as u64;
+        chain.push(format!("adelay=delays={}S:all=1", delay));
+        chain.join(",")
+    }
+
+    /// Whether the clip input has to be read in a loop
+    fn loops_input(&self) -> bool {
+        match &self.source {
+            OverlaySource::Clip { clip_duration, fit, .. } => {
+                fit.resolve(*clip_duration, self.duration) == ClipFit::Loop
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Everything a strategy wants done to the programme audio, rendered in a
+/// single ffmpeg pass: the original is attenuated by `ducks` and each
+/// overlay is mixed on top
+#[derive(Debug, Clone, Default)]
+pub struct RenderPlan {
+    pub ducks: Vec<Duck>,
+    pub overlays: Vec<Overlay>,
+    /// Files read by clip overlays, kept until the plan is dropped
+    pub temp_files: Vec<Arc<TempFile>>,
+}
+
+impl RenderPlan {
+    pub fn is_empty(&self) -> bool {
+        self.ducks.is_empty() && self.overlays.is_empty()
+    }
+
+    /// Add another plan's work to this one, so both render in one pass
+    pub fn merge(&mut self, other: RenderPlan) {
+        self.ducks.extend(other.ducks);
+        self.overlays.extend(other.overlays);
+        self.temp_files.extend(other.temp_files);
+    }
+
+    /// Extra ffmpeg input arguments for clip and span overlays, in input
+    /// index order. Spans re-read `programme` seeked to their own range, so
+    /// they never hold back the main input while waiting for their start.
+    fn input_args(&self, programme: &Path) -> Vec<String> {
+        let mut args = Vec::new();
+        for overlay in &self.overlays {
+            match &overlay.source {
+                OverlaySource::Clip { path, offset, .. } => {
+                    if overlay.loops_input() {
+                        args.extend(["-stream_loop".to_string(), "-1".to_string()]);
+                    }
+                    if *offset > 0.0 {
+                        args.extend(["-ss".to_string(), format!("{:.4}", offset)]);
+                    }
+                    args.extend(["-i".to_string(), path.to_string_lossy().to_string()]);
+                }
+                OverlaySource::Span { .. } => {
+                    args.extend([
+                        "-ss".to_string(), format!("{:.4}", overlay.start.max(0.0)),
+                        "-t".to_string(), format!("{:.4}", overlay.duration),
+                        "-i".to_string(), programme.to_string_lossy().to_string(),
+                    ]);
+                }
+                _ => {}
+            }
+        }
+        args
+    }
+
+    /// Build the `-filter_complex` graph for this plan, with output label `[out]`
+    pub fn filter_graph(&self, format: &AudioStreamInfo) -> String {
+        let mut graph = vec![format!("[0:a]{}[dry]", envelope::volume_filter(&self.ducks))];
+
+        let mut mix_inputs = "[dry]".to_string();
+        let mut next_input = 1;
+
+        for (i, overlay) in self.overlays.iter().enumerate() {
+            let input = match overlay.source {
+                OverlaySource::Clip { .. } | OverlaySource::Span { .. } => {
+                    next_input += 1;
+                    Some(next_input - 1)
+                }
+                _ => None,
+            };
+            graph.push(format!("{}[ov{}]", overlay.filter_chain(format, input), i));
+            mix_inputs.push_str(&format!("[ov{}]", i));
+        }
+
+        if self.overlays.is_empty() {
+            graph.push("[dry]anull[out]".to_string());
+        } else {
+            graph.push(format!(
+                "{}amix=inputs={}:duration=first:dropout_transition=0:normalize=0[out]",
+                mix_inputs, self.overlays.len() + 1
+            ));
+        }
+        graph.join(";")
+    }
+}
+
+/// Render a plan from `input_path` into a PCM WAV at `output_path`
+pub async fn render(input_path: &Path, output_path: &Path, plan: &RenderPlan) -> Result<()> {
+    if plan.is_empty() {
+        tokio::fs::copy(input_path, output_path).await
+            .map_err(|e| crate::error::fs_error(e, output_path.to_path_buf()))?;
+        return Ok(());
+    }
+
+    let format = audio::probe_audio_stream(input_path).await?;
+    let filter_complex = plan.filter_graph(&format);
+    debug!("Render filter: {}", filter_complex);
+
+    let output = Command::new("ffmpeg")
+        .args(["-i", input_path.to_str().ok_or_else(|| invalid_path(input_path))?])
+        .args(plan.input_args(input_path))
+        .args([
+            "-filter_complex", &filter_complex,
+            "-map", "[out]",
+            "-c:a", "pcm_s16le",
+            "-y",
+            output_path.to_str().ok_or_else(|| invalid_path(output_path))?,
+        ])
+        .output()
+        .await
+        .map_err(|e| ffmpeg_error(format!("Failed to execute ffmpeg: {}", e), None))?;
+
+    if !output.status.success() {
+        return Err(ffmpeg_error(
+            "Failed to render censored audio",
+            Some(String::from_utf8_lossy(&output.stderr).to_string()),
+        ));
+    }
+
+    Ok(())
+}
+
+fn invalid_path(path: &Path) -> BabymodeError {
+    BabymodeError::Processing {
+        message: format!("Invalid path encoding: {}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioSegment;
+
+    fn stereo() -> AudioStreamInfo {
+        AudioStreamInfo {
+            sample_rate: 48000,
+            channels: 2,
+            channel_layout: Some("stereo".to_string()),
+            codec: "pcm_s16le".to_string(),
+            bit_rate: None,
+        }
+    }
+
+    #[test]
+    fn test_overlay_is_placed_at_segment_start() {
+        let overlay = Overlay {
+            source: OverlaySource::Expression("sin(2*PI*1000*t)".to_string()),
+            start: 1.5,
+            duration: 0.5,
+            gain: 0.25,
+            edge: 0.01,
+            curve: FadeCurve::EqualPower,
+        };
+        let chain = overlay.filter_chain(&stereo(), None);
+        assert!(chain.starts_with("aevalsrc=exprs='sin(2*PI*1000*t)':c=stereo:s=48000:d=0.5000"));
+        assert!(chain.contains("afade=t=out:st=0.4900:d=0.0100:curve=qsin"));
+        assert!(chain.ends_with("adelay=delays=72000S:all=1"));
+    }
+
+    #[test]
+    fn test_filter_graph_mixes_every_overlay_once() {
+        let segment = AudioSegment::new(1.0, 2.0);
+        let overlay = |start| Overlay {
+            source: OverlaySource::Expression("0".to_string()),
+            start,
+            duration: 1.0,
+            gain: 1.0,
+            edge: 0.0,
+            curve: FadeCurve::Linear,
+        };
+        let plan = RenderPlan {
+            ducks: envelope::ducks_for_segments(&[segment], 0.0, 0.01, FadeCurve::Linear),
+            overlays: vec![overlay(1.0), overlay(5.0)],
+            ..Default::default()
+        };
+        let graph = plan.filter_graph(&stereo());
+        assert_eq!(graph.matches("[0:a]").count(), 1);
+        assert!(graph.contains("[dry][ov0][ov1]amix=inputs=3"));
+        assert!(graph.ends_with("[out]"));
+    }
+
+    #[test]
+    fn test_span_overlays_read_their_own_range() {
+        let span = |start| Overlay {
+            source: OverlaySource::Span { filters: "areverse".to_string() },
+            start,
+            duration: 0.5,
+            gain: 1.0,
+            edge: 0.02,
+            curve: FadeCurve::EqualPower,
+        };
+        let plan = RenderPlan {
+            ducks: Vec::new(),
+            overlays: vec![span(1.0), span(4.0)],
+            ..Default::default()
+        };
+        assert_eq!(
+            plan.input_args(Path::new("in.wav")),
+            vec!["-ss", "1.0000", "-t", "0.5000", "-i", "in.wav",
+                 "-ss", "4.0000", "-t", "0.5000", "-i", "in.wav"]
+        );
+        let graph = plan.filter_graph(&stereo());
+        assert!(graph.starts_with("[0:a]anull[dry]"));
+        assert!(!graph.contains("asplit"));
+        assert!(graph.contains("[2:a]atrim=0:0.5000,asetpts=PTS-STARTPTS,areverse"));
+        assert!(graph.contains("adelay=delays=192000S:all=1[ov1]"));
+    }
+
+    #[test]
+    fn test_merged_plans_render_together() {
+        let beep = RenderPlan {
+            ducks: envelope::ducks_for_segments(&[AudioSegment::new(1.0, 1.5)], 0.0, 0.01, FadeCurve::Linear),
+            overlays: vec![Overlay {
+                source: OverlaySource::Expression("sin(2*PI*1000*t)".to_string()),
+                start: 1.0,
+                duration: 0.5,
+                gain: 0.5,
+                edge: 0.01,
+                curve: FadeCurve::Linear,
+            }],
+            ..Default::default()
+        };
+        let reverse = RenderPlan {
+            ducks: envelope::ducks_for_segments(&[AudioSegment::new(3.0, 3.4)], 0.0, 0.02, FadeCurve::Linear),
+            overlays: vec![Overlay {
+                source: OverlaySource::Span { filters: "areverse".to_string() },
+                start: 3.0,
+                duration: 0.4,
+                gain: 1.0,
+                edge: 0.02,
+                curve: FadeCurve::Linear,
+            }],
+            ..Default::default()
+        };
+
+        let mut plan = RenderPlan::default();
+        plan.merge(beep);
+        plan.merge(reverse);
+        let graph = plan.filter_graph(&stereo());
+        assert!(graph.contains("[1:a]atrim=0:0.4000"));
+        assert_eq!(graph.matches("volume=enable").count(), 2);
+        assert!(graph.contains("[dry][ov0][ov1]amix=inputs=3"));
+    }
+
+    #[test]
+    fn test_clip_fit_resolution() {
+        assert_eq!(ClipFit::Auto.resolve(1.1, 1.0), ClipFit::Stretch);
+        assert_eq!(ClipFit::Auto.resolve(0.3, 1.0), ClipFit::Loop);
+        assert_eq!(ClipFit::Auto.resolve(3.0, 1.0), ClipFit::Trim);
+        assert_eq!(ClipFit::Trim.resolve(0.3, 1.0), ClipFit::Trim);
+    }
+
+    #[test]
+    fn test_clip_overlays_get_their_own_inputs() {
+        let clip = |fit, start| Overlay {
+            source: OverlaySource::Clip {
+                path: PathBuf::from("boing.wav"),
+                offset: 0.0,
+                clip_duration: 0.25,
+                fit,
+            },
+            start,
+            duration: 1.0,
+            gain: 0.5,
+            edge: 0.02,
+            curve: FadeCurve::Linear,
+        };
+        let plan = RenderPlan {
+            ducks: Vec::new(),
+            overlays: vec![clip(ClipFit::Loop, 1.0), clip(ClipFit::Stretch, 3.0)],
+            ..Default::default()
+        };
+        assert_eq!(
+            plan.input_args(Path::new("in.wav")),
+            vec!["-stream_loop", "-1", "-i", "boing.wav", "-i", "boing.wav"]
+        );
+
+        let graph = plan.filter_graph(&stereo());
+        assert!(graph.contains("[1:a]aformat"));
+        assert!(graph.contains("[2:a]aformat"));
+        // 0.25s stretched to 1s needs two atempo steps
+        assert!(graph.contains("atempo=0.5,atempo=0.500000"));
+    }
+}
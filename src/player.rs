@@ -0,0 +1,207 @@
+use anyhow::{Context, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::error::{config_error, BabymodeError};
+use crate::export::ExportSegment;
+
+/// .NET ticks (100 ns) per second, the unit of Jellyfin media segments
+const TICKS_PER_SECOND: f64 = 10_000_000.0;
+
+/// Sidecar files that let a media player mute the original file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayerFormat {
+    /// Kodi and MPlayer `.edl`, picked up automatically next to the video
+    Kodi,
+    /// MPV Lua script, loaded with `--script`
+    Mpv,
+    /// Jellyfin-style media segments JSON
+    Segments,
+}
+
+impl PlayerFormat {
+    pub const ALL: [PlayerFormat; 3] = [PlayerFormat::Kodi, PlayerFormat::Mpv, PlayerFormat::Segments];
+
+    /// Suffix replacing the input's extension
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PlayerFormat::Kodi => "edl",
+            PlayerFormat::Mpv => "mute.lua",
+            PlayerFormat::Segments => "segments.json",
+        }
+    }
+}
+
+impl std::str::FromStr for PlayerFormat {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "kodi" | "mplayer" => Ok(PlayerFormat::Kodi),
+            "mpv" => Ok(PlayerFormat::Mpv),
+            "segments" | "jellyfin" | "plex" => Ok(PlayerFormat::Segments),
+            _ => Err(config_error(
+                "player_sidecars",
+                format!("Invalid player sidecar '{}'. Valid options: kodi, mpv, segments", s)
+            )),
+        }
+    }
+}
+
+/// Kodi/MPlayer EDL: `start end action` per line, action 1 being mute
+pub fn render_kodi(segments: &[ExportSegment]) -> String {
+    segments.iter()
+        .map(|s| format!("{:.3}\t{:.3}\t1\n", s.start, s.end))
+        .collect()
+}
+
+/// `text` as a double-quoted Lua 5.1 string literal. Control characters
+/// use decimal escapes, which LuaJIT and every Lua version understand.
+fn lua_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() && c.is_ascii() => out.push_str(&format!("\\{:03}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// MPV script muting the segments while `file_name` plays, and leaving
+/// other files alone
+pub fn render_mpv(segments: &[ExportSegment], file_name: &str) -> String {
+    let list: String = segments.iter()
+        .map(|s| format!("    {{{:.3}, {:.3}}},\n", s.start, s.end))
+        .collect();
+    format!(
+        r#"-- Mutes censored words in {name}: mpv --script={name_stem}.mute.lua {name}
+local file = {file}
+local segments = {{
+{list}}}
+
+local active = false
+local muted = false
+
+local function update(_, pos)
+    if not active or pos == nil then
+        return
+    end
+    local inside = false
+    for _, segment in ipairs(segments) do
+        if pos >= segment[1] and pos < segment[2] then
+            inside = true
+            break
+        end
+    end
+    if inside ~= muted then
+        muted = inside
+        mp.set_property_bool("mute", inside)
+    end
+end
+
+mp.register_event("file-loaded", function()
+    active = mp.get_property("filename") == file
+    if not active and muted then
+        muted = false
+        mp.set_property_bool("mute", false)
+    end
+end)
+mp.observe_property("time-pos", "number", update)
+"#,
+        // Keep the usage comment on one line whatever the name holds
+        name = file_name.replace(char::is_control, "?"),
+        name_stem = Path::new(file_name).file_stem().unwrap_or_default().to_string_lossy().replace(char::is_control, "?"),
+        file = lua_string(file_name),
+        list = list,
+    )
+}
+
+/// Media segments JSON: Jellyfin's tick-based fields, plus seconds and the
+/// censored words for tools that want them
+pub fn render_segments(segments: &[ExportSegment], file_name: &str) -> String {
+    let entries: Vec<serde_json::Value> = segments.iter()
+        .map(|s| serde_json::json!({
+            "Type": "Unknown",
+            "StartTicks": (s.start * TICKS_PER_SECOND).round() as u64,
+            "EndTicks": (s.end * TICKS_PER_SECOND).round() as u64,
+            "Start": s.start,
+            "End": s.end,
+            "Action": "Mute",
+            "Words": s.words,
+        }))
+        .collect();
+    let document = serde_json::json!({
+        "File": file_name,
+        "Segments": entries,
+    });
+    serde_json::to_string_pretty(&document).unwrap_or_default() + "\n"
+}
+
+/// `segments` in `format`, for `file_name`
+pub fn render(format: PlayerFormat, segments: &[ExportSegment], file_name: &str) -> String {
+    match format {
+        PlayerFormat::Kodi => render_kodi(segments),
+        PlayerFormat::Mpv => render_mpv(segments, file_name),
+        PlayerFormat::Segments => render_segments(segments, file_name),
+    }
+}
+
+/// Sidecar path for `input_path`, e.g. `film.edl` next to `film.mkv`
+pub fn sidecar_path(input_path: &Path, format: PlayerFormat) -> PathBuf {
+    input_path.with_extension(format.extension())
+}
+
+/// Write a sidecar next to `input_path` for each of `formats`
+pub async fn write_sidecars(
+    segments: &[ExportSegment],
+    input_path: &Path,
+    formats: &[PlayerFormat],
+) -> Result<Vec<PathBuf>> {
+    let file_name = input_path.file_name().unwrap_or_default().to_string_lossy();
+    let mut paths = Vec::new();
+    for &format in formats {
+        let path = sidecar_path(input_path, format);
+        tokio::fs::write(&path, render(format, segments, &file_name)).await
+            .with_context(|| format!("Failed to write player sidecar to {:?}", path))?;
+        info!("Wrote {} mutes to {:?}", segments.len(), path);
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let segments = vec![
+            ExportSegment { start: 1.0, end: 1.5, strategy: "beep".to_string(), words: vec!["damn".to_string()] },
+            ExportSegment { start: 62.25, end: 63.0, strategy: "mute".to_string(), words: vec!["hell".to_string()] },
+        ];
+        assert_eq!(render_kodi(&segments), "1.000\t1.500\t1\n62.250\t63.000\t1\n");
+
+        let lua = render_mpv(&segments, "film.mkv");
+        assert!(lua.starts_with("-- Mutes censored words in film.mkv: mpv --script=film.mute.lua film.mkv\n"));
+        assert!(lua.contains("local file = \"film.mkv\"\nlocal segments = {\n    {1.000, 1.500},\n    {62.250, 63.000},\n}\n"));
+
+        let lua = render_mpv(&segments, "a \"b\"\\c\u{1b}\n.mkv");
+        assert!(lua.starts_with("-- Mutes censored words in a \"b\"\\c??.mkv:"));
+        assert!(lua.contains("local file = \"a \\\"b\\\"\\\\c\\027\\010.mkv\"\n"));
+        assert_eq!(lua_string("caf\u{e9}"), "\"caf\u{e9}\"");
+
+        let json: serde_json::Value = serde_json::from_str(&render_segments(&segments, "film.mkv")).unwrap();
+        assert_eq!(json["Segments"][1]["StartTicks"], 622_500_000);
+        assert_eq!(json["Segments"][0]["Words"][0], "damn");
+
+        assert_eq!(sidecar_path(Path::new("/tv/film.mkv"), PlayerFormat::Mpv), PathBuf::from("/tv/film.mute.lua"));
+        assert_eq!("jellyfin".parse::<PlayerFormat>().unwrap(), PlayerFormat::Segments);
+    }
+}
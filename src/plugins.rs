@@ -1,11 +1,18 @@
-use crate::audio::AudioSegment;
-use crate::error::{BabymodeError, Result};
+use crate::audio::{self, AudioSegment, AudioStreamInfo};
+use crate::config::Config;
+use crate::envelope::{self, FadeCurve};
+use crate::overlay::{self, ClipFit, Overlay, OverlaySource, RenderPlan};
+use crate::tones::{self, BeepPreset, NoiseColor};
+use crate::error::{config_error, BabymodeError, Result};
+use crate::resources::TempFile;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
-use std::path::Path;
-use tokio::process::Command;
-use log::{debug, info};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use log::{debug, info, warn};
 
 /// Trait defining a censoring strategy plugin
 #[async_trait]
@@ -15,7 +22,12 @@ pub trait CensoringStrategy: Send + Sync {
     
     /// Description of what this strategy does
     fn description(&self) -> &str;
-    
+
+    /// Parameters this strategy reads from `CensoringConfig::custom_params`
+    fn parameters(&self) -> Vec<ParamSpec> {
+        Vec::new()
+    }
+
     /// Apply censoring to the given audio segments
     async fn apply_censoring(
         &self,
@@ -25,6 +37,19 @@ pub trait CensoringStrategy: Send + Sync {
         config: &CensoringConfig,
     ) -> Result<()>;
     
+    /// Describe the censoring as a render plan instead of writing audio, so
+    /// it can share a single ffmpeg pass with other strategies. Strategies
+    /// that return `None` are run on their own through `apply_censoring`.
+    async fn plan(
+        &self,
+        input_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<Option<RenderPlan>> {
+        let _ = (input_path, segments, config);
+        Ok(None)
+    }
+
     /// Validate configuration for this strategy
     fn validate_config(&self, config: &CensoringConfig) -> Result<()> {
         // Default implementation - no validation required
@@ -38,9 +63,33 @@ pub trait CensoringStrategy: Send + Sync {
 pub struct CensoringConfig {
     pub volume: f32,
     pub fade_duration: f32,
+    #[serde(default)]
+    pub fade_curve: FadeCurve,
     pub replacement_audio: Option<String>,
     pub beep_frequency: Option<f32>,
-    pub custom_params: HashMap<String, serde_json::Value>,
+    pub custom_params: HashMap<String, Value>,
+}
+
+impl CensoringConfig {
+    /// String value of a strategy-specific parameter
+    pub fn param_str(&self, key: &str) -> Option<&str> {
+        self.custom_params.get(key).and_then(|v| v.as_str())
+    }
+
+    /// Numeric value of a strategy-specific parameter
+    pub fn param_f64(&self, key: &str) -> Option<f64> {
+        self.custom_params.get(key).and_then(|v| v.as_f64())
+    }
+
+    /// Whole-number value of a strategy-specific parameter
+    pub fn param_i64(&self, key: &str) -> Option<i64> {
+        self.custom_params.get(key).and_then(|v| v.as_i64())
+    }
+
+    /// Boolean value of a strategy-specific parameter
+    pub fn param_bool(&self, key: &str) -> Option<bool> {
+        self.custom_params.get(key).and_then(|v| v.as_bool())
+    }
 }
 
 impl Default for CensoringConfig {
@@ -48,6 +97,7 @@ impl Default for CensoringConfig {
         Self {
             volume: 0.1,
             fade_duration: 0.2,
+            fade_curve: FadeCurve::default(),
             replacement_audio: None,
             beep_frequency: Some(1000.0),
             custom_params: HashMap::new(),
@@ -55,6 +105,205 @@ impl Default for CensoringConfig {
     }
 }
 
+impl CensoringConfig {
+    /// Censoring settings for `strategy`, with its parameters from `config`
+    pub fn for_strategy(config: &Config, strategy: &str) -> Self {
+        Self {
+            volume: config.censor_volume,
+            fade_duration: config.fade_duration,
+            fade_curve: config.fade_curve,
+            replacement_audio: config.replacement_audio.as_ref()
+                .map(|p| p.to_string_lossy().to_string()),
+            custom_params: config.strategy_params.get(strategy)
+                .cloned()
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&Config> for CensoringConfig {
+    /// Censoring settings for the strategy selected in `config`
+    fn from(config: &Config) -> Self {
+        Self::for_strategy(config, &config.strategy)
+    }
+}
+
+/// Type and valid values of a strategy parameter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ParamKind {
+    Number { min: f64, max: f64 },
+    Integer { min: i64, max: i64 },
+    Bool,
+    Choice {
+        options: Vec<String>,
+        /// Other accepted spellings, mapped to the option they stand for
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        aliases: HashMap<String, String>,
+    },
+    Text,
+}
+
+/// Declaration of one strategy parameter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamSpec {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: ParamKind,
+    #[serde(default)]
+    pub default: Option<Value>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl ParamSpec {
+    fn new(name: &str, kind: ParamKind, default: Option<Value>, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            default,
+            description: description.to_string(),
+        }
+    }
+
+    pub fn number(name: &str, min: f64, max: f64, default: Option<f64>, description: &str) -> Self {
+        Self::new(name, ParamKind::Number { min, max }, default.map(Value::from), description)
+    }
+
+    pub fn integer(name: &str, min: i64, max: i64, default: Option<i64>, description: &str) -> Self {
+        Self::new(name, ParamKind::Integer { min, max }, default.map(Value::from), description)
+    }
+
+    pub fn bool(name: &str, default: Option<bool>, description: &str) -> Self {
+        Self::new(name, ParamKind::Bool, default.map(Value::from), description)
+    }
+
+    pub fn choice(name: &str, options: &[&str], default: Option<&str>, description: &str) -> Self {
+        Self::choice_with_aliases(name, options, &[], default, description)
+    }
+
+    /// Choice that also accepts `aliases`, each stored as the option it names
+    pub fn choice_with_aliases(
+        name: &str,
+        options: &[&str],
+        aliases: &[(&str, &str)],
+        default: Option<&str>,
+        description: &str,
+    ) -> Self {
+        let options = options.iter().map(|o| o.to_string()).collect();
+        let aliases = aliases.iter().map(|(alias, option)| (alias.to_string(), option.to_string())).collect();
+        Self::new(name, ParamKind::Choice { options, aliases }, default.map(Value::from), description)
+    }
+
+    pub fn text(name: &str, description: &str) -> Self {
+        Self::new(name, ParamKind::Text, None, description)
+    }
+
+    /// Check `value` against this parameter and convert it to the declared
+    /// type. Strings are parsed, so values from the command line work too.
+    pub fn coerce(&self, value: &Value) -> Result<Value> {
+        let invalid = |message: String| config_error(self.name.as_str(), message);
+        match &self.kind {
+            ParamKind::Number { min, max } => {
+                let number = match value {
+                    Value::String(s) => s.trim().parse::<f64>().ok(),
+                    other => other.as_f64(),
+                }
+                .filter(|n| n.is_finite())
+                .ok_or_else(|| invalid(format!("Expected a number, got {}", value)))?;
+                if !(*min..=*max).contains(&number) {
+                    return Err(invalid(format!("Must be between {} and {}, got {}", min, max, number)));
+                }
+                Ok(Value::from(number))
+            }
+            ParamKind::Integer { min, max } => {
+                let number = match value {
+                    Value::String(s) => s.trim().parse::<i64>().ok(),
+                    other => other.as_i64(),
+                }
+                .ok_or_else(|| invalid(format!("Expected a whole number, got {}", value)))?;
+                if !(*min..=*max).contains(&number) {
+                    return Err(invalid(format!("Must be between {} and {}, got {}", min, max, number)));
+                }
+                Ok(Value::from(number))
+            }
+            ParamKind::Bool => match value {
+                Value::Bool(b) => Ok(Value::Bool(*b)),
+                Value::String(s) => match s.trim().to_lowercase().as_str() {
+                    "true" | "yes" | "on" | "1" => Ok(Value::Bool(true)),
+                    "false" | "no" | "off" | "0" => Ok(Value::Bool(false)),
+                    _ => Err(invalid(format!("Expected true or false, got '{}'", s))),
+                },
+                other => Err(invalid(format!("Expected true or false, got {}", other))),
+            },
+            ParamKind::Choice { options, aliases } => {
+                let choice = value.as_str()
+                    .map(|s| s.trim().to_lowercase().replace('-', "_"))
+                    .map(|s| aliases.get(&s).cloned().unwrap_or(s))
+                    .filter(|s| options.contains(s))
+                    .ok_or_else(|| invalid(format!(
+                        "Invalid value {}. Valid options: {}", value, options.join(", ")
+                    )))?;
+                Ok(Value::String(choice))
+            }
+            ParamKind::Text => value.as_str()
+                .map(|s| Value::String(s.to_string()))
+                .ok_or_else(|| invalid(format!("Expected text, got {}", value))),
+        }
+    }
+
+    /// Short description of the accepted values, e.g. `number 0-0.5`
+    pub fn summary(&self) -> String {
+        let kind = match &self.kind {
+            ParamKind::Number { min, max } => format!("number {}-{}", min, max),
+            ParamKind::Integer { min, max } => format!("integer {}-{}", min, max),
+            ParamKind::Bool => "bool".to_string(),
+            ParamKind::Choice { options, .. } => options.join("|"),
+            ParamKind::Text => "text".to_string(),
+        };
+        match &self.default {
+            Some(default) => format!("{}, default {}", kind, default),
+            None => kind,
+        }
+    }
+}
+
+/// Check `params` against a strategy's schema, converting every value to its
+/// declared type and filling in defaults for parameters that were not set
+pub fn resolve_params(
+    strategy_name: &str,
+    specs: &[ParamSpec],
+    params: &HashMap<String, Value>,
+) -> Result<HashMap<String, Value>> {
+    if let Some(unknown) = params.keys().find(|key| !specs.iter().any(|s| &s.name == *key)) {
+        let valid: Vec<&str> = specs.iter().map(|s| s.name.as_str()).collect();
+        return Err(config_error(
+            unknown.as_str(),
+            if valid.is_empty() {
+                format!("Strategy '{}' takes no parameters", strategy_name)
+            } else {
+                format!("Unknown parameter for strategy '{}'. Valid parameters: {}", strategy_name, valid.join(", "))
+            },
+        ));
+    }
+
+    let mut resolved = HashMap::with_capacity(specs.len());
+    for spec in specs {
+        match params.get(&spec.name) {
+            Some(value) => {
+                resolved.insert(spec.name.clone(), spec.coerce(value)?);
+            }
+            None => {
+                if let Some(default) = &spec.default {
+                    resolved.insert(spec.name.clone(), default.clone());
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
 /// Registry of available censoring strategies
 pub struct StrategyRegistry {
     strategies: HashMap<String, Box<dyn CensoringStrategy>>,
@@ -71,6 +320,10 @@ impl StrategyRegistry {
         registry.register(Box::new(VolumeReductionStrategy));
         registry.register(Box::new(BeepStrategy));
         registry.register(Box::new(ReverseAudioStrategy));
+        registry.register(Box::new(ReplacementStrategy));
+        registry.register(Box::new(NoiseStrategy));
+        registry.register(Box::new(PitchShiftStrategy));
+        registry.register(Box::new(VocalRemovalStrategy));
         
         registry
     }
@@ -80,15 +333,47 @@ impl StrategyRegistry {
         self.strategies.insert(name, strategy);
     }
     
+    /// Register the external strategies found in `dir`, returning how many
+    /// were added. Plugins cannot replace a strategy that is already registered.
+    pub async fn load_plugins(&mut self, dir: &Path) -> usize {
+        let mut added = 0;
+        for plugin in crate::external::discover(dir).await {
+            if self.strategies.contains_key(plugin.name()) {
+                warn!("Ignoring plugin {}: strategy '{}' already exists",
+                      plugin.program().display(), plugin.name());
+                continue;
+            }
+            self.register(Box::new(plugin));
+            added += 1;
+        }
+        added
+    }
+
     pub fn get_strategy(&self, name: &str) -> Option<&dyn CensoringStrategy> {
         self.strategies.get(name).map(|s| s.as_ref())
     }
     
     pub fn list_strategies(&self) -> Vec<(&str, &str)> {
-        self.strategies
+        let mut strategies: Vec<(&str, &str)> = self.strategies
             .values()
             .map(|s| (s.name(), s.description()))
-            .collect()
+            .collect();
+        strategies.sort();
+        strategies
+    }
+
+    /// Copy of `config` with its parameters checked against the strategy's
+    /// schema and defaults filled in
+    pub fn resolve_config(&self, strategy_name: &str, config: &CensoringConfig) -> Result<CensoringConfig> {
+        let strategy = self.get_strategy(strategy_name)
+            .ok_or_else(|| BabymodeError::Processing {
+                message: format!("Unknown censoring strategy: {}", strategy_name),
+            })?;
+
+        let mut resolved = config.clone();
+        resolved.custom_params = resolve_params(strategy_name, &strategy.parameters(), &config.custom_params)?;
+        strategy.validate_config(&resolved)?;
+        Ok(resolved)
     }
     
     pub async fn apply_strategy(
@@ -99,16 +384,110 @@ impl StrategyRegistry {
         segments: &[AudioSegment],
         config: &CensoringConfig,
     ) -> Result<()> {
-        let strategy = self.get_strategy(strategy_name)
-            .ok_or_else(|| BabymodeError::Processing {
-                message: format!("Unknown censoring strategy: {}", strategy_name),
-            })?;
-        
-        strategy.validate_config(config)?;
-        strategy.apply_censoring(input_path, output_path, segments, config).await
+        let assignment = StrategyAssignment {
+            strategy: strategy_name.to_string(),
+            rule: None,
+            config: config.clone(),
+            segments: segments.to_vec(),
+        };
+        self.apply_mixed(input_path, output_path, std::slice::from_ref(&assignment)).await
+    }
+
+    /// Apply several strategies, each to its own segments. Everything that
+    /// can be planned is rendered in a single ffmpeg pass; strategies without
+    /// a plan then run one after another on the result.
+    pub async fn apply_mixed(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        assignments: &[StrategyAssignment],
+    ) -> Result<()> {
+        let mut combined = RenderPlan::default();
+        let mut deferred = Vec::new();
+        for assignment in assignments.iter().filter(|a| !a.segments.is_empty()) {
+            let config = self.resolve_config(&assignment.strategy, &assignment.config)?;
+            let strategy = self.get_strategy(&assignment.strategy)
+                .ok_or_else(|| BabymodeError::Processing {
+                    message: format!("Unknown censoring strategy: {}", assignment.strategy),
+                })?;
+
+            match strategy.plan(input_path, &assignment.segments, &config).await? {
+                Some(plan) => combined.merge(plan),
+                None => deferred.push((strategy, config, &assignment.segments)),
+            }
+        }
+
+        if deferred.is_empty() {
+            return overlay::render(input_path, output_path, &combined).await
+                .map_err(|e| BabymodeError::Processing {
+                    message: format!("FFmpeg failed applying censoring plan: {}", e),
+                });
+        }
+
+        // Strategies without a plan are chained through temporary files
+        let mut current: Option<TempFile> = None;
+        if !combined.is_empty() {
+            let pass = scratch_wav()?;
+            overlay::render(input_path, pass.path(), &combined).await?;
+            current = Some(pass);
+        }
+        let last = deferred.len() - 1;
+        for (i, (strategy, config, segments)) in deferred.into_iter().enumerate() {
+            let source = current.as_ref().map(|f| f.path()).unwrap_or(input_path);
+            if i == last {
+                strategy.apply_censoring(source, output_path, segments, &config).await?;
+            } else {
+                let pass = scratch_wav()?;
+                strategy.apply_censoring(source, pass.path(), segments, &config).await?;
+                current = Some(pass);
+            }
+        }
+        Ok(())
     }
 }
 
+/// Segments to censor with one strategy and its settings
+#[derive(Debug, Clone)]
+pub struct StrategyAssignment {
+    pub strategy: String,
+    /// Index of the rule that chose the strategy, `None` for the default.
+    /// Earlier rules take priority where padded segments overlap.
+    pub rule: Option<usize>,
+    pub config: CensoringConfig,
+    pub segments: Vec<AudioSegment>,
+}
+
+/// Empty WAV path for an intermediate pass, removed on drop
+fn scratch_wav() -> Result<TempFile> {
+    let path = tempfile::Builder::new()
+        .prefix("babymode_pass_")
+        .suffix(".wav")
+        .tempfile()
+        .and_then(|f| f.into_temp_path().keep().map_err(|e| e.error))
+        .map_err(|e| BabymodeError::Processing {
+            message: format!("Failed to create temp file: {}", e),
+        })?;
+    Ok(TempFile::new(path))
+}
+
+/// Render a strategy's own plan straight to `output_path`
+async fn render_plan<S: CensoringStrategy + ?Sized>(
+    strategy: &S,
+    input_path: &Path,
+    output_path: &Path,
+    segments: &[AudioSegment],
+    config: &CensoringConfig,
+) -> Result<()> {
+    let plan = strategy.plan(input_path, segments, config).await?.unwrap_or_default();
+    overlay::render(input_path, output_path, &plan).await
+        .map_err(|e| BabymodeError::Processing {
+            message: format!("FFmpeg failed with {} strategy: {}", strategy.name(), e),
+        })?;
+
+    info!("Applied {} censoring to {} segments", strategy.name(), segments.len());
+    Ok(())
+}
+
 impl Default for StrategyRegistry {
     fn default() -> Self {
         Self::new()
@@ -133,50 +512,22 @@ impl CensoringStrategy for SilenceStrategy {
         input_path: &Path,
         output_path: &Path,
         segments: &[AudioSegment],
-        _config: &CensoringConfig,
+        config: &CensoringConfig,
     ) -> Result<()> {
-        if segments.is_empty() {
-            tokio::fs::copy(input_path, output_path).await
-                .map_err(|e| BabymodeError::Processing { 
-                    message: format!("Failed to copy audio: {}", e) 
-                })?;
-            return Ok(());
-        }
-
-        let mut volume_conditions = Vec::new();
-        
-        for segment in segments {
-            volume_conditions.push(format!(
-                "volume=enable='between(t,{:.3},{:.3})':volume=0",
-                segment.start_time, segment.end_time
-            ));
-        }
-        
-        let filter_complex = volume_conditions.join(",");
-        
-        let output = Command::new("ffmpeg")
-            .args([
-                "-i", input_path.to_str().unwrap(),
-                "-af", &filter_complex,
-                "-c:a", "pcm_s16le",
-                "-y",
-                output_path.to_str().unwrap(),
-            ])
-            .output()
-            .await
-            .map_err(|e| BabymodeError::Processing { 
-                message: format!("FFmpeg failed: {}", e) 
-            })?;
-
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(BabymodeError::Processing {
-                message: format!("FFmpeg failed with silence strategy: {}", error),
-            });
-        }
+        render_plan(self, input_path, output_path, segments, config).await
+    }
 
-        info!("Applied silence censoring to {} segments", segments.len());
-        Ok(())
+    async fn plan(
+        &self,
+        input_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<Option<RenderPlan>> {
+        let _ = input_path;
+        let ducks = envelope::ducks_for_segments(
+            segments, 0.0, config.fade_duration, config.fade_curve,
+        );
+        Ok(Some(RenderPlan { ducks, ..Default::default() }))
     }
 }
 
@@ -200,67 +551,90 @@ impl CensoringStrategy for VolumeReductionStrategy {
         segments: &[AudioSegment],
         config: &CensoringConfig,
     ) -> Result<()> {
-        if segments.is_empty() {
-            tokio::fs::copy(input_path, output_path).await
-                .map_err(|e| BabymodeError::Processing { 
-                    message: format!("Failed to copy audio: {}", e) 
-                })?;
-            return Ok(());
-        }
+        render_plan(self, input_path, output_path, segments, config).await
+    }
 
-        let mut volume_conditions = Vec::new();
-        
-        for segment in segments {
-            // Create fade in and fade out with reduced volume
-            let fade_in_end = segment.start_time + config.fade_duration as f64;
-            let fade_out_start = segment.end_time - config.fade_duration as f64;
-            
-            volume_conditions.push(format!(
-                "volume=enable='between(t,{:.3},{:.3})':volume='if(lt(t,{:.3}),(t-{:.3})/{:.3}*{:.3},{:.3})'",
-                segment.start_time, segment.end_time,
-                fade_in_end, segment.start_time, config.fade_duration, config.volume, config.volume
-            ));
-            
-            if fade_out_start > fade_in_end {
-                volume_conditions.push(format!(
-                    "volume=enable='between(t,{:.3},{:.3})':volume='if(gt(t,{:.3}),({:.3}-t)/{:.3}*{:.3}+1-{:.3},{:.3})'",
-                    fade_out_start, segment.end_time,
-                    fade_out_start, segment.end_time, config.fade_duration, config.volume, config.volume, config.volume
-                ));
-            }
-        }
-        
-        let filter_complex = volume_conditions.join(",");
-        
-        let output = Command::new("ffmpeg")
-            .args([
-                "-i", input_path.to_str().unwrap(),
-                "-af", &filter_complex,
-                "-c:a", "pcm_s16le",
-                "-y",
-                output_path.to_str().unwrap(),
-            ])
-            .output()
-            .await
-            .map_err(|e| BabymodeError::Processing { 
-                message: format!("FFmpeg failed: {}", e) 
-            })?;
+    async fn plan(
+        &self,
+        input_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<Option<RenderPlan>> {
+        let _ = input_path;
+        let ducks = envelope::ducks_for_segments(
+            segments, config.volume, config.fade_duration, config.fade_curve,
+        );
+        Ok(Some(RenderPlan { ducks, ..Default::default() }))
+    }
+}
 
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(BabymodeError::Processing {
-                message: format!("FFmpeg failed with volume reduction: {}", error),
-            });
+/// Level matching shared by strategies that insert their own audio, so the
+/// inserted sound sits at the level of the dialogue around it
+struct LevelMatch {
+    enabled: bool,
+    offset_db: f32,
+    fallback_dbfs: f32,
+}
+
+impl LevelMatch {
+    /// Seconds of audio either side of a segment used to gauge dialogue level
+    const WINDOW: f64 = 1.5;
+    /// Level used when matching is off or there is no dialogue around a segment
+    const DEFAULT_FALLBACK_DBFS: f32 = -20.0;
+
+    fn from_config(config: &CensoringConfig, default_offset_db: f32) -> Self {
+        Self {
+            enabled: config.param_bool("level_match").unwrap_or(true),
+            offset_db: config.param_f64("level_offset_db").map(|o| o as f32).unwrap_or(default_offset_db),
+            fallback_dbfs: config.param_f64("level_db").map(|l| l as f32).unwrap_or(Self::DEFAULT_FALLBACK_DBFS),
         }
+    }
 
-        info!("Applied volume reduction to {} segments", segments.len());
-        Ok(())
+    fn parameters(default_offset_db: f32) -> Vec<ParamSpec> {
+        vec![
+            ParamSpec::bool("level_match", Some(true), "Match the level of the surrounding dialogue"),
+            ParamSpec::number("level_offset_db", -30.0, 12.0, Some(default_offset_db as f64),
+                              "Level relative to the surrounding dialogue, in dB"),
+            ParamSpec::number("level_db", -60.0, 0.0, Some(Self::DEFAULT_FALLBACK_DBFS as f64),
+                              "Level when not matching or no dialogue is found, in dBFS"),
+        ]
+    }
+
+    /// Gain for a source with RMS `source_rms` placed over `segment`
+    async fn gain(&self, input_path: &Path, segment: &AudioSegment, source_rms: f32) -> Result<f32> {
+        let dialogue_rms = if self.enabled {
+            audio::measure_surrounding_level(input_path, segment, Self::WINDOW).await?
+        } else {
+            None
+        };
+        let gain = tones::matched_gain(source_rms, dialogue_rms, self.offset_db, self.fallback_dbfs);
+        debug!("Level match at {:.3}s: dialogue rms {:?}, gain {:.3}", segment.start_time, dialogue_rms, gain);
+        Ok(gain)
     }
 }
 
 /// Beep strategy - replaces profanity with beep sounds
 pub struct BeepStrategy;
 
+impl BeepStrategy {
+    const DEFAULT_FREQUENCY: f32 = 1000.0;
+    const DEFAULT_LEVEL_OFFSET_DB: f32 = -3.0;
+
+    /// Tone frequency, from the `frequency` parameter or `beep_frequency`
+    fn frequency(config: &CensoringConfig) -> f32 {
+        config.param_f64("frequency")
+            .map(|f| f as f32)
+            .or(config.beep_frequency)
+            .unwrap_or(Self::DEFAULT_FREQUENCY)
+    }
+
+    fn preset(config: &CensoringConfig) -> Result<BeepPreset> {
+        config.param_str("preset")
+            .map(|p| p.parse())
+            .unwrap_or(Ok(BeepPreset::default()))
+    }
+}
+
 #[async_trait]
 impl CensoringStrategy for BeepStrategy {
     fn name(&self) -> &str {
@@ -268,7 +642,19 @@ impl CensoringStrategy for BeepStrategy {
     }
     
     fn description(&self) -> &str {
-        "Replace profanity with beep sounds"
+        "Replace profanity with a level-matched beep tone"
+    }
+
+    fn parameters(&self) -> Vec<ParamSpec> {
+        let presets: Vec<&str> = BeepPreset::ALL.iter().map(|p| p.as_str()).collect();
+        let mut params = vec![
+            ParamSpec::choice_with_aliases("preset", &presets, BeepPreset::ALIASES,
+                                           Some(BeepPreset::default().as_str()), "Tone to play"),
+            ParamSpec::number("frequency", 100.0, 10000.0, None,
+                              "Tone frequency in Hz (default 1000, or beep_frequency)"),
+        ];
+        params.extend(LevelMatch::parameters(Self::DEFAULT_LEVEL_OFFSET_DB));
+        params
     }
     
     fn validate_config(&self, config: &CensoringConfig) -> Result<()> {
@@ -280,6 +666,7 @@ impl CensoringStrategy for BeepStrategy {
                 });
             }
         }
+        Self::preset(config)?;
         Ok(())
     }
     
@@ -290,70 +677,240 @@ impl CensoringStrategy for BeepStrategy {
         segments: &[AudioSegment],
         config: &CensoringConfig,
     ) -> Result<()> {
-        if segments.is_empty() {
-            tokio::fs::copy(input_path, output_path).await
-                .map_err(|e| BabymodeError::Processing { 
-                    message: format!("Failed to copy audio: {}", e) 
-                })?;
-            return Ok(());
+        render_plan(self, input_path, output_path, segments, config).await
+    }
+
+    async fn plan(
+        &self,
+        input_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<Option<RenderPlan>> {
+        let preset = Self::preset(config)?;
+        let frequency = Self::frequency(config);
+        let level = LevelMatch::from_config(config, Self::DEFAULT_LEVEL_OFFSET_DB);
+
+        // Mute the original under each tone with the same short edge, so the
+        // two crossfade instead of leaving a gap or a doubled word
+        let ducks = envelope::normalize_ducks(&envelope::ducks_for_segments(
+            segments, 0.0, preset.edge(), config.fade_curve,
+        ));
+
+        let mut overlays = Vec::with_capacity(ducks.len());
+        for duck in &ducks {
+            let segment = AudioSegment::new(duck.start, duck.end);
+            let gain = level.gain(input_path, &segment, preset.rms()).await?;
+
+            overlays.push(Overlay {
+                source: OverlaySource::Expression(preset.expression(frequency, segment.duration)),
+                start: segment.start_time,
+                duration: segment.duration,
+                gain,
+                edge: preset.edge(),
+                curve: config.fade_curve,
+            });
         }
 
-        let frequency = config.beep_frequency.unwrap_or(1000.0);
-        let mut filter_parts = vec!["[0:a]".to_string()];
-        
-        for (i, segment) in segments.iter().enumerate() {
-            let duration = segment.end_time - segment.start_time;
-            let beep_filter = format!(
-                "sine=frequency={}:duration={}[beep{}];",
-                frequency, duration, i
-            );
-            filter_parts.push(beep_filter);
-            
-            let overlay_filter = format!(
-                "[{}][beep{}]amix=inputs=2:duration=first:dropout_transition=0,volume=enable='between(t,{:.3},{:.3})':volume=0[out{}];",
-                filter_parts.last().unwrap().trim_end_matches(';'),
-                i, segment.start_time, segment.end_time, i
-            );
-            filter_parts.push(overlay_filter);
+        debug!("Planned {} beeps over {} segments", preset.as_str(), segments.len());
+        Ok(Some(RenderPlan { ducks, overlays, ..Default::default() }))
+    }
+}
+
+/// Replacement strategy - covers profanity with a user-supplied clip
+pub struct ReplacementStrategy;
+
+impl ReplacementStrategy {
+    /// File extensions picked up when `replacement_audio` is a folder
+    const CLIP_EXTENSIONS: &'static [&'static str] = &["wav", "mp3", "flac", "ogg", "opus", "m4a", "aac"];
+    const DEFAULT_EDGE: f64 = 0.02;
+
+    fn fit(config: &CensoringConfig) -> Result<ClipFit> {
+        config.param_str("fit")
+            .map(|f| f.parse())
+            .unwrap_or(Ok(ClipFit::default()))
+    }
+
+    /// Clips to choose from: the file itself, or every audio file in the folder
+    fn collect_clips(path: &Path) -> Result<Vec<PathBuf>> {
+        if path.is_file() {
+            return Ok(vec![path.to_path_buf()]);
         }
-        
-        // Remove the last semicolon and build final filter
-        let mut filter_complex = filter_parts.join("");
-        if let Some(last_index) = filter_complex.rfind(';') {
-            filter_complex.truncate(last_index);
+
+        let entries = std::fs::read_dir(path)
+            .map_err(|e| crate::error::fs_error(e, path.to_path_buf()))?;
+        let mut clips: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.is_file())
+            .filter(|p| {
+                p.extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| Self::CLIP_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+                    .unwrap_or(false)
+            })
+            .collect();
+        clips.sort();
+
+        if clips.is_empty() {
+            return Err(BabymodeError::Config {
+                field: "replacement_audio".to_string(),
+                message: format!("No audio clips found in {}", path.display()),
+            });
         }
-        
-        debug!("Beep filter: {}", filter_complex);
-        
-        let output = Command::new("ffmpeg")
-            .args([
-                "-i", input_path.to_str().unwrap(),
-                "-filter_complex", &filter_complex,
-                "-c:a", "pcm_s16le",
-                "-y",
-                output_path.to_str().unwrap(),
-            ])
-            .output()
-            .await
-            .map_err(|e| BabymodeError::Processing { 
-                message: format!("FFmpeg failed: {}", e) 
-            })?;
+        Ok(clips)
+    }
 
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(BabymodeError::Processing {
-                message: format!("FFmpeg failed with beep strategy: {}", error),
+    /// Pseudo-random but reproducible choice of clip for segment `index`,
+    /// the same on every build for a given seed
+    fn pick_clip(clips: &[PathBuf], seed: u64, index: usize) -> usize {
+        use crate::lexicon::{fnv1a, FNV_OFFSET_BASIS};
+        let mut hash = fnv1a(fnv1a(FNV_OFFSET_BASIS, &seed.to_le_bytes()), &(index as u64).to_le_bytes());
+        // FNV barely mixes short inputs (its low bit just follows the index),
+        // so finish with MurmurHash3's fmix64 avalanche
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+        hash ^= hash >> 33;
+        (hash % clips.len() as u64) as usize
+    }
+
+    fn clip_path(config: &CensoringConfig) -> Result<PathBuf> {
+        let path = config.replacement_audio.as_ref().ok_or_else(|| BabymodeError::Config {
+            field: "replacement_audio".to_string(),
+            message: "The replacement strategy needs a clip or folder of clips".to_string(),
+        })?;
+        let path = PathBuf::from(path);
+        if !path.exists() {
+            return Err(BabymodeError::Config {
+                field: "replacement_audio".to_string(),
+                message: format!("Replacement audio does not exist: {}", path.display()),
             });
         }
+        Ok(path)
+    }
+}
 
-        info!("Applied beep censoring to {} segments", segments.len());
+#[async_trait]
+impl CensoringStrategy for ReplacementStrategy {
+    fn name(&self) -> &str {
+        "replacement"
+    }
+
+    fn description(&self) -> &str {
+        "Cover profanity with a custom audio clip (or a random clip from a folder)"
+    }
+
+    fn parameters(&self) -> Vec<ParamSpec> {
+        let mut params = vec![
+            ParamSpec::choice("fit", &["auto", "trim", "loop", "stretch"], Some(ClipFit::default().as_str()),
+                              "How clips are fitted to the segment length"),
+            ParamSpec::number("edge", 0.0, 0.5, Some(Self::DEFAULT_EDGE),
+                              "Crossfade at each end of a clip, in seconds"),
+            ParamSpec::integer("seed", 0, i64::MAX, None,
+                               "Seed for picking clips from a folder (random when unset)"),
+        ];
+        params.extend(LevelMatch::parameters(0.0));
+        params
+    }
+
+    fn validate_config(&self, config: &CensoringConfig) -> Result<()> {
+        Self::collect_clips(&Self::clip_path(config)?)?;
+        Self::fit(config)?;
         Ok(())
     }
+
+    async fn apply_censoring(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<()> {
+        render_plan(self, input_path, output_path, segments, config).await
+    }
+
+    async fn plan(
+        &self,
+        input_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<Option<RenderPlan>> {
+        let clips = Self::collect_clips(&Self::clip_path(config)?)?;
+        let fit = Self::fit(config)?;
+        let level = LevelMatch::from_config(config, 0.0);
+        let edge = config.param_f64("edge").unwrap_or(Self::DEFAULT_EDGE) as f32;
+        // The schema keeps seeds in 0..=i64::MAX, so the cast is lossless
+        let seed = config.param_i64("seed").map(|s| s as u64).unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+
+        let ducks = envelope::normalize_ducks(&envelope::ducks_for_segments(
+            segments, 0.0, edge, config.fade_curve,
+        ));
+
+        // Clip length and level only need measuring once per clip
+        let mut clip_info: HashMap<usize, (f64, Option<f32>)> = HashMap::new();
+        let mut overlays = Vec::with_capacity(ducks.len());
+        for (i, duck) in ducks.iter().enumerate() {
+            let segment = AudioSegment::new(duck.start, duck.end);
+            let clip_index = Self::pick_clip(&clips, seed, i);
+            let clip = &clips[clip_index];
+
+            let (clip_duration, clip_rms) = match clip_info.get(&clip_index) {
+                Some(info) => *info,
+                None => {
+                    let duration = audio::get_audio_duration(clip).await?;
+                    let samples = audio::read_pcm_samples(clip, 0.0, duration, 16000).await?;
+                    let info = (duration, audio::active_rms(&samples, 16000));
+                    clip_info.insert(clip_index, info);
+                    info
+                }
+            };
+
+            // Unmatched or silent clips play at their own level
+            let gain = match (level.enabled, clip_rms) {
+                (true, Some(rms)) => level.gain(input_path, &segment, rms).await?,
+                _ => 1.0,
+            };
+            debug!("Replacement at {:.3}s: {:?} ({:.2}s, {}), gain {:.3}",
+                   segment.start_time, clip, clip_duration,
+                   fit.resolve(clip_duration, segment.duration).as_str(), gain);
+
+            overlays.push(Overlay {
+                source: OverlaySource::Clip {
+                    path: clip.clone(),
+                    offset: 0.0,
+                    clip_duration,
+                    fit,
+                },
+                start: segment.start_time,
+                duration: segment.duration,
+                gain,
+                edge,
+                curve: config.fade_curve,
+            });
+        }
+
+        debug!("Planned replacement audio over {} segments", segments.len());
+        Ok(Some(RenderPlan { ducks, overlays, ..Default::default() }))
+    }
 }
 
 /// Reverse audio strategy - plays profanity backwards
 pub struct ReverseAudioStrategy;
 
+impl ReverseAudioStrategy {
+    /// Default length of the crossfade at each end of a reversed span
+    const DEFAULT_CROSSFADE: f64 = 0.02;
+
+    fn crossfade(config: &CensoringConfig) -> f32 {
+        config.param_f64("crossfade").unwrap_or(Self::DEFAULT_CROSSFADE) as f32
+    }
+}
+
 #[async_trait]
 impl CensoringStrategy for ReverseAudioStrategy {
     fn name(&self) -> &str {
@@ -363,59 +920,436 @@ impl CensoringStrategy for ReverseAudioStrategy {
     fn description(&self) -> &str {
         "Play profanity segments in reverse"
     }
+
+    fn parameters(&self) -> Vec<ParamSpec> {
+        vec![
+            ParamSpec::number("crossfade", 0.0, 0.5, Some(Self::DEFAULT_CROSSFADE),
+                              "Crossfade at each end of a reversed span, in seconds"),
+        ]
+    }
     
     async fn apply_censoring(
         &self,
         input_path: &Path,
         output_path: &Path,
         segments: &[AudioSegment],
-        _config: &CensoringConfig,
+        config: &CensoringConfig,
     ) -> Result<()> {
-        if segments.is_empty() {
-            tokio::fs::copy(input_path, output_path).await
-                .map_err(|e| BabymodeError::Processing { 
-                    message: format!("Failed to copy audio: {}", e) 
-                })?;
-            return Ok(());
+        render_plan(self, input_path, output_path, segments, config).await
+    }
+
+    async fn plan(
+        &self,
+        input_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<Option<RenderPlan>> {
+        let _ = input_path;
+        let crossfade = Self::crossfade(config);
+
+        // Each span is cut out, reversed and mixed back over the muted
+        // original, crossfading at both ends so the splice is inaudible
+        let ducks = envelope::normalize_ducks(&envelope::ducks_for_segments(
+            segments, 0.0, crossfade, config.fade_curve,
+        ));
+        let overlays = ducks.iter()
+            .map(|duck| Overlay {
+                source: OverlaySource::Span { filters: "areverse".to_string() },
+                start: duck.start,
+                duration: duck.end - duck.start,
+                gain: 1.0,
+                edge: crossfade,
+                curve: config.fade_curve,
+            })
+            .collect();
+
+        debug!("Planned reversal of {} segments", segments.len());
+        Ok(Some(RenderPlan { ducks, overlays, ..Default::default() }))
+    }
+}
+
+/// Noise strategy - replaces profanity with white, pink or brown noise
+pub struct NoiseStrategy;
+
+impl NoiseStrategy {
+    const DEFAULT_EDGE: f32 = 0.01;
+    const DEFAULT_LEVEL_OFFSET_DB: f32 = -6.0;
+
+    fn color(config: &CensoringConfig) -> Result<NoiseColor> {
+        config.param_str("color")
+            .map(|c| c.parse())
+            .unwrap_or(Ok(NoiseColor::default()))
+    }
+}
+
+#[async_trait]
+impl CensoringStrategy for NoiseStrategy {
+    fn name(&self) -> &str {
+        "noise"
+    }
+
+    fn description(&self) -> &str {
+        "Replace profanity with level-matched white, pink or brown noise"
+    }
+
+    fn parameters(&self) -> Vec<ParamSpec> {
+        let mut params = vec![
+            ParamSpec::choice_with_aliases("color", &["white", "pink", "brown"], &[("brownian", "brown")],
+                                           Some(NoiseColor::default().as_str()), "Colour of the noise"),
+        ];
+        params.extend(LevelMatch::parameters(Self::DEFAULT_LEVEL_OFFSET_DB));
+        params
+    }
+
+    fn validate_config(&self, config: &CensoringConfig) -> Result<()> {
+        Self::color(config)?;
+        Ok(())
+    }
+
+    async fn apply_censoring(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<()> {
+        render_plan(self, input_path, output_path, segments, config).await
+    }
+
+    async fn plan(
+        &self,
+        input_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<Option<RenderPlan>> {
+        let color = Self::color(config)?;
+        let level = LevelMatch::from_config(config, Self::DEFAULT_LEVEL_OFFSET_DB);
+
+        let ducks = envelope::normalize_ducks(&envelope::ducks_for_segments(
+            segments, 0.0, Self::DEFAULT_EDGE, config.fade_curve,
+        ));
+
+        let mut overlays = Vec::with_capacity(ducks.len());
+        for duck in &ducks {
+            let segment = AudioSegment::new(duck.start, duck.end);
+            overlays.push(Overlay {
+                source: OverlaySource::Noise(color),
+                start: segment.start_time,
+                duration: segment.duration,
+                gain: level.gain(input_path, &segment, color.rms()).await?,
+                edge: Self::DEFAULT_EDGE,
+                curve: config.fade_curve,
+            });
         }
 
-        // This is a simplified implementation
-        // A full implementation would need to extract segments, reverse them, and recombine
-        let mut volume_conditions = Vec::new();
-        
-        for segment in segments {
-            volume_conditions.push(format!(
-                "areverse=enable='between(t,{:.3},{:.3})'",
-                segment.start_time, segment.end_time
-            ));
+        debug!("Planned {} noise over {} segments", color.as_str(), segments.len());
+        Ok(Some(RenderPlan { ducks, overlays, ..Default::default() }))
+    }
+}
+
+/// Pitch shift strategy - makes profanity unintelligible by shifting its
+/// pitch and optionally scrambling formants
+pub struct PitchShiftStrategy;
+
+impl PitchShiftStrategy {
+    const DEFAULT_CROSSFADE: f32 = 0.02;
+
+    /// Semitone shift and formant scrambling of a named preset
+    fn preset_settings(name: &str) -> Result<(f64, bool)> {
+        match name {
+            "chipmunk" => Ok((7.0, false)),
+            "demon" => Ok((-8.0, true)),
+            _ => Err(BabymodeError::Config {
+                field: "preset".to_string(),
+                message: format!("Invalid pitch preset '{}'. Valid options: chipmunk, demon", name),
+            }),
         }
-        
-        let filter_complex = volume_conditions.join(",");
-        
-        let output = Command::new("ffmpeg")
-            .args([
-                "-i", input_path.to_str().unwrap(),
-                "-af", &filter_complex,
-                "-c:a", "pcm_s16le",
-                "-y",
-                output_path.to_str().unwrap(),
-            ])
+    }
+
+    /// Resolve the shift in semitones and whether to scramble formants
+    fn settings(config: &CensoringConfig) -> Result<(f64, bool)> {
+        let (preset_semitones, preset_scramble) =
+            Self::preset_settings(config.param_str("preset").unwrap_or("chipmunk"))?;
+        Ok((
+            config.param_f64("semitones").unwrap_or(preset_semitones),
+            config.param_bool("scramble").unwrap_or(preset_scramble),
+        ))
+    }
+
+    /// Filter chain shifting pitch by `semitones` without changing duration
+    fn shift_filters(semitones: f64, scramble: bool, sample_rate: u32) -> String {
+        let ratio = 2f64.powf(semitones / 12.0);
+        let mut chain = vec![
+            format!("asetrate={}", (sample_rate as f64 * ratio).round() as u64),
+            format!("aresample={}", sample_rate),
+        ];
+        chain.extend(overlay::atempo_chain(1.0 / ratio));
+        if scramble {
+            // Wobble pitch and smear phase so formants stop tracking the voice
+            chain.push("vibrato=f=8:d=0.7".to_string());
+            chain.push("aphaser=decay=0.6:speed=1.5".to_string());
+        }
+        chain.join(",")
+    }
+}
+
+#[async_trait]
+impl CensoringStrategy for PitchShiftStrategy {
+    fn name(&self) -> &str {
+        "pitch_shift"
+    }
+
+    fn description(&self) -> &str {
+        "Pitch-shift profanity (chipmunk, demon) with optional formant scrambling"
+    }
+
+    fn parameters(&self) -> Vec<ParamSpec> {
+        vec![
+            ParamSpec::choice("preset", &["chipmunk", "demon"], Some("chipmunk"),
+                              "Starting point for semitones and scramble"),
+            ParamSpec::number("semitones", -24.0, 24.0, None, "Pitch shift, overriding the preset"),
+            ParamSpec::bool("scramble", None, "Scramble formants, overriding the preset"),
+        ]
+    }
+
+    fn validate_config(&self, config: &CensoringConfig) -> Result<()> {
+        let (semitones, _) = Self::settings(config)?;
+        if !(-24.0..=24.0).contains(&semitones) || semitones == 0.0 {
+            return Err(BabymodeError::Config {
+                field: "semitones".to_string(),
+                message: "Pitch shift must be non-zero and between -24 and 24 semitones".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn apply_censoring(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<()> {
+        render_plan(self, input_path, output_path, segments, config).await
+    }
+
+    async fn plan(
+        &self,
+        input_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<Option<RenderPlan>> {
+        let (semitones, scramble) = Self::settings(config)?;
+        let format = audio::probe_audio_stream(input_path).await?;
+        let filters = Self::shift_filters(semitones, scramble, format.sample_rate);
+
+        let ducks = envelope::normalize_ducks(&envelope::ducks_for_segments(
+            segments, 0.0, Self::DEFAULT_CROSSFADE, config.fade_curve,
+        ));
+        let overlays = ducks.iter()
+            .map(|duck| Overlay {
+                source: OverlaySource::Span { filters: filters.clone() },
+                start: duck.start,
+                duration: duck.end - duck.start,
+                gain: 1.0,
+                edge: Self::DEFAULT_CROSSFADE,
+                curve: config.fade_curve,
+            })
+            .collect();
+
+        debug!("Planned {:+.1} semitone pitch shift of {} segments", semitones, segments.len());
+        Ok(Some(RenderPlan { ducks, overlays, ..Default::default() }))
+    }
+}
+
+/// Vocal removal strategy - removes only the dialogue under each segment
+/// and keeps the music and effects around it.
+///
+/// With a `separator` command, each span (plus some context either side) is
+/// written to a WAV file and the command is run with `{input}` and `{output}`
+/// replaced by that file and the path where it must write the background
+/// stem, e.g. `separate-background {input} {output}`. Without one, or when it
+/// fails, stereo audio keeps only its side (L-R) signal and 5.1/7.1 audio
+/// mutes its centre channel, which removes centre-panned dialogue.
+pub struct VocalRemovalStrategy;
+
+impl VocalRemovalStrategy {
+    const DEFAULT_CROSSFADE: f32 = 0.03;
+    /// Seconds of audio either side of a span handed to the separator
+    const DEFAULT_CONTEXT: f64 = 1.0;
+
+    /// Filters that drop the dialogue position of a channel layout. Stereo
+    /// keeps only the side signal (L-R), in phase on both channels so it
+    /// survives a mono downmix, scaled by 1/√2 so an uncorrelated bed keeps
+    /// its level.
+    fn fallback_filters(format: &AudioStreamInfo) -> Option<String> {
+        match format.layout().as_str() {
+            "stereo" | "2c" => Some("pan=stereo|c0=0.7071*c0-0.7071*c1|c1=0.7071*c0-0.7071*c1".to_string()),
+            "5.1" => Some("pan=5.1|FL=FL|FR=FR|FC=0*FC|LFE=LFE|BL=BL|BR=BR".to_string()),
+            "5.1(side)" => Some("pan=5.1(side)|FL=FL|FR=FR|FC=0*FC|LFE=LFE|SL=SL|SR=SR".to_string()),
+            "7.1" => Some("pan=7.1|FL=FL|FR=FR|FC=0*FC|LFE=LFE|BL=BL|BR=BR|SL=SL|SR=SR".to_string()),
+            _ => None,
+        }
+    }
+
+    /// Split the separator command template and fill in the file paths
+    fn separator_args(template: &str, input: &Path, output: &Path) -> Vec<String> {
+        template.split_whitespace()
+            .map(|arg| arg
+                .replace("{input}", &input.to_string_lossy())
+                .replace("{output}", &output.to_string_lossy()))
+            .collect()
+    }
+
+    /// Run the separator over one span, returning the background stem
+    async fn separate(
+        template: &str,
+        input_path: &Path,
+        span_start: f64,
+        span_duration: f64,
+    ) -> Result<TempFile> {
+        // Numbered per process, as several rules may use this strategy in one run
+        static NEXT_SPAN: AtomicUsize = AtomicUsize::new(0);
+        let index = NEXT_SPAN.fetch_add(1, Ordering::Relaxed);
+        let temp_dir = std::env::temp_dir();
+        let span = TempFile::new(temp_dir.join(format!("babymode_span_{}_{}.wav", std::process::id(), index)));
+        let background = TempFile::new(temp_dir.join(format!("babymode_background_{}_{}.wav", std::process::id(), index)));
+
+        audio::extract_audio_segment(input_path, span.path(), span_start, span_duration).await?;
+
+        let args = Self::separator_args(template, span.path(), background.path());
+        let (program, args) = args.split_first().ok_or_else(|| BabymodeError::Config {
+            field: "separator".to_string(),
+            message: "Separator command is empty".to_string(),
+        })?;
+
+        let output = tokio::process::Command::new(program)
+            .args(args)
             .output()
             .await
-            .map_err(|e| BabymodeError::Processing { 
-                message: format!("FFmpeg failed: {}", e) 
+            .map_err(|e| BabymodeError::MissingDependency {
+                name: program.clone(),
+                suggestion: format!("Could not run separator: {}", e),
             })?;
 
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() || !background.exists() {
             return Err(BabymodeError::Processing {
-                message: format!("FFmpeg failed with reverse strategy: {}", error),
+                message: format!(
+                    "Separator failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
             });
         }
+        Ok(background)
+    }
+}
 
-        info!("Applied reverse audio censoring to {} segments", segments.len());
+#[async_trait]
+impl CensoringStrategy for VocalRemovalStrategy {
+    fn name(&self) -> &str {
+        "vocal_removal"
+    }
+
+    fn description(&self) -> &str {
+        "Remove only the dialogue during profanity, keeping music and effects"
+    }
+
+    fn parameters(&self) -> Vec<ParamSpec> {
+        vec![
+            ParamSpec::text("separator", "Source separation command with {input} and {output} placeholders"),
+            ParamSpec::number("context", 0.0, 10.0, Some(Self::DEFAULT_CONTEXT),
+                              "Seconds of audio either side of a span given to the separator"),
+        ]
+    }
+
+    fn validate_config(&self, config: &CensoringConfig) -> Result<()> {
+        if let Some(separator) = config.param_str("separator") {
+            if !separator.contains("{input}") || !separator.contains("{output}") {
+                return Err(BabymodeError::Config {
+                    field: "separator".to_string(),
+                    message: "Separator command must contain {input} and {output} placeholders".to_string(),
+                });
+            }
+        }
         Ok(())
     }
+
+    async fn apply_censoring(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<()> {
+        render_plan(self, input_path, output_path, segments, config).await
+    }
+
+    async fn plan(
+        &self,
+        input_path: &Path,
+        segments: &[AudioSegment],
+        config: &CensoringConfig,
+    ) -> Result<Option<RenderPlan>> {
+        let format = audio::probe_audio_stream(input_path).await?;
+        let fallback = Self::fallback_filters(&format);
+        let separator = config.param_str("separator");
+        let context = config.param_f64("context").unwrap_or(Self::DEFAULT_CONTEXT);
+        let crossfade = Self::DEFAULT_CROSSFADE;
+
+        let ducks = envelope::normalize_ducks(&envelope::ducks_for_segments(
+            segments, 0.0, crossfade, config.fade_curve,
+        ));
+
+        // Background stems have to outlive the render
+        let mut stems = Vec::new();
+        let mut overlays = Vec::with_capacity(ducks.len());
+        for duck in &ducks {
+            let duration = duck.end - duck.start;
+            let mut source = None;
+
+            if let Some(template) = separator {
+                let span_start = (duck.start - context).max(0.0);
+                match Self::separate(template, input_path, span_start, duck.end + context - span_start).await {
+                    Ok(stem) => {
+                        source = Some(OverlaySource::Clip {
+                            path: stem.path().to_path_buf(),
+                            offset: duck.start - span_start,
+                            clip_duration: duration,
+                            fit: ClipFit::Trim,
+                        });
+                        stems.push(Arc::new(stem));
+                    }
+                    Err(e) => warn!("Separator failed at {:.2}s, using fallback: {}", duck.start, e),
+                }
+            }
+
+            let source = match (source, &fallback) {
+                (Some(source), _) => source,
+                (None, Some(filters)) => OverlaySource::Span { filters: filters.clone() },
+                // Nothing to keep, the segment is simply silenced
+                (None, None) => continue,
+            };
+
+            overlays.push(Overlay {
+                source,
+                start: duck.start,
+                duration,
+                gain: 1.0,
+                edge: crossfade,
+                curve: config.fade_curve,
+            });
+        }
+
+        if overlays.len() < ducks.len() {
+            warn!(
+                "No separator and no centre/side fallback for '{}' audio; {} segments were silenced instead",
+                format.layout(), ducks.len() - overlays.len()
+            );
+        }
+
+        debug!("Planned removal of dialogue from {} segments", segments.len());
+        Ok(Some(RenderPlan { ducks, overlays, temp_files: stems }))
+    }
 }
 
 #[cfg(test)]
@@ -458,6 +1392,152 @@ mod tests {
             ..Default::default()
         };
         assert!(beep_strategy.validate_config(&invalid_config).is_err());
+
+        // Unknown preset
+        let mut preset_config = CensoringConfig::default();
+        preset_config.custom_params.insert("preset".to_string(), "duck_quack".into());
+        assert!(beep_strategy.validate_config(&preset_config).is_ok());
+        preset_config.custom_params.insert("preset".to_string(), "siren".into());
+        assert!(beep_strategy.validate_config(&preset_config).is_err());
+    }
+
+    #[test]
+    fn test_noise_and_pitch_validation() {
+        let registry = StrategyRegistry::new();
+        let noise = registry.get_strategy("noise").unwrap();
+        let pitch = registry.get_strategy("pitch_shift").unwrap();
+
+        let mut config = CensoringConfig::default();
+        assert!(noise.validate_config(&config).is_ok());
+        assert!(pitch.validate_config(&config).is_ok());
+
+        config.custom_params.insert("color".to_string(), "pink".into());
+        config.custom_params.insert("preset".to_string(), "demon".into());
+        assert!(noise.validate_config(&config).is_ok());
+        assert!(pitch.validate_config(&config).is_ok());
+
+        config.custom_params.insert("color".to_string(), "purple".into());
+        config.custom_params.insert("semitones".to_string(), 30.0.into());
+        assert!(noise.validate_config(&config).is_err());
+        assert!(pitch.validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_parameter_schema_resolution() {
+        let registry = StrategyRegistry::new();
+        let mut config = CensoringConfig::default();
+        config.custom_params.insert("frequency".to_string(), "800".into());
+        config.custom_params.insert("level_match".to_string(), "no".into());
+        config.custom_params.insert("preset".to_string(), "Dual-Tone".into());
+
+        // Strings from the command line are converted and defaults filled in
+        let resolved = registry.resolve_config("beep", &config).unwrap();
+        assert_eq!(resolved.param_f64("frequency"), Some(800.0));
+        assert_eq!(resolved.param_bool("level_match"), Some(false));
+        assert_eq!(resolved.param_str("preset"), Some("dual_tone"));
+        assert_eq!(resolved.param_f64("level_offset_db"), Some(-3.0));
+        assert_eq!(BeepStrategy::frequency(&resolved), 800.0);
+
+        config.custom_params.insert("frequency".to_string(), 20000.into());
+        assert!(registry.resolve_config("beep", &config).is_err());
+
+        let mut config = CensoringConfig::default();
+        config.custom_params.insert("colour".to_string(), "pink".into());
+        let err = registry.resolve_config("noise", &config).unwrap_err();
+        assert!(err.to_string().contains("Valid parameters: color"));
+        assert!(registry.resolve_config("silence", &config).is_err());
+
+        // Aliases the strategies accept pass the schema as their canonical names
+        let mut config = CensoringConfig::default();
+        config.custom_params.insert("preset".to_string(), "Duck".into());
+        let resolved = registry.resolve_config("beep", &config).unwrap();
+        assert_eq!(resolved.param_str("preset"), Some("duck_quack"));
+        for (alias, preset) in BeepPreset::ALIASES {
+            assert_eq!(alias.parse::<BeepPreset>().unwrap().as_str(), *preset);
+        }
+        let mut config = CensoringConfig::default();
+        config.custom_params.insert("color".to_string(), "brownian".into());
+        assert_eq!(registry.resolve_config("noise", &config).unwrap().param_str("color"), Some("brown"));
+
+        // Large seeds survive the round trip without going through f64
+        let seed = registry.get_strategy("replacement").unwrap().parameters()
+            .into_iter().find(|spec| spec.name == "seed").unwrap();
+        let mut config = CensoringConfig::default();
+        config.custom_params.insert("seed".to_string(), seed.coerce(&"9007199254740993".into()).unwrap());
+        assert_eq!(config.param_i64("seed"), Some(9_007_199_254_740_993));
+        assert!(seed.coerce(&(-1).into()).is_err());
+
+        // Every built-in default passes its own schema
+        for (name, _) in registry.list_strategies() {
+            for spec in registry.get_strategy(name).unwrap().parameters() {
+                if let Some(default) = &spec.default {
+                    assert_eq!(&spec.coerce(default).unwrap(), default, "{}.{}", name, spec.name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pitch_shift_keeps_duration() {
+        let filters = PitchShiftStrategy::shift_filters(12.0, false, 48000);
+        assert_eq!(filters, "asetrate=96000,aresample=48000,atempo=0.500000");
+
+        let filters = PitchShiftStrategy::shift_filters(-8.0, true, 16000);
+        assert!(filters.contains("vibrato"));
+    }
+
+    #[test]
+    fn test_vocal_removal_fallbacks() {
+        let format = |layout: &str, channels| AudioStreamInfo {
+            sample_rate: 48000,
+            channels,
+            channel_layout: Some(layout.to_string()),
+            codec: "pcm_s16le".to_string(),
+            bit_rate: None,
+        };
+        // Both channels carry the same side signal, never L-R against R-L
+        assert_eq!(
+            VocalRemovalStrategy::fallback_filters(&format("stereo", 2)).unwrap(),
+            "pan=stereo|c0=0.7071*c0-0.7071*c1|c1=0.7071*c0-0.7071*c1"
+        );
+        assert!(VocalRemovalStrategy::fallback_filters(&format("5.1", 6)).unwrap().contains("FC=0*FC"));
+        assert!(VocalRemovalStrategy::fallback_filters(&format("mono", 1)).is_none());
+
+        let args = VocalRemovalStrategy::separator_args(
+            "sep --stems 2 {input} -o {output}",
+            Path::new("/tmp/in.wav"),
+            Path::new("/tmp/out.wav"),
+        );
+        assert_eq!(args, vec!["sep", "--stems", "2", "/tmp/in.wav", "-o", "/tmp/out.wav"]);
+
+        let mut config = CensoringConfig::default();
+        config.custom_params.insert("separator".to_string(), "sep {input}".into());
+        assert!(VocalRemovalStrategy.validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_replacement_clip_selection() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for name in ["b.wav", "a.mp3", "notes.txt"] {
+            std::fs::File::create(temp_dir.path().join(name)).unwrap();
+        }
+
+        let clips = ReplacementStrategy::collect_clips(temp_dir.path()).unwrap();
+        assert_eq!(clips.len(), 2);
+        assert!(clips[0].ends_with("a.mp3"));
+
+        // Same seed, same choices
+        let first: Vec<usize> = (0..8).map(|i| ReplacementStrategy::pick_clip(&clips, 7, i)).collect();
+        let second: Vec<usize> = (0..8).map(|i| ReplacementStrategy::pick_clip(&clips, 7, i)).collect();
+        assert_eq!(first, second);
+        // Fixed hash, so the choices don't change with the toolchain
+        assert_eq!(first, vec![1, 1, 1, 1, 1, 0, 0, 0]);
+
+        let config = CensoringConfig {
+            replacement_audio: Some(temp_dir.path().join("missing.wav").to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        assert!(ReplacementStrategy.validate_config(&config).is_err());
     }
 
     #[tokio::test]
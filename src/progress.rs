@@ -1,6 +1,7 @@
 use indicatif::{ProgressBar, ProgressStyle, MultiProgress};
-use std::sync::Arc;
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
 /// Progress tracker for babymode operations
@@ -60,6 +61,14 @@ impl Default for ProgressTracker {
 pub struct ProgressOperation {
     pub tracker: ProgressTracker,
     pub enabled: bool,
+    stages: Mutex<Vec<StageTiming>>,
+}
+
+/// How long one operation took
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageTiming {
+    pub stage: String,
+    pub seconds: f64,
 }
 
 impl ProgressOperation {
@@ -67,6 +76,21 @@ impl ProgressOperation {
         Self {
             tracker: ProgressTracker::new(),
             enabled,
+            stages: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Every operation run so far and how long it took, in order
+    pub fn timings(&self) -> Vec<StageTiming> {
+        self.stages.lock().map(|stages| stages.clone()).unwrap_or_default()
+    }
+
+    fn record(&self, message: &str, started: Instant) {
+        if let Ok(mut stages) = self.stages.lock() {
+            stages.push(StageTiming {
+                stage: message.to_string(),
+                seconds: started.elapsed().as_secs_f64(),
+            });
         }
     }
 
@@ -79,14 +103,17 @@ impl ProgressOperation {
     where 
         F: FnMut(Option<&ProgressBar>) -> T,
     {
-        if self.enabled {
+        let started = Instant::now();
+        let result = if self.enabled {
             let pb = self.tracker.create_progress_bar(total, message);
             let result = operation(Some(&pb));
             pb.finish_with_message(format!("✓ {}", message));
             result
         } else {
             operation(None)
-        }
+        };
+        self.record(message, started);
+        result
     }
 
     /// Execute an operation with a spinner if enabled
@@ -97,14 +124,17 @@ impl ProgressOperation {
     where 
         F: FnMut(Option<&ProgressBar>) -> T,
     {
-        if self.enabled {
+        let started = Instant::now();
+        let result = if self.enabled {
             let pb = self.tracker.create_spinner(message);
             let result = operation(Some(&pb));
             pb.finish_with_message(format!("✓ {}", message));
             result
         } else {
             operation(None)
-        }
+        };
+        self.record(message, started);
+        result
     }
 }
 
@@ -155,5 +185,8 @@ mod tests {
         }).await;
         
         assert_eq!(result, "success");
+        let timings = progress.timings();
+        assert_eq!(timings.len(), 1);
+        assert_eq!(timings[0].stage, "Test");
     }
 }
\ No newline at end of file
@@ -0,0 +1,183 @@
+use log::debug;
+use std::path::Path;
+
+use crate::audio::{self, AudioSegment};
+use crate::error::Result;
+use crate::whisper::WordDetection;
+
+/// Sample rate used for the energy analysis
+const ANALYSIS_RATE: u32 = 16000;
+/// Length of each energy frame, in seconds
+const FRAME_DURATION: f64 = 0.005;
+/// Frames at or below this RMS always count as quiet (-50 dBFS)
+const QUIET_GATE: f32 = 0.003_162;
+/// Frames within this factor of the quietest frame count as quiet (+6 dB)
+const FLOOR_MARGIN: f32 = 2.0;
+
+/// Short-term RMS of a stretch of audio starting at `start_time`
+#[derive(Debug, Clone)]
+pub struct EnergyEnvelope {
+    frames: Vec<f32>,
+    start_time: f64,
+}
+
+impl EnergyEnvelope {
+    pub fn from_samples(samples: &[f32], sample_rate: u32, start_time: f64) -> Self {
+        let frame_len = ((sample_rate as f64 * FRAME_DURATION) as usize).max(1);
+        let frames = samples.chunks(frame_len)
+            .map(|frame| {
+                let power = frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32;
+                power.sqrt()
+            })
+            .collect();
+        Self { frames, start_time }
+    }
+
+    fn time_of(&self, index: usize) -> f64 {
+        self.start_time + index as f64 * FRAME_DURATION
+    }
+
+    fn index_of(&self, time: f64) -> usize {
+        (((time - self.start_time) / FRAME_DURATION).round().max(0.0) as usize)
+            .min(self.frames.len().saturating_sub(1))
+    }
+
+    /// RMS below which a frame is treated as a gap between words, or `None`
+    /// when the level barely changes and there are no gaps to find
+    fn quiet_threshold(&self) -> Option<f32> {
+        let floor = self.frames.iter().copied().fold(f32::INFINITY, f32::min);
+        let peak = self.frames.iter().copied().fold(0.0, f32::max);
+        let threshold = (floor * FLOOR_MARGIN).max(QUIET_GATE);
+        (threshold * FLOOR_MARGIN < peak).then_some(threshold)
+    }
+
+    /// Quiet frame in `lo..=hi` closest to `target`, preferring earlier frames on ties
+    fn nearest_quiet(&self, target: usize, lo: usize, hi: usize, threshold: f32) -> Option<usize> {
+        (lo..=hi)
+            .filter(|&i| self.frames[i] <= threshold)
+            .min_by_key(|&i| (i as isize - target as isize).unsigned_abs())
+    }
+}
+
+/// Move the edges of `segment` to the nearest low-energy points no more than
+/// `window` seconds away. Each edge lands where the quiet gap meets the word,
+/// so the segment is tight without reaching into neighbouring words.
+pub fn refine_segment(envelope: &EnergyEnvelope, segment: &AudioSegment, window: f64) -> AudioSegment {
+    let threshold = match envelope.quiet_threshold() {
+        Some(threshold) => threshold,
+        None => return segment.clone(),
+    };
+    let middle = envelope.index_of((segment.start_time + segment.end_time) / 2.0);
+
+    // Start: find the gap before the word, then walk forward to the onset
+    let start_target = envelope.index_of(segment.start_time);
+    let start_hi = envelope.index_of(segment.start_time + window).min(middle);
+    let start = envelope.index_of(segment.start_time - window).min(start_hi);
+    let new_start = envelope.nearest_quiet(start_target, start, start_hi, threshold)
+        .map(|mut i| {
+            while i < start_hi && envelope.frames[i + 1] <= threshold {
+                i += 1;
+            }
+            envelope.time_of(i + 1)
+        });
+
+    // End: find the gap after the word, then walk back to the offset
+    let end_target = envelope.index_of(segment.end_time);
+    let end_lo = envelope.index_of(segment.end_time - window).max(middle);
+    let end_hi = envelope.index_of(segment.end_time + window).max(end_lo);
+    let new_end = envelope.nearest_quiet(end_target, end_lo, end_hi, threshold)
+        .map(|mut i| {
+            while i > end_lo && envelope.frames[i - 1] <= threshold {
+                i -= 1;
+            }
+            envelope.time_of(i)
+        });
+
+    let start_time = new_start.unwrap_or(segment.start_time)
+        .clamp(segment.start_time - window, segment.start_time + window)
+        .max(0.0);
+    let end_time = new_end.unwrap_or(segment.end_time)
+        .clamp(segment.end_time - window, segment.end_time + window);
+
+    if end_time <= start_time {
+        return segment.clone();
+    }
+    AudioSegment::new(start_time, end_time)
+}
+
+/// Snap the timing of each detection to the energy envelope of `audio_path`
+pub async fn refine_detections(audio_path: &Path, detections: &mut [WordDetection], window: f64) -> Result<()> {
+    for detection in detections.iter_mut() {
+        let analysis_start = (detection.start_time - window).max(0.0);
+        let samples = audio::read_pcm_samples(
+            audio_path,
+            analysis_start,
+            detection.end_time + window - analysis_start,
+            ANALYSIS_RATE,
+        ).await?;
+
+        let envelope = EnergyEnvelope::from_samples(&samples, ANALYSIS_RATE, analysis_start);
+        let refined = refine_segment(&envelope, &detection.to_audio_segment(), window);
+        debug!(
+            "Refined '{}' from {:.3}-{:.3}s to {:.3}-{:.3}s",
+            detection.word, detection.start_time, detection.end_time, refined.start_time, refined.end_time
+        );
+        detection.start_time = refined.start_time;
+        detection.end_time = refined.end_time;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Silence with 440 Hz bursts over each `(start, end)` span
+    fn speech(spans: &[(f64, f64)], duration: f64) -> Vec<f32> {
+        (0..(duration * ANALYSIS_RATE as f64) as usize)
+            .map(|i| {
+                let t = i as f64 / ANALYSIS_RATE as f64;
+                if spans.iter().any(|(s, e)| t >= *s && t < *e) {
+                    0.5 * (2.0 * std::f64::consts::PI * 440.0 * t).sin() as f32
+                } else {
+                    0.0005
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_edges_snap_to_word_boundaries() {
+        let envelope = EnergyEnvelope::from_samples(&speech(&[(1.0, 1.5)], 2.0), ANALYSIS_RATE, 0.0);
+
+        // Late start and early end leak the first and last phonemes
+        let refined = refine_segment(&envelope, &AudioSegment::new(1.08, 1.42), 0.15);
+        assert!((refined.start_time - 1.0).abs() < 0.011, "{:?}", refined);
+        assert!((refined.end_time - 1.5).abs() < 0.011, "{:?}", refined);
+
+        // Loose timestamps are tightened
+        let refined = refine_segment(&envelope, &AudioSegment::new(0.9, 1.6), 0.15);
+        assert!((refined.start_time - 1.0).abs() < 0.011, "{:?}", refined);
+        assert!((refined.end_time - 1.5).abs() < 0.011, "{:?}", refined);
+    }
+
+    #[test]
+    fn test_neighbouring_words_are_not_clipped() {
+        let samples = speech(&[(0.6, 0.96), (1.0, 1.5), (1.55, 1.8)], 2.0);
+        let envelope = EnergyEnvelope::from_samples(&samples, ANALYSIS_RATE, 0.0);
+
+        let refined = refine_segment(&envelope, &AudioSegment::new(1.05, 1.45), 0.15);
+        assert!(refined.start_time >= 0.96 && refined.start_time <= 1.01, "{:?}", refined);
+        assert!(refined.end_time >= 1.49 && refined.end_time <= 1.55, "{:?}", refined);
+    }
+
+    #[test]
+    fn test_no_quiet_point_keeps_the_edges() {
+        // Continuous speech, nothing to snap to
+        let envelope = EnergyEnvelope::from_samples(&speech(&[(0.0, 3.0)], 3.0), ANALYSIS_RATE, 0.0);
+        let segment = AudioSegment::new(1.0, 1.4);
+        let refined = refine_segment(&envelope, &segment, 0.1);
+        assert_eq!(refined.start_time, segment.start_time);
+        assert_eq!(refined.end_time, segment.end_time);
+    }
+}
@@ -0,0 +1,294 @@
+use anyhow::{Context, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::censoring::CensoringStats;
+use crate::config::Config;
+use crate::dependencies::ToolVersions;
+use crate::lexicon::Severity;
+use crate::loudness::LoudnessReport;
+use crate::plugins::{CensoringConfig, StrategyAssignment};
+use crate::progress::StageTiming;
+use crate::rules;
+use crate::subtitles::{CueChange, SubtitleReport};
+use crate::video::{MediaProbe, VideoMetadata};
+use crate::whisper::{self, WordDetection};
+
+/// A stretch of the programme, in seconds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// What was read
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputReport {
+    pub path: PathBuf,
+    /// Container, as named by ffprobe
+    pub format: String,
+    pub audio_codec: String,
+    pub video: Option<VideoReport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VideoReport {
+    pub codec: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    pub duration: f64,
+}
+
+impl InputReport {
+    pub fn new(path: &Path, probe: &MediaProbe, video: Option<&VideoMetadata>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            format: probe.format_name.clone(),
+            audio_codec: probe.audio_codec.clone(),
+            video: video.map(|metadata| VideoReport {
+                codec: metadata.codec.clone(),
+                width: metadata.width,
+                height: metadata.height,
+                fps: metadata.fps,
+                duration: metadata.duration,
+            }),
+        }
+    }
+}
+
+/// Word list the run censored with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LexiconReport {
+    /// Fingerprint of the swear words and lexicon entries
+    pub version: String,
+    pub words: usize,
+}
+
+/// One censored word and how it was handled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetectionReport {
+    pub word: String,
+    /// The word as transcribed or written
+    pub text: String,
+    /// Swear word it matched
+    pub matched: Option<String>,
+    /// Index of the strategy rule it matched, `None` for the default strategy
+    pub rule: Option<usize>,
+    pub strategy: String,
+    pub category: Option<String>,
+    pub severity: Option<Severity>,
+    pub confidence: f64,
+    pub speaker: Option<String>,
+    /// Timing as detected
+    pub original: Span,
+    /// Merged and padded segment that censored it
+    #[serde(rename = "final")]
+    pub final_segment: Option<Span>,
+}
+
+/// Settings and segments of one strategy group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyReport {
+    pub strategy: String,
+    pub config: CensoringConfig,
+    pub segments: Vec<Span>,
+}
+
+impl From<&StrategyAssignment> for StrategyReport {
+    fn from(assignment: &StrategyAssignment) -> Self {
+        Self {
+            strategy: assignment.strategy.clone(),
+            config: assignment.config.clone(),
+            segments: assignment.segments.iter()
+                .map(|s| Span { start: s.start_time, end: s.end_time })
+                .collect(),
+        }
+    }
+}
+
+/// Everything a run found and did, for auditing and aggregating runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    pub babymode_version: String,
+    /// Unix time the run started
+    pub started_at: u64,
+    pub input: InputReport,
+    /// Rendered file, `None` for detect-only runs
+    pub output: Option<PathBuf>,
+    pub model: String,
+    pub language: String,
+    /// Where detections came from: whisper, subtitles or labels
+    pub transcript_source: String,
+    pub lexicon: LexiconReport,
+    pub detections: Vec<DetectionReport>,
+    pub strategies: Vec<StrategyReport>,
+    pub stats: Option<CensoringStats>,
+    pub loudness: Option<LoudnessReport>,
+    pub subtitles: Vec<SubtitleReport>,
+    /// Caption cues that were masked
+    pub captions: Vec<CueChange>,
+    pub timings: Vec<StageTiming>,
+    pub tools: ToolVersions,
+}
+
+impl RunReport {
+    pub fn new(config: &Config, input: InputReport) -> Self {
+        Self {
+            babymode_version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            input,
+            output: None,
+            model: config.whisper_model.as_str().to_string(),
+            language: whisper::LANGUAGE.to_string(),
+            transcript_source: config.transcript_source.as_str().to_string(),
+            lexicon: LexiconReport {
+                version: config.lexicon.fingerprint(&config.swear_words),
+                words: config.swear_words.len(),
+            },
+            detections: Vec::new(),
+            strategies: Vec::new(),
+            stats: None,
+            loudness: None,
+            subtitles: Vec::new(),
+            captions: Vec::new(),
+            timings: Vec::new(),
+            tools: ToolVersions::default(),
+        }
+    }
+
+    /// Record the detections and the strategy groups that censored them.
+    /// `original` is the detections before refinement, in the same order.
+    pub fn set_detections(
+        &mut self,
+        original: &[WordDetection],
+        detections: &[WordDetection],
+        assignments: &[StrategyAssignment],
+        config: &Config,
+    ) {
+        self.detections = detection_reports(original, detections, assignments, config);
+        self.strategies = assignments.iter().map(StrategyReport::from).collect();
+    }
+
+    /// Write the report as pretty JSON
+    pub async fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize run report")?;
+        tokio::fs::write(path, json + "\n").await
+            .with_context(|| format!("Failed to write run report to {:?}", path))?;
+        info!("Wrote run report to {:?}", path);
+        Ok(())
+    }
+}
+
+/// A report entry per detection, with the segment that censored it
+pub fn detection_reports(
+    original: &[WordDetection],
+    detections: &[WordDetection],
+    assignments: &[StrategyAssignment],
+    config: &Config,
+) -> Vec<DetectionReport> {
+    detections.iter()
+        .enumerate()
+        .map(|(i, detection)| {
+            let rule = rules::matching_rule(config, detection);
+            let strategy = match rule {
+                Some(index) => config.rules[index].strategy.clone(),
+                None => config.strategy.clone(),
+            };
+            // Rules can share a strategy, so the group is found by rule index.
+            // Overlaps clipped away by an earlier rule leave only part of
+            // the word in its own group, hence any overlap.
+            let final_segment = assignments.iter()
+                .filter(|a| a.rule == rule)
+                .flat_map(|a| a.segments.iter())
+                .find(|s| s.start_time < detection.end_time && detection.start_time < s.end_time)
+                .map(|s| Span { start: s.start_time, end: s.end_time });
+            let before = original.get(i).unwrap_or(detection);
+            DetectionReport {
+                word: detection.word.clone(),
+                text: detection.text.clone(),
+                matched: detection.matched.clone(),
+                rule,
+                strategy,
+                category: detection.category.clone(),
+                severity: detection.severity,
+                confidence: detection.confidence,
+                speaker: detection.speaker.clone(),
+                original: Span { start: before.start_time, end: before.end_time },
+                final_segment,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioSegment;
+
+    #[test]
+    fn test_detection_reports() {
+        let config = Config::default();
+        let original = vec![WordDetection {
+            word: "damn".to_string(),
+            text: "Damn!".to_string(),
+            start_time: 1.0,
+            end_time: 1.4,
+            confidence: 0.9,
+            is_swear: true,
+            ..Default::default()
+        }.classified(Some("damn"), &config)];
+        let mut refined = original.clone();
+        refined[0].start_time = 1.05;
+        let assignments = rules::assign_strategies(&refined, &config);
+        let assignments: Vec<StrategyAssignment> = assignments.into_iter()
+            .map(|mut a| {
+                a.segments = vec![AudioSegment::new(0.95, 1.5)];
+                a
+            })
+            .collect();
+
+        let reports = detection_reports(&original, &refined, &assignments, &config);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].matched.as_deref(), Some("damn"));
+        assert_eq!(reports[0].rule, None);
+        assert_eq!(reports[0].strategy, config.strategy);
+        assert_eq!(reports[0].category.as_deref(), Some("blasphemy"));
+        assert_eq!(reports[0].original, Span { start: 1.0, end: 1.4 });
+        assert_eq!(reports[0].final_segment, Some(Span { start: 0.95, end: 1.5 }));
+
+        let json = serde_json::to_value(&reports[0]).unwrap();
+        assert_eq!(json["final"]["start"], 0.95);
+    }
+
+    #[test]
+    fn test_rules_sharing_a_strategy_report_their_own_segments() {
+        let rules: Vec<crate::rules::StrategyRule> = serde_yaml::from_str(
+            "- category: slur\n  strategy: beep\n  params: { frequency: 500 }\n- strategy: beep\n"
+        ).unwrap();
+        let config = Config { rules, ..Default::default() };
+        let word = |start: f64, category: &str| WordDetection {
+            word: "xyz".to_string(),
+            start_time: start,
+            end_time: start + 0.4,
+            is_swear: true,
+            matched: Some("xyz".to_string()),
+            category: Some(category.to_string()),
+            ..Default::default()
+        };
+        // The second word sits inside the first group's segment too
+        let detections = vec![word(1.0, "profanity"), word(2.0, "slur")];
+        let mut assignments = rules::assign_strategies(&detections, &config);
+        assignments[0].segments = vec![AudioSegment::new(0.9, 2.5)];
+        assignments[1].segments = vec![AudioSegment::new(1.9, 2.5)];
+
+        let reports = detection_reports(&detections, &detections, &assignments, &config);
+        assert_eq!(reports[1].rule, Some(0));
+        assert_eq!(reports[1].final_segment, Some(Span { start: 1.9, end: 2.5 }));
+        assert_eq!(reports[0].final_segment, Some(Span { start: 0.9, end: 2.5 }));
+        // The match comes from classification, not the reported word
+        assert_eq!(reports[0].matched.as_deref(), Some("xyz"));
+    }
+}
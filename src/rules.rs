@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use crate::config::Config;
+use crate::lexicon::Severity;
+use crate::plugins::{CensoringConfig, StrategyAssignment};
+use crate::whisper::WordDetection;
+
+/// Chooses the strategy for detections that meet all of its conditions.
+/// Conditions left unset match anything.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyRule {
+    /// Lexicon category, e.g. `slur` or `blasphemy`
+    pub category: Option<String>,
+    pub min_severity: Option<Severity>,
+    pub max_severity: Option<Severity>,
+    /// Speaker label. Only detections found from subtitles carry one, from
+    /// WebVTT voice tags or the ASS Name field.
+    pub speaker: Option<String>,
+    pub min_confidence: Option<f64>,
+    pub max_confidence: Option<f64>,
+    /// Strategy applied to matching detections
+    pub strategy: String,
+    /// Parameters for the strategy, over those from its `strategies` section
+    #[serde(default)]
+    pub params: HashMap<String, Value>,
+}
+
+impl StrategyRule {
+    pub fn matches(&self, detection: &WordDetection) -> bool {
+        let category_ok = self.category.as_ref().is_none_or(|category| {
+            detection.category.as_ref().is_some_and(|c| c.eq_ignore_ascii_case(category))
+        });
+        let severity = detection.severity.unwrap_or_default();
+        let speaker_ok = self.speaker.as_ref().is_none_or(|speaker| {
+            detection.speaker.as_ref() == Some(speaker)
+        });
+
+        category_ok
+            && speaker_ok
+            && self.min_severity.is_none_or(|min| severity >= min)
+            && self.max_severity.is_none_or(|max| severity <= max)
+            && self.min_confidence.is_none_or(|min| detection.confidence >= min)
+            && self.max_confidence.is_none_or(|max| detection.confidence <= max)
+    }
+}
+
+/// Strategy settings for detections handled by `rule`, or by the default
+/// strategy when `rule` is `None`
+pub fn rule_config(config: &Config, rule: Option<&StrategyRule>) -> (String, CensoringConfig) {
+    match rule {
+        Some(rule) => {
+            let mut censoring = CensoringConfig::for_strategy(config, &rule.strategy);
+            censoring.custom_params.extend(rule.params.clone());
+            (rule.strategy.clone(), censoring)
+        }
+        None => (config.strategy.clone(), CensoringConfig::from(config)),
+    }
+}
+
+/// Index of the first rule `detection` matches, `None` for the default
+/// strategy
+pub fn matching_rule(config: &Config, detection: &WordDetection) -> Option<usize> {
+    config.rules.iter().position(|rule| rule.matches(detection))
+}
+
+/// Group detections by the first rule they match, falling back to the
+/// default strategy, so each group can be censored with its own settings
+pub fn assign_strategies(detections: &[WordDetection], config: &Config) -> Vec<StrategyAssignment> {
+    let mut groups: Vec<StrategyAssignment> = Vec::new();
+    for detection in detections {
+        let index = matching_rule(config, detection);
+        let group = match groups.iter().position(|a| a.rule == index) {
+            Some(existing) => existing,
+            None => {
+                let (strategy, censoring) = rule_config(config, index.map(|i| &config.rules[i]));
+                groups.push(StrategyAssignment {
+                    strategy,
+                    rule: index,
+                    config: censoring,
+                    segments: Vec::new(),
+                });
+                groups.len() - 1
+            }
+        };
+        groups[group].segments.push(detection.to_audio_segment());
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detection(word: &str, category: &str, severity: Severity, confidence: f64) -> WordDetection {
+        WordDetection {
+            word: word.to_string(),
+            start_time: 1.0,
+            end_time: 1.4,
+            confidence,
+            is_swear: true,
+            matched: Some(word.to_string()),
+            category: Some(category.to_string()),
+            severity: Some(severity),
+            speaker: None,
+            text: word.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_rules_pick_strategies() {
+        let rules: Vec<StrategyRule> = serde_yaml::from_str(r#"
+- category: slur
+  strategy: beep
+  params:
+    preset: broadcast
+- max_severity: mild
+  strategy: volume_reduction
+- min_severity: strong
+  min_confidence: 0.5
+  strategy: silence
+"#).unwrap();
+        let config = Config {
+            strategy: "noise".to_string(),
+            rules,
+            ..Default::default()
+        };
+
+        let detections = vec![
+            detection("damn", "blasphemy", Severity::Mild, 0.9),
+            detection("fuck", "profanity", Severity::Strong, 0.9),
+            detection("xyz", "slur", Severity::Strong, 0.9),
+            detection("fuck", "profanity", Severity::Strong, 0.3),
+            detection("hell", "blasphemy", Severity::Mild, 0.8),
+        ];
+        let assignments = assign_strategies(&detections, &config);
+        let summary: Vec<(&str, usize)> = assignments.iter()
+            .map(|a| (a.strategy.as_str(), a.segments.len()))
+            .collect();
+        assert_eq!(summary, vec![("volume_reduction", 2), ("silence", 1), ("beep", 1), ("noise", 1)]);
+
+        // Rule parameters sit on top of the strategy's own section
+        assert_eq!(assignments[2].config.param_str("preset"), Some("broadcast"));
+    }
+}
@@ -0,0 +1,661 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::process::Command;
+
+use crate::resources::TempFile;
+use crate::whisper;
+
+/// What replaces a swear word in subtitle text
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum SubtitleMask {
+    /// `****`, one per letter
+    #[default]
+    Asterisks,
+    /// `@#$%`, one symbol per letter
+    Grawlix,
+    /// Fixed text for the whole word, e.g. `[bleep]`
+    Text(String),
+}
+
+impl SubtitleMask {
+    const GRAWLIX: [char; 6] = ['@', '#', '$', '%', '&', '!'];
+
+    /// Masked form of `word`
+    pub fn apply(&self, word: &str) -> String {
+        match self {
+            SubtitleMask::Asterisks => "*".repeat(word.chars().count()),
+            SubtitleMask::Grawlix => (0..word.chars().count())
+                .map(|i| Self::GRAWLIX[i % Self::GRAWLIX.len()])
+                .collect(),
+            SubtitleMask::Text(text) => text.clone(),
+        }
+    }
+}
+
+impl std::str::FromStr for SubtitleMask {
+    type Err = std::convert::Infallible;
+
+    /// `asterisks`, `grawlix`, or any other text used as-is
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "asterisks" | "stars" => SubtitleMask::Asterisks,
+            "grawlix" => SubtitleMask::Grawlix,
+            _ => SubtitleMask::Text(s.to_string()),
+        })
+    }
+}
+
+impl std::fmt::Display for SubtitleMask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubtitleMask::Asterisks => write!(f, "asterisks"),
+            SubtitleMask::Grawlix => write!(f, "grawlix"),
+            SubtitleMask::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+impl Serialize for SubtitleMask {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for SubtitleMask {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(s.parse().unwrap_or_default())
+    }
+}
+
+/// Text subtitle formats that can be edited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Ass,
+}
+
+impl SubtitleFormat {
+    /// Format a stream is extracted to, `None` for bitmap subtitles
+    pub fn for_codec(codec: &str) -> Option<Self> {
+        match codec {
+            "subrip" | "srt" | "mov_text" | "text" => Some(SubtitleFormat::Srt),
+            "webvtt" => Some(SubtitleFormat::Vtt),
+            "ass" | "ssa" => Some(SubtitleFormat::Ass),
+            _ => None,
+        }
+    }
+
+    /// Format of a subtitle file, from its extension
+    pub fn for_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" => Some(SubtitleFormat::Vtt),
+            "ass" | "ssa" => Some(SubtitleFormat::Ass),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+            SubtitleFormat::Ass => "ass",
+        }
+    }
+
+    /// ffmpeg encoder that writes this format
+    fn encoder(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "webvtt",
+            SubtitleFormat::Ass => "ass",
+        }
+    }
+}
+
+/// Subtitle stream of the input, as reported by ffprobe
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtitleStream {
+    /// Absolute stream index in the input, `None` for tracks that were not
+    /// read from it
+    pub index: Option<usize>,
+    pub codec: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub default: bool,
+    pub forced: bool,
+}
+
+impl SubtitleStream {
+    /// Read a stream from ffprobe's `-show_streams` JSON, if it is a subtitle
+    pub fn from_probe(stream: &serde_json::Value) -> Option<Self> {
+        if stream.get("codec_type").and_then(|t| t.as_str()) != Some("subtitle") {
+            return None;
+        }
+        let tag = |name: &str| stream.get("tags")
+            .and_then(|t| t.get(name))
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string());
+        let flag = |name: &str| stream.get("disposition")
+            .and_then(|d| d.get(name))
+            .and_then(|v| v.as_u64()) == Some(1);
+
+        Some(Self {
+            index: Some(stream.get("index")?.as_u64()? as usize),
+            codec: stream.get("codec_name").and_then(|c| c.as_str()).unwrap_or("unknown").to_string(),
+            language: tag("language"),
+            title: tag("title"),
+            default: flag("default"),
+            forced: flag("forced"),
+        })
+    }
+
+    pub(crate) fn label(&self) -> String {
+        format!(
+            "{} ({}{})",
+            self.index.map(|i| format!("#{}", i)).or_else(|| self.title.clone()).unwrap_or_default(),
+            self.codec,
+            self.language.as_ref().map(|l| format!(", {}", l)).unwrap_or_default()
+        )
+    }
+}
+
+/// A cue whose text was masked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CueChange {
+    /// Position of the cue in the track, from 1
+    pub cue: usize,
+    /// Start time as written in the track
+    pub start: String,
+    pub original: String,
+    pub censored: String,
+}
+
+/// Timed cue of a subtitle file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start: f64,
+    pub end: f64,
+    /// Cue text with markup removed and lines joined
+    pub text: String,
+    /// Speaker from a WebVTT voice tag (`<v Alice>`) or the ASS Name field
+    pub speaker: Option<String>,
+}
+
+/// Seconds from an SRT, WebVTT or ASS timestamp: `01:02:03,456`,
+/// `02:03.456` or `1:02:03.45`
+fn parse_timestamp(timestamp: &str) -> Option<f64> {
+    let timestamp = timestamp.trim().replace(',', ".");
+    let mut seconds = 0.0;
+    for part in timestamp.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(seconds)
+}
+
+/// `text` without HTML-style tags, ASS override blocks or ASS escapes
+fn plain_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '<' => for ch in chars.by_ref() { if ch == '>' { break; } },
+            '{' => for ch in chars.by_ref() { if ch == '}' { break; } },
+            '\\' => {
+                if matches!(chars.next(), Some('N' | 'n')) {
+                    out.push(' ');
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Name in the first WebVTT voice tag of `line`: `<v Alice>` or
+/// `<v.loud Alice>`
+fn voice(line: &str) -> Option<String> {
+    let start = line.find("<v")?;
+    let tag = &line[start + 2..start + line[start..].find('>')?];
+    if !tag.starts_with([' ', '\t', '.']) {
+        return None;
+    }
+    let name = tag.split_once([' ', '\t']).map_or("", |(_, name)| name.trim());
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Read the cues of a subtitle file
+pub fn parse_cues(contents: &str, format: SubtitleFormat) -> Vec<Cue> {
+    let mut cues: Vec<Cue> = Vec::new();
+    match format {
+        SubtitleFormat::Srt | SubtitleFormat::Vtt => {
+            let mut in_cue_text = false;
+            for line in contents.lines() {
+                if let Some((start, end)) = line.split_once("-->") {
+                    // WebVTT cue settings follow the end time
+                    let end = end.split_whitespace().next().unwrap_or("");
+                    in_cue_text = false;
+                    if let (Some(start), Some(end)) = (parse_timestamp(start), parse_timestamp(end)) {
+                        cues.push(Cue { start, end, text: String::new(), speaker: None });
+                        in_cue_text = true;
+                    }
+                } else if line.trim().is_empty() {
+                    in_cue_text = false;
+                } else if in_cue_text {
+                    let cue = cues.last_mut().expect("cue text follows a timing line");
+                    if cue.speaker.is_none() {
+                        cue.speaker = voice(line);
+                    }
+                    if !cue.text.is_empty() {
+                        cue.text.push(' ');
+                    }
+                    cue.text.push_str(&plain_text(line));
+                }
+            }
+        }
+        SubtitleFormat::Ass => {
+            for line in contents.lines() {
+                let Some(rest) = line.strip_prefix("Dialogue:") else { continue };
+                let fields: Vec<&str> = rest.splitn(10, ',').collect();
+                if let (Some(start), Some(end), Some(text)) = (
+                    fields.get(1).and_then(|s| parse_timestamp(s)),
+                    fields.get(2).and_then(|s| parse_timestamp(s)),
+                    fields.get(9),
+                ) {
+                    let speaker = fields.get(4).map(|name| name.trim()).filter(|name| !name.is_empty());
+                    cues.push(Cue { start, end, text: plain_text(text), speaker: speaker.map(str::to_string) });
+                }
+            }
+        }
+    }
+    cues.retain(|cue| !cue.text.is_empty() && cue.end > cue.start);
+    cues
+}
+
+/// Mask every swear word in `text`, leaving markup alone: HTML-style tags
+/// (`<i>`), ASS override blocks (`{\i1}`) and ASS escapes (`\N`).
+/// Returns `None` when nothing matched.
+pub fn mask_text(text: &str, swear_words: &[String], mask: &SubtitleMask) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut changed = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let closing = match c {
+            '<' => Some('>'),
+            '{' => Some('}'),
+            _ => None,
+        };
+        if let Some(close) = closing {
+            if let Some(len) = chars[i..].iter().position(|&ch| ch == close) {
+                out.extend(&chars[i..=i + len]);
+                i += len + 1;
+                continue;
+            }
+        }
+        if c == '\\' && i + 1 < chars.len() {
+            out.extend(&chars[i..i + 2]);
+            i += 2;
+            continue;
+        }
+
+        // Apostrophes count only inside a word, so quotes stay as they are
+        let is_word_char = |ch: char| ch.is_alphanumeric() || ch == '*';
+        if is_word_char(c) {
+            let start = i;
+            while i < chars.len()
+                && (is_word_char(chars[i])
+                    || (chars[i] == '\'' && chars.get(i + 1).is_some_and(|&next| is_word_char(next))))
+            {
+                i += 1;
+            }
+            let token: String = chars[start..i].iter().collect();
+            if whisper::matched_swear_form(&whisper::clean_word(&token), swear_words).is_some() {
+                out.push_str(&mask.apply(&token));
+                changed = true;
+            } else {
+                out.push_str(&token);
+            }
+            continue;
+        }
+
+        out.push(c);
+        i += 1;
+    }
+
+    changed.then_some(out)
+}
+
+/// Mask swear words in the cues of a subtitle file, keeping its timing,
+/// styling and layout byte for byte apart from the masked words
+pub fn censor_cues(
+    contents: &str,
+    format: SubtitleFormat,
+    swear_words: &[String],
+    mask: &SubtitleMask,
+) -> (String, Vec<CueChange>) {
+    let mut out = String::with_capacity(contents.len());
+    let mut changes: Vec<CueChange> = Vec::new();
+    let mut cue = 0;
+    let mut cue_start = String::new();
+    let mut in_cue_text = false;
+
+    for raw_line in contents.split_inclusive('\n') {
+        let line = raw_line.trim_end_matches(['\r', '\n']);
+        let ending = &raw_line[line.len()..];
+
+        let masked = match format {
+            SubtitleFormat::Srt | SubtitleFormat::Vtt => {
+                if line.contains("-->") {
+                    cue += 1;
+                    cue_start = line.split("-->").next().unwrap_or("").trim().to_string();
+                    in_cue_text = true;
+                    None
+                } else if line.trim().is_empty() {
+                    in_cue_text = false;
+                    None
+                } else if in_cue_text {
+                    mask_text(line, swear_words, mask)
+                } else {
+                    None
+                }
+            }
+            SubtitleFormat::Ass => match line.strip_prefix("Dialogue:") {
+                Some(rest) => {
+                    cue += 1;
+                    let fields: Vec<&str> = rest.splitn(10, ',').collect();
+                    cue_start = fields.get(1).map(|s| s.trim().to_string()).unwrap_or_default();
+                    match fields.get(9) {
+                        Some(text) => mask_text(text, swear_words, mask).map(|masked| {
+                            let prefix_len = line.len() - text.len();
+                            format!("{}{}", &line[..prefix_len], masked)
+                        }),
+                        None => None,
+                    }
+                }
+                None => None,
+            },
+        };
+
+        match masked {
+            Some(masked) => {
+                // Lines of one multi-line cue fold into a single change
+                let (original, censored) = match format {
+                    SubtitleFormat::Ass => (
+                        line.splitn(10, ',').nth(9).unwrap_or(line).to_string(),
+                        masked.splitn(10, ',').nth(9).unwrap_or(&masked).to_string(),
+                    ),
+                    _ => (line.to_string(), masked.clone()),
+                };
+                match changes.last_mut() {
+                    Some(last) if last.cue == cue => {
+                        last.original.push('\n');
+                        last.original.push_str(&original);
+                        last.censored.push('\n');
+                        last.censored.push_str(&censored);
+                    }
+                    _ => changes.push(CueChange { cue, start: cue_start.clone(), original, censored }),
+                }
+                out.push_str(&masked);
+            }
+            None => out.push_str(line),
+        }
+        out.push_str(ending);
+    }
+
+    (out, changes)
+}
+
+/// Cleaned copy of one subtitle stream
+#[derive(Debug)]
+pub struct CensoredTrack {
+    pub stream: SubtitleStream,
+    pub format: SubtitleFormat,
+    pub file: TempFile,
+    pub changes: Vec<CueChange>,
+}
+
+/// Cues changed in one track, as written to the run report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtitleReport {
+    pub stream: SubtitleStream,
+    pub changes: Vec<CueChange>,
+}
+
+impl CensoredTrack {
+    pub fn report(&self) -> SubtitleReport {
+        SubtitleReport {
+            stream: self.stream.clone(),
+            changes: self.changes.clone(),
+        }
+    }
+}
+
+/// Extract stream `index` of `input_path` as a text subtitle file
+pub(crate) async fn extract_track(input_path: &Path, index: usize, format: SubtitleFormat) -> Result<TempFile> {
+    let output_path = std::env::temp_dir().join(format!(
+        "babymode_subtitles_{}_{}.{}",
+        std::process::id(),
+        index,
+        format.extension()
+    ));
+
+    let output = Command::new("ffmpeg")
+        .args([
+            "-i", input_path.to_str().context("Invalid input path")?,
+            "-map", &format!("0:{}", index),
+            "-c:s", format.encoder(),
+            "-y",
+            output_path.to_str().context("Invalid subtitle path")?,
+        ])
+        .output()
+        .await
+        .context("Failed to execute ffmpeg")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg failed to extract subtitle stream {}: {}", index, error);
+    }
+    Ok(TempFile::new(output_path))
+}
+
+/// Extract each text subtitle stream and mask its swear words. Bitmap
+/// subtitles can't be read, so they are left out with a warning.
+pub async fn censor_tracks(
+    input_path: &Path,
+    streams: &[SubtitleStream],
+    swear_words: &[String],
+    mask: &SubtitleMask,
+) -> Result<Vec<CensoredTrack>> {
+    let mut tracks = Vec::new();
+    for stream in streams {
+        let (index, format) = match (stream.index, SubtitleFormat::for_codec(&stream.codec)) {
+            (Some(index), Some(format)) => (index, format),
+            _ => {
+                warn!("Dropping subtitle stream {}: {} subtitles can't be censored", stream.label(), stream.codec);
+                continue;
+            }
+        };
+
+        let file = extract_track(input_path, index, format).await?;
+        let contents = tokio::fs::read_to_string(file.path()).await
+            .with_context(|| format!("Failed to read subtitle stream {}", index))?;
+        let (censored, changes) = censor_cues(&contents, format, swear_words, mask);
+        tokio::fs::write(file.path(), censored).await
+            .with_context(|| format!("Failed to write subtitle stream {}", index))?;
+
+        info!("Subtitle stream {}: {} cues censored", stream.label(), changes.len());
+        for change in &changes {
+            info!("  cue {} at {}: {:?} -> {:?}", change.cue, change.start, change.original, change.censored);
+        }
+        tracks.push(CensoredTrack { stream: stream.clone(), format, file, changes });
+    }
+    Ok(tracks)
+}
+
+/// Subtitle codec for `format` in the container of `output_path`, or
+/// `None` when the container can't hold text subtitles
+pub fn output_codec(output_path: &Path, format: SubtitleFormat) -> Option<&'static str> {
+    let extension = output_path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "mp4" | "m4v" | "mov" | "3gp" => Some("mov_text"),
+        "webm" => Some("webvtt"),
+        "avi" | "flv" | "wmv" | "mpg" | "mpeg" => None,
+        _ => Some(format.encoder()),
+    }
+}
+
+/// ffmpeg arguments that add the cleaned files of `tracks` as inputs
+pub fn input_args(tracks: &[CensoredTrack]) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    for track in tracks {
+        args.push("-i".to_string());
+        args.push(track.file.path().to_str().context("Invalid subtitle path")?.to_string());
+    }
+    Ok(args)
+}
+
+/// ffmpeg output arguments that map `tracks` (added as inputs from
+/// `first_input` on) with their original language, title and flags
+pub fn mapping_args(tracks: &[CensoredTrack], first_input: usize, output_path: &Path) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut mapped = 0;
+    for (i, track) in tracks.iter().enumerate() {
+        let codec = match output_codec(output_path, track.format) {
+            Some(codec) => codec,
+            None => {
+                warn!("Dropping subtitle stream {}: {:?} can't hold subtitles", track.stream.label(), output_path);
+                continue;
+            }
+        };
+        args.extend(["-map".to_string(), format!("{}:0", first_input + i)]);
+        args.extend([format!("-c:s:{}", mapped), codec.to_string()]);
+        if let Some(ref language) = track.stream.language {
+            args.extend([format!("-metadata:s:s:{}", mapped), format!("language={}", language)]);
+        }
+        if let Some(ref title) = track.stream.title {
+            args.extend([format!("-metadata:s:s:{}", mapped), format!("title={}", title)]);
+        }
+        let disposition = match (track.stream.default, track.stream.forced) {
+            (true, true) => "default+forced",
+            (true, false) => "default",
+            (false, true) => "forced",
+            (false, false) => "0",
+        };
+        args.extend([format!("-disposition:s:{}", mapped), disposition.to_string()]);
+        mapped += 1;
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words() -> Vec<String> {
+        vec!["fuck".to_string(), "shit".to_string()]
+    }
+
+    #[test]
+    fn test_mask_text() {
+        let masked = mask_text("<i>Oh shit,</i> what the fuck?!", &words(), &SubtitleMask::Asterisks);
+        assert_eq!(masked.as_deref(), Some("<i>Oh ****,</i> what the ****?!"));
+
+        let masked = mask_text("{\\i1}Shit{\\i0}\\Nno", &words(), &SubtitleMask::Grawlix);
+        assert_eq!(masked.as_deref(), Some("{\\i1}@#$%{\\i0}\\Nno"));
+
+        let masked = mask_text("'fuck' it", &words(), &"[bleep]".parse().unwrap());
+        assert_eq!(masked.as_deref(), Some("'[bleep]' it"));
+
+        assert_eq!(mask_text("Shiitake mushrooms", &words(), &SubtitleMask::Asterisks), None);
+
+        // Only whole words and their forms, never a swear word inside another word
+        let words = vec!["hell".to_string(), "shit".to_string()];
+        assert_eq!(mask_text("Hello Michelle, check the shell. Well?", &words, &SubtitleMask::Asterisks), None);
+        let masked = mask_text("Hell, that's shitty sh*t", &words, &SubtitleMask::Asterisks);
+        assert_eq!(masked.as_deref(), Some("****, that's ****** ****"));
+    }
+
+    #[test]
+    fn test_censor_srt_and_vtt() {
+        let srt = "1\r\n00:00:01,000 --> 00:00:02,000\r\nWhat the fuck\r\nis <b>this shit</b>\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\nFine.\r\n";
+        let (censored, changes) = censor_cues(srt, SubtitleFormat::Srt, &words(), &SubtitleMask::Asterisks);
+        assert_eq!(
+            censored,
+            "1\r\n00:00:01,000 --> 00:00:02,000\r\nWhat the ****\r\nis <b>this ****</b>\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\nFine.\r\n"
+        );
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].cue, 1);
+        assert_eq!(changes[0].start, "00:00:01,000");
+        assert_eq!(changes[0].censored, "What the ****\nis <b>this ****</b>");
+
+        // The cue identifier and header are not cue text
+        let vtt = "WEBVTT\n\nshit-cue\n00:01.000 --> 00:02.000 align:start\nOh shit\n";
+        let (censored, changes) = censor_cues(vtt, SubtitleFormat::Vtt, &words(), &SubtitleMask::Asterisks);
+        assert_eq!(censored, "WEBVTT\n\nshit-cue\n00:01.000 --> 00:02.000 align:start\nOh ****\n");
+        assert_eq!(changes.len(), 1);
+    }
+
+    #[test]
+    fn test_censor_ass() {
+        let ass = "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                   Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{\\b1}Holy shit{\\b0}, right\n\
+                   Dialogue: 0,0:00:03.00,0:00:04.00,Default,,0,0,0,,Nothing here\n";
+        let (censored, changes) = censor_cues(ass, SubtitleFormat::Ass, &words(), &SubtitleMask::Asterisks);
+        assert!(censored.contains("Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{\\b1}Holy ****{\\b0}, right\n"));
+        assert!(censored.contains("Nothing here"));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].start, "0:00:01.00");
+        assert_eq!(changes[0].original, "{\\b1}Holy shit{\\b0}, right");
+    }
+
+    #[test]
+    fn test_parse_cues() {
+        let vtt = "WEBVTT\n\nintro\n00:01.500 --> 00:03.000 align:start\n<i>Oh shit,</i>\nlook\n\n\
+                   00:00:04.000 --> 00:00:05.250\n<v.loud Bob Smith>Fine</v>\n";
+        let cues = parse_cues(vtt, SubtitleFormat::Vtt);
+        assert_eq!(cues, vec![
+            Cue { start: 1.5, end: 3.0, text: "Oh shit, look".to_string(), speaker: None },
+            Cue { start: 4.0, end: 5.25, text: "Fine".to_string(), speaker: Some("Bob Smith".to_string()) },
+        ]);
+
+        let ass = "Dialogue: 0,0:01:02.50,0:01:04.00,Default,,0,0,0,,{\\i1}Bloody\\Nhell{\\i0}\n\
+                   Dialogue: 0,0:01:05.00,0:01:06.00,Default,Alice,0,0,0,,Damn\n";
+        let cues = parse_cues(ass, SubtitleFormat::Ass);
+        assert_eq!(cues[0].start, 62.5);
+        assert_eq!(cues[0].text, "Bloody hell");
+        assert_eq!(cues[0].speaker, None);
+        assert_eq!(cues[1].speaker.as_deref(), Some("Alice"));
+        assert_eq!(SubtitleFormat::for_path(Path::new("film.en.SRT")), Some(SubtitleFormat::Srt));
+    }
+
+    #[test]
+    fn test_stream_probe_and_codecs() {
+        let stream = SubtitleStream::from_probe(&serde_json::json!({
+            "index": 3,
+            "codec_type": "subtitle",
+            "codec_name": "mov_text",
+            "tags": {"language": "eng"},
+            "disposition": {"default": 1, "forced": 0}
+        })).unwrap();
+        assert_eq!(stream.index, Some(3));
+        assert_eq!(stream.language.as_deref(), Some("eng"));
+        assert!(stream.default);
+        assert_eq!(SubtitleFormat::for_codec(&stream.codec), Some(SubtitleFormat::Srt));
+        assert_eq!(SubtitleFormat::for_codec("hdmv_pgs_subtitle"), None);
+
+        assert_eq!(output_codec(Path::new("out.mp4"), SubtitleFormat::Ass), Some("mov_text"));
+        assert_eq!(output_codec(Path::new("out.mkv"), SubtitleFormat::Ass), Some("ass"));
+        assert_eq!(output_codec(Path::new("out.avi"), SubtitleFormat::Srt), None);
+    }
+}
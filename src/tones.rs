@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use crate::error::{config_error, BabymodeError, Result};
+
+/// Built-in tones used by the beep strategy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BeepPreset {
+    /// Plain 1 kHz sine
+    #[default]
+    Classic,
+    /// Harder-edged bleep with a touch of third harmonic
+    Broadcast,
+    /// Two sines a fifth apart
+    DualTone,
+    /// Nasal sawtooth with a falling pitch and wobble
+    DuckQuack,
+}
+
+impl BeepPreset {
+    pub const ALL: [BeepPreset; 4] = [
+        BeepPreset::Classic,
+        BeepPreset::Broadcast,
+        BeepPreset::DualTone,
+        BeepPreset::DuckQuack,
+    ];
+
+    /// Short names accepted for presets, and the preset each stands for
+    pub const ALIASES: &'static [(&'static str, &'static str)] = &[
+        ("dual", "dual_tone"),
+        ("duck", "duck_quack"),
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BeepPreset::Classic => "classic",
+            BeepPreset::Broadcast => "broadcast",
+            BeepPreset::DualTone => "dual_tone",
+            BeepPreset::DuckQuack => "duck_quack",
+        }
+    }
+
+    /// `aevalsrc` expression of the waveform, peak-normalised to 1.0.
+    /// `frequency` is the fundamental for the tonal presets; the duck
+    /// quack has its own pitch contour over `duration`.
+    pub fn expression(&self, frequency: f32, duration: f64) -> String {
+        match self {
+            BeepPreset::Classic => format!("sin(2*PI*{}*t)", frequency),
+            BeepPreset::Broadcast => format!(
+                "0.8*sin(2*PI*{f}*t)+0.2*sin(2*PI*{f3}*t)",
+                f = frequency, f3 = frequency * 3.0
+            ),
+            BeepPreset::DualTone => format!(
+                "0.5*sin(2*PI*{f}*t)+0.5*sin(2*PI*{f2}*t)",
+                f = frequency, f2 = frequency * 1.5
+            ),
+            BeepPreset::DuckQuack => format!(
+                "(2*mod(450*t-67.5*t*t/{d:.4},1)-1)*(0.6+0.4*sin(2*PI*12*t))",
+                d = duration.max(0.01)
+            ),
+        }
+    }
+
+    /// RMS of the peak-normalised waveform, used for level matching
+    pub fn rms(&self) -> f32 {
+        match self {
+            BeepPreset::Classic => std::f32::consts::FRAC_1_SQRT_2,
+            BeepPreset::Broadcast => 0.583,
+            BeepPreset::DualTone => 0.5,
+            BeepPreset::DuckQuack => 0.383,
+        }
+    }
+
+    /// Fade length at the start and end of each tone
+    pub fn edge(&self) -> f32 {
+        match self {
+            BeepPreset::Classic => 0.010,
+            BeepPreset::Broadcast => 0.003,
+            BeepPreset::DualTone => 0.010,
+            BeepPreset::DuckQuack => 0.015,
+        }
+    }
+}
+
+impl std::str::FromStr for BeepPreset {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "classic" => Ok(BeepPreset::Classic),
+            "broadcast" => Ok(BeepPreset::Broadcast),
+            "dual_tone" => Ok(BeepPreset::DualTone),
+            "duck_quack" => Ok(BeepPreset::DuckQuack),
+            other => match Self::ALIASES.iter().find(|(alias, _)| *alias == other) {
+                Some((_, preset)) => preset.parse(),
+                None => Err(config_error(
+                    "preset",
+                    format!("Invalid beep preset '{}'. Valid options: classic, broadcast, dual_tone, duck_quack", s)
+                )),
+            },
+        }
+    }
+}
+
+/// Colour of the noise used by the noise strategy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoiseColor {
+    #[default]
+    White,
+    Pink,
+    Brown,
+}
+
+impl NoiseColor {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NoiseColor::White => "white",
+            NoiseColor::Pink => "pink",
+            NoiseColor::Brown => "brown",
+        }
+    }
+
+    /// Approximate RMS of `anoisesrc` at amplitude 1.0, used for level matching
+    pub fn rms(&self) -> f32 {
+        match self {
+            NoiseColor::White => 0.577, // uniform distribution, 1/sqrt(3)
+            NoiseColor::Pink => 0.25,
+            NoiseColor::Brown => 0.2,
+        }
+    }
+}
+
+impl std::str::FromStr for NoiseColor {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "white" => Ok(NoiseColor::White),
+            "pink" => Ok(NoiseColor::Pink),
+            "brown" | "brownian" => Ok(NoiseColor::Brown),
+            _ => Err(config_error(
+                "color",
+                format!("Invalid noise color '{}'. Valid options: white, pink, brown", s)
+            )),
+        }
+    }
+}
+
+/// Convert decibels to a linear amplitude factor
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// Gain that brings a waveform with RMS `source_rms` to the surrounding
+/// dialogue level plus `offset_db`. Falls back to `fallback_dbfs` when no
+/// dialogue was measured, and never lets the peak exceed full scale.
+pub fn matched_gain(source_rms: f32, dialogue_rms: Option<f32>, offset_db: f32, fallback_dbfs: f32) -> f32 {
+    let target_rms = match dialogue_rms {
+        Some(rms) => rms * db_to_gain(offset_db),
+        None => db_to_gain(fallback_dbfs),
+    };
+    (target_rms / source_rms.max(1e-6)).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preset_parsing() {
+        for preset in BeepPreset::ALL {
+            assert_eq!(preset.as_str().parse::<BeepPreset>().unwrap(), preset);
+        }
+        assert_eq!("duck".parse::<BeepPreset>().unwrap(), BeepPreset::DuckQuack);
+        assert!("siren".parse::<BeepPreset>().is_err());
+    }
+
+    #[test]
+    fn test_matched_gain() {
+        // A sine matched to -20 dBFS dialogue with no offset
+        let gain = matched_gain(BeepPreset::Classic.rms(), Some(0.1), 0.0, -18.0);
+        assert!((gain * BeepPreset::Classic.rms() - 0.1).abs() < 1e-6);
+
+        // Loud dialogue cannot push the tone past full scale
+        assert_eq!(matched_gain(0.5, Some(0.9), 6.0, -18.0), 1.0);
+
+        // No dialogue measured, use the fallback level
+        let gain = matched_gain(1.0, None, 0.0, -20.0);
+        assert!((gain - 0.1).abs() < 1e-6);
+    }
+}
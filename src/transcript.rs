@@ -0,0 +1,417 @@
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::audio::{self, AudioSegment};
+use crate::config::Config;
+use crate::error::{config_error, BabymodeError};
+use crate::refine::{self, EnergyEnvelope};
+use crate::resources::TempFile;
+use crate::subtitles::{self, Cue, SubtitleFormat, SubtitleStream};
+use crate::whisper::{self, WordDetection};
+
+/// Sample rate used for energy localisation
+const ANALYSIS_RATE: u32 = 16000;
+/// Slack around each cue, since subtitle timing is rarely exact
+const CUE_MARGIN: f64 = 0.5;
+/// How far energy localisation may move an estimated word edge
+const ENERGY_WINDOW: f64 = 0.3;
+/// Confidence of a word whose timing was estimated rather than transcribed
+const ESTIMATED_CONFIDENCE: f64 = 0.8;
+
+/// Where the words to censor are read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptSource {
+    /// Transcribe the whole programme
+    #[default]
+    Whisper,
+    /// Read a subtitle track and only transcribe cues with swear words
+    Subtitles,
+}
+
+impl TranscriptSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TranscriptSource::Whisper => "whisper",
+            TranscriptSource::Subtitles => "subtitles",
+        }
+    }
+}
+
+impl std::str::FromStr for TranscriptSource {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "whisper" => Ok(TranscriptSource::Whisper),
+            "subtitles" | "subtitle" => Ok(TranscriptSource::Subtitles),
+            _ => Err(config_error(
+                "transcript_source",
+                format!("Invalid transcript source '{}'. Valid options: whisper, subtitles", s)
+            )),
+        }
+    }
+}
+
+/// How a swear word found in a cue is pinned to the audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CueLocalizer {
+    /// Transcribe the cue's audio for word timestamps
+    #[default]
+    Whisper,
+    /// Estimate from the word's place in the cue, snapped to the energy envelope
+    Energy,
+}
+
+impl CueLocalizer {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CueLocalizer::Whisper => "whisper",
+            CueLocalizer::Energy => "energy",
+        }
+    }
+}
+
+impl std::str::FromStr for CueLocalizer {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "whisper" => Ok(CueLocalizer::Whisper),
+            "energy" => Ok(CueLocalizer::Energy),
+            _ => Err(config_error(
+                "cue_localizer",
+                format!("Invalid cue localizer '{}'. Valid options: whisper, energy", s)
+            )),
+        }
+    }
+}
+
+/// Swear word found in the text of a cue
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueMatch {
+    pub cue: Cue,
+    /// The word as written in the cue
+    pub text: String,
+    /// Lexicon word it matched
+    pub matched: String,
+    /// Where the word sits in the cue text, as fractions of its length
+    pub position: (f64, f64),
+}
+
+impl CueMatch {
+    /// Timing of the word if speech runs evenly through the cue
+    fn estimate(&self) -> AudioSegment {
+        let duration = self.cue.end - self.cue.start;
+        AudioSegment::new(
+            self.cue.start + duration * self.position.0,
+            self.cue.start + duration * self.position.1,
+        )
+    }
+
+    fn detection(&self, segment: &AudioSegment, config: &Config) -> WordDetection {
+        WordDetection {
+            word: whisper::clean_word(&self.text),
+            start_time: segment.start_time,
+            end_time: segment.end_time,
+            confidence: ESTIMATED_CONFIDENCE,
+            is_swear: true,
+            speaker: self.cue.speaker.clone(),
+            text: self.text.clone(),
+            ..Default::default()
+        }
+        .classified(Some(&self.matched), config)
+    }
+}
+
+/// Swear words in the text of `cues`. Written text is matched by whole
+/// words and their forms, so "Hello" never counts as "hell".
+pub fn find_matches(cues: &[Cue], swear_words: &[String]) -> Vec<CueMatch> {
+    let mut matches = Vec::new();
+    for cue in cues {
+        let total = cue.text.chars().count() as f64;
+        let mut offset = 0;
+        for token in cue.text.split(' ') {
+            let len = token.chars().count();
+            if let Some(matched) = whisper::matched_swear_form(&whisper::clean_word(token), swear_words) {
+                matches.push(CueMatch {
+                    cue: cue.clone(),
+                    text: token.to_string(),
+                    matched: matched.to_string(),
+                    position: (offset as f64 / total, (offset + len) as f64 / total),
+                });
+            }
+            offset += len + 1;
+        }
+    }
+    matches
+}
+
+/// Pin each match to the quiet gaps around where its cue suggests it is
+pub async fn localize_energy(audio_path: &Path, matches: &[CueMatch], config: &Config) -> Result<Vec<WordDetection>> {
+    let mut detections = Vec::new();
+    for cue_match in matches {
+        let estimate = cue_match.estimate();
+        let analysis_start = (estimate.start_time - ENERGY_WINDOW).max(0.0);
+        let samples = audio::read_pcm_samples(
+            audio_path,
+            analysis_start,
+            estimate.end_time + ENERGY_WINDOW - analysis_start,
+            ANALYSIS_RATE,
+        ).await?;
+
+        let envelope = EnergyEnvelope::from_samples(&samples, ANALYSIS_RATE, analysis_start);
+        let segment = refine::refine_segment(&envelope, &estimate, ENERGY_WINDOW);
+        debug!(
+            "Estimated '{}' at {:.3}-{:.3}s from its cue",
+            cue_match.text, segment.start_time, segment.end_time
+        );
+        detections.push(cue_match.detection(&segment, config));
+    }
+    Ok(detections)
+}
+
+/// Audio windows around the matched cues, merged where they overlap, with
+/// the indices of the matches each one holds
+fn windows(matches: &[CueMatch]) -> Vec<((f64, f64), Vec<usize>)> {
+    let mut order: Vec<usize> = (0..matches.len()).collect();
+    order.sort_by(|&a, &b| matches[a].cue.start.total_cmp(&matches[b].cue.start));
+
+    let mut windows: Vec<((f64, f64), Vec<usize>)> = Vec::new();
+    for i in order {
+        let start = (matches[i].cue.start - CUE_MARGIN).max(0.0);
+        let end = matches[i].cue.end + CUE_MARGIN;
+        match windows.last_mut() {
+            Some(((_, last_end), indices)) if start <= *last_end => {
+                *last_end = last_end.max(end);
+                indices.push(i);
+            }
+            _ => windows.push(((start, end), vec![i])),
+        }
+    }
+    windows
+}
+
+/// Window that `time` in the joined windows falls in, and the matching
+/// time in the programme
+fn programme_time(spans: &[(f64, f64)], time: f64) -> (usize, f64) {
+    let mut offset = 0.0;
+    for (k, (start, end)) in spans.iter().enumerate() {
+        let length = end - start;
+        if time < offset + length || k == spans.len() - 1 {
+            return (k, start + (time - offset).clamp(0.0, length));
+        }
+        offset += length;
+    }
+    (0, time)
+}
+
+/// Pair each swear word Whisper heard in window `k` of `windows` with the
+/// cue match it most likely is: one whose cue (with margin) holds the word,
+/// preferring the same lexicon word, then the closest estimate. Returns
+/// the match each hit paired with.
+fn pair_hits(
+    matches: &[CueMatch],
+    windows: &[((f64, f64), Vec<usize>)],
+    hits: &[(usize, AudioSegment, Option<String>)],
+) -> Vec<Option<usize>> {
+    let mut heard = vec![false; matches.len()];
+    let mut pairs = Vec::with_capacity(hits.len());
+    for (k, hit, matched) in hits {
+        let middle = (hit.start_time + hit.end_time) / 2.0;
+        let distance = |i: usize| {
+            let estimate = matches[i].estimate();
+            ((estimate.start_time + estimate.end_time) / 2.0 - middle).abs()
+        };
+        let best = windows[*k].1.iter().copied()
+            .filter(|&i| !heard[i])
+            .filter(|&i| {
+                let cue = &matches[i].cue;
+                (cue.start - CUE_MARGIN..=cue.end + CUE_MARGIN).contains(&middle)
+            })
+            .min_by(|&a, &b| {
+                let differs = |i: usize| matched.as_deref() != Some(matches[i].matched.as_str());
+                differs(a).cmp(&differs(b)).then(distance(a).total_cmp(&distance(b)))
+            });
+        if let Some(i) = best {
+            heard[i] = true;
+        }
+        pairs.push(best);
+    }
+    pairs
+}
+
+/// Transcribe only the audio around the matched cues, in one Whisper run.
+/// Matches Whisper doesn't hear fall back to energy localisation.
+pub async fn localize_whisper(audio_path: &Path, matches: &[CueMatch], config: &Config) -> Result<Vec<WordDetection>> {
+    let windows = windows(matches);
+    let spans: Vec<(f64, f64)> = windows.iter().map(|(span, _)| *span).collect();
+    let covered: f64 = spans.iter().map(|(start, end)| end - start).sum();
+    info!("Transcribing {:.1}s of audio around {} cue windows", covered, spans.len());
+
+    let joined = TempFile::new(std::env::temp_dir().join(format!(
+        "babymode_cues_{}.wav",
+        std::process::id()
+    )));
+    audio::extract_windows(audio_path, joined.path(), &spans).await?;
+    let words = whisper::transcribe(joined.path(), config).await?;
+
+    let mut detections = Vec::new();
+    let mut hits = Vec::new();
+    for word in words.into_iter().filter(|w| w.is_swear) {
+        let (k, start_time) = programme_time(&spans, word.start_time);
+        let end_time = (start_time + word.end_time - word.start_time).min(spans[k].1);
+        hits.push((k, AudioSegment::new(start_time, end_time), word.matched.clone()));
+        detections.push(WordDetection { start_time, end_time, ..word });
+    }
+
+    // Whisper has no speaker labels, so heard words take their cue's
+    let mut heard = vec![false; matches.len()];
+    for (detection, pair) in detections.iter_mut().zip(pair_hits(matches, &windows, &hits)) {
+        if let Some(i) = pair {
+            heard[i] = true;
+            detection.speaker = matches[i].cue.speaker.clone();
+        }
+    }
+    let missed: Vec<CueMatch> = matches.iter().zip(&heard)
+        .filter(|(_, heard)| !**heard)
+        .map(|(cue_match, _)| cue_match.clone())
+        .collect();
+    if !missed.is_empty() {
+        debug!("Whisper did not hear {} of {} cue matches, estimating them from their cues", missed.len(), matches.len());
+        detections.extend(localize_energy(audio_path, &missed, config).await?);
+    }
+
+    detections.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    Ok(detections)
+}
+
+/// Cues of the configured sidecar file, or else of the input's default text
+/// subtitle stream (its first when none is marked default)
+async fn read_cues(config: &Config, streams: &[SubtitleStream]) -> Result<Option<(String, Vec<Cue>)>> {
+    if let Some(ref path) = config.subtitle_file {
+        let format = SubtitleFormat::for_path(path)
+            .with_context(|| format!("Unsupported subtitle file {:?}, expected srt, vtt or ass", path))?;
+        let contents = tokio::fs::read_to_string(path).await
+            .with_context(|| format!("Failed to read subtitle file {:?}", path))?;
+        return Ok(Some((format!("{:?}", path), subtitles::parse_cues(&contents, format))));
+    }
+
+    let text_streams: Vec<&SubtitleStream> = streams.iter()
+        .filter(|s| s.index.is_some() && SubtitleFormat::for_codec(&s.codec).is_some())
+        .collect();
+    let stream = match text_streams.iter().find(|s| s.default).or(text_streams.first()) {
+        Some(stream) => *stream,
+        None => return Ok(None),
+    };
+    let (Some(index), Some(format)) = (stream.index, SubtitleFormat::for_codec(&stream.codec)) else {
+        return Ok(None);
+    };
+
+    let file = subtitles::extract_track(&config.input_file, index, format).await?;
+    let contents = tokio::fs::read_to_string(file.path()).await
+        .with_context(|| format!("Failed to read subtitle stream {}", index))?;
+    Ok(Some((stream.label(), subtitles::parse_cues(&contents, format))))
+}
+
+/// Find swear words from subtitle cues, then localise only those words in
+/// `audio_path`. Returns `None` when there are no subtitles to read.
+pub async fn detect_from_subtitles(
+    audio_path: &Path,
+    config: &Config,
+    streams: &[SubtitleStream],
+) -> Result<Option<Vec<WordDetection>>> {
+    let (label, cues) = match read_cues(config, streams).await? {
+        Some((label, cues)) if !cues.is_empty() => (label, cues),
+        Some((label, _)) => {
+            warn!("Subtitles {} have no cues", label);
+            return Ok(None);
+        }
+        None => return Ok(None),
+    };
+
+    let matches = find_matches(&cues, &config.swear_words);
+    info!(
+        "Subtitles {}: {} swear words in {} cues, localising with {}",
+        label,
+        matches.len(),
+        cues.len(),
+        config.cue_localizer.as_str()
+    );
+    if matches.is_empty() {
+        return Ok(Some(Vec::new()));
+    }
+
+    let detections = match config.cue_localizer {
+        CueLocalizer::Whisper => localize_whisper(audio_path, &matches, config).await?,
+        CueLocalizer::Energy => localize_energy(audio_path, &matches, config).await?,
+    };
+    info!("Found {} swear word occurrences", detections.len());
+    Ok(Some(detections))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start: f64, end: f64, text: &str) -> Cue {
+        Cue { start, end, text: text.to_string(), speaker: None }
+    }
+
+    #[test]
+    fn test_find_matches() {
+        let swear_words = vec!["shit".to_string()];
+        let cues = vec![cue(1.0, 3.0, "Oh shit, look"), cue(5.0, 6.0, "Nothing here")];
+        let matches = find_matches(&cues, &swear_words);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].text, "shit,");
+        assert_eq!(matches[0].matched, "shit");
+
+        // "shit," is characters 3..8 of 13, over a two second cue
+        let estimate = matches[0].estimate();
+        assert!((estimate.start_time - (1.0 + 2.0 * 3.0 / 13.0)).abs() < 1e-9);
+        assert!((estimate.end_time - (1.0 + 2.0 * 8.0 / 13.0)).abs() < 1e-9);
+
+        // The cue's speaker carries over so speaker rules can match
+        let cues = vec![Cue { speaker: Some("Alice".to_string()), ..cue(1.0, 3.0, "Oh shit") }];
+        let matches = find_matches(&cues, &swear_words);
+        let detection = matches[0].detection(&matches[0].estimate(), &Config::default());
+        assert_eq!(detection.speaker.as_deref(), Some("Alice"));
+    }
+
+    #[test]
+    fn test_cue_windows() {
+        let swear_words = vec!["shit".to_string()];
+        let cues = vec![cue(10.0, 11.0, "shit"), cue(1.0, 2.0, "shit"), cue(2.5, 3.0, "shit")];
+        let matches = find_matches(&cues, &swear_words);
+        let windows = windows(&matches);
+        assert_eq!(windows, vec![((0.5, 3.5), vec![1, 2]), ((9.5, 11.5), vec![0])]);
+
+        // The second window starts 3 s into the joined audio
+        let spans: Vec<(f64, f64)> = windows.iter().map(|(span, _)| *span).collect();
+        assert_eq!(programme_time(&spans, 1.0), (0, 1.5));
+        assert_eq!(programme_time(&spans, 3.5), (1, 10.0));
+        assert_eq!(programme_time(&spans, 9.0), (1, 11.5));
+    }
+
+    #[test]
+    fn test_unheard_matches_fall_back_per_match() {
+        let swear_words = vec!["shit".to_string(), "damn".to_string()];
+        let cues = vec![cue(1.0, 3.0, "shit, damn it"), cue(3.2, 4.0, "Hello shit")];
+        let matches = find_matches(&cues, &swear_words);
+        assert_eq!(matches.len(), 3);
+        let windows = windows(&matches);
+        assert_eq!(windows.len(), 1);
+
+        // Whisper only heard the first "shit" in the shared window
+        let hits = vec![(0, AudioSegment::new(1.1, 1.4), Some("shit".to_string()))];
+        assert_eq!(pair_hits(&matches, &windows, &hits), vec![Some(0)]);
+
+        // A hit near the second cue pairs with its own "shit", not the first
+        let hits = vec![(0, AudioSegment::new(3.7, 3.95), Some("shit".to_string()))];
+        assert_eq!(pair_hits(&matches, &windows, &hits), vec![Some(2)]);
+    }
+}
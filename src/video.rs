@@ -1,11 +1,14 @@
 use anyhow::{Context, Result};
-use log::{debug, info};
+use log::{debug, info, warn};
+use crate::encoding::AudioEncoding;
+use crate::error::BabymodeError;
+use crate::subtitles::{self, CensoredTrack, SubtitleStream};
 use std::path::Path;
 use tokio::process::Command;
 
-/// Supported video file extensions
-const SUPPORTED_EXTENSIONS: &[&str] = &[
-    "mp4", "avi", "mov", "mkv", "wmv", "flv", "webm", "m4v", "3gp", "mpg", "mpeg"
+/// Audio-only file extensions, used to pick the output format
+pub const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "m4a", "aac", "flac", "wav", "opus", "ogg", "oga", "aiff", "aif", "wma"
 ];
 
 /// Video metadata structure
@@ -18,34 +21,222 @@ pub struct VideoMetadata {
     pub has_audio: bool,
     pub codec: String,
     pub bitrate: Option<u64>,
+    /// How the first video stream is encoded
+    pub format: VideoFormat,
 }
 
-/// Validate that the given file is a supported video file
-pub fn validate_video_file(path: &Path) -> Result<()> {
-    if !path.exists() {
-        anyhow::bail!("Video file does not exist: {:?}", path);
+/// Encoding parameters of a video stream, used to re-encode parts of it so
+/// they can be joined to stream-copied parts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VideoFormat {
+    pub codec: String,
+    /// Codec profile as ffprobe names it, e.g. `High` or `Main 10`
+    pub profile: Option<String>,
+    /// Codec level as ffprobe reports it, e.g. 40 for H.264 level 4.0
+    pub level: Option<i64>,
+    pub pix_fmt: Option<String>,
+    pub color_range: Option<String>,
+    pub color_space: Option<String>,
+    pub color_transfer: Option<String>,
+    pub color_primaries: Option<String>,
+    /// Stream bitrate in bits per second
+    pub bitrate: Option<u64>,
+}
+
+impl VideoFormat {
+    /// Read the format of an ffprobe stream entry
+    pub fn from_stream(stream: &serde_json::Value) -> Self {
+        let text = |key: &str| stream.get(key)
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty() && *v != "unknown" && *v != "reserved")
+            .map(|v| v.to_string());
+        Self {
+            codec: text("codec_name").unwrap_or_default(),
+            profile: text("profile"),
+            level: stream.get("level").and_then(|l| l.as_i64()).filter(|&l| l > 0),
+            pix_fmt: text("pix_fmt"),
+            color_range: text("color_range"),
+            color_space: text("color_space"),
+            color_transfer: text("color_transfer"),
+            color_primaries: text("color_primaries"),
+            bitrate: text("bit_rate").and_then(|b| b.parse().ok()),
+        }
     }
 
-    if !path.is_file() {
-        anyhow::bail!("Path is not a file: {:?}", path);
+    /// Colour tags to copy onto re-encoded video
+    fn color_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (flag, value) in [
+            ("-color_range", &self.color_range),
+            ("-colorspace", &self.color_space),
+            ("-color_trc", &self.color_transfer),
+            ("-color_primaries", &self.color_primaries),
+        ] {
+            if let Some(value) = value {
+                args.extend([flag.to_string(), value.clone()]);
+            }
+        }
+        args
     }
 
-    let extension = path
-        .extension()
+    /// Cap the bitrate of re-encoded video near the source's, so the edited
+    /// GOPs don't stand out in size or overflow the decoder buffer
+    fn rate_args(&self) -> Vec<String> {
+        match self.bitrate {
+            Some(bitrate) => vec![
+                "-maxrate".to_string(), bitrate.to_string(),
+                "-bufsize".to_string(), (bitrate * 2).to_string(),
+            ],
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Whether `path` has the extension of an audio-only format
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
         .and_then(|ext| ext.to_str())
-        .context("File has no extension")?
-        .to_lowercase();
-
-    if !SUPPORTED_EXTENSIONS.contains(&extension.as_str()) {
-        anyhow::bail!(
-            "Unsupported video format: {}. Supported formats: {:?}",
-            extension,
-            SUPPORTED_EXTENSIONS
-        );
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// What ffprobe found in an input file
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaProbe {
+    /// Container, as named by ffprobe (e.g. `mov,mp4,m4a,3gp,3g2,mj2`)
+    pub format_name: String,
+    /// Whether there is a video stream other than cover art
+    pub has_video: bool,
+    /// Whether an audio-only file carries cover art
+    pub has_cover_art: bool,
+    /// Codec of the first audio stream
+    pub audio_codec: String,
+    /// Soft subtitle and caption streams
+    pub subtitle_streams: Vec<SubtitleStream>,
+}
+
+impl MediaProbe {
+    pub fn is_audio_only(&self) -> bool {
+        !self.has_video
     }
+}
 
-    debug!("Video file validation passed for: {:?}", path);
-    Ok(())
+/// Check that `path` is something ffmpeg can read and decode audio from,
+/// whatever its extension
+pub async fn validate_media_file(path: &Path) -> crate::error::Result<MediaProbe> {
+    if !path.exists() {
+        return Err(BabymodeError::VideoProcessing {
+            message: format!("Input file does not exist: {:?}", path),
+        });
+    }
+    if !path.is_file() {
+        return Err(BabymodeError::VideoProcessing {
+            message: format!("Input path is not a file: {:?}", path),
+        });
+    }
+    let path_str = path.to_str().ok_or_else(|| BabymodeError::VideoProcessing {
+        message: format!("Invalid path encoding: {:?}", path),
+    })?;
+
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-print_format", "json", "-show_format", "-show_streams", path_str])
+        .output()
+        .await
+        .map_err(|e| BabymodeError::VideoProcessing {
+            message: format!("Failed to execute ffprobe: {}", e),
+        })?;
+
+    if !output.status.success() {
+        return Err(BabymodeError::VideoProcessing {
+            message: format!(
+                "{:?} is corrupt or not a media file: {}",
+                path,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
+    }
+
+    let probe_data: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| BabymodeError::VideoProcessing {
+            message: format!("Failed to parse ffprobe output for {:?}: {}", path, e),
+        })?;
+    let probe = parse_media_probe(path, &probe_data)?;
+
+    // ffprobe only reads headers; decode a little audio to catch missing
+    // decoders and damaged streams
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-i", path_str, "-map", "0:a:0", "-t", "1", "-f", "null", "-"])
+        .output()
+        .await
+        .map_err(|e| BabymodeError::VideoProcessing {
+            message: format!("Failed to execute ffmpeg: {}", e),
+        })?;
+    if !output.status.success() {
+        return Err(decode_error(path, &probe.audio_codec, &String::from_utf8_lossy(&output.stderr)));
+    }
+
+    debug!("Media probe for {:?}: {:?}", path, probe);
+    Ok(probe)
+}
+
+/// Streams of interest in ffprobe's `-show_format -show_streams` JSON
+fn parse_media_probe(path: &Path, probe_data: &serde_json::Value) -> crate::error::Result<MediaProbe> {
+    let streams = probe_data.get("streams")
+        .and_then(|s| s.as_array())
+        .map(|s| s.as_slice())
+        .unwrap_or_default();
+    let format_name = probe_data.get("format")
+        .and_then(|f| f.get("format_name"))
+        .and_then(|f| f.as_str())
+        .unwrap_or("unknown")
+        .to_string();
+
+    let of_type = |codec_type: &str| -> Vec<&serde_json::Value> {
+        streams.iter()
+            .filter(|s| s.get("codec_type").and_then(|t| t.as_str()) == Some(codec_type))
+            .collect()
+    };
+    let is_cover_art = |stream: &&serde_json::Value| stream.get("disposition")
+        .and_then(|d| d.get("attached_pic"))
+        .and_then(|a| a.as_u64()) == Some(1);
+    let video = of_type("video");
+
+    let audio = *of_type("audio").first().ok_or_else(|| BabymodeError::VideoProcessing {
+        message: format!("No audio stream found in {:?} ({})", path, format_name),
+    })?;
+
+    let audio_codec = audio.get("codec_name").and_then(|c| c.as_str()).unwrap_or("none");
+    if audio_codec == "none" || audio_codec == "unknown" {
+        let tag = audio.get("codec_tag_string").and_then(|t| t.as_str()).unwrap_or("unknown");
+        return Err(unsupported_codec(tag));
+    }
+
+    Ok(MediaProbe {
+        has_video: video.iter().any(|s| !is_cover_art(s)),
+        has_cover_art: video.iter().any(is_cover_art),
+        audio_codec: audio_codec.to_string(),
+        subtitle_streams: streams.iter().filter_map(SubtitleStream::from_probe).collect(),
+        format_name,
+    })
+}
+
+fn unsupported_codec(codec: &str) -> BabymodeError {
+    BabymodeError::UnsupportedFormat {
+        extension: format!("audio codec {}", codec),
+        supported: vec!["audio codecs this ffmpeg build can decode (see `ffmpeg -decoders`)".to_string()],
+    }
+}
+
+/// Tell a missing decoder apart from a damaged stream in ffmpeg's stderr
+fn decode_error(path: &Path, codec: &str, stderr: &str) -> BabymodeError {
+    let lower = stderr.to_lowercase();
+    if lower.contains("decoder") && (lower.contains("not found") || lower.contains("unknown"))
+        || lower.contains("unsupported codec")
+    {
+        return unsupported_codec(codec);
+    }
+    BabymodeError::VideoProcessing {
+        message: format!("Audio in {:?} could not be decoded, the file may be corrupt: {}", path, stderr.trim()),
+    }
 }
 
 /// Get video metadata using ffprobe
@@ -133,6 +324,8 @@ pub async fn get_video_metadata(path: &Path) -> Result<VideoMetadata> {
 
     let fps = parse_frame_rate(fps_str)?;
 
+    let format = VideoFormat::from_stream(video_stream);
+
     let metadata = VideoMetadata {
         duration,
         width,
@@ -141,6 +334,7 @@ pub async fn get_video_metadata(path: &Path) -> Result<VideoMetadata> {
         has_audio,
         codec,
         bitrate,
+        format,
     };
 
     debug!("Video metadata: {:?}", metadata);
@@ -171,21 +365,32 @@ pub async fn combine_video_audio(
     video_path: &Path,
     audio_path: &Path,
     output_path: &Path,
+    encoding: &AudioEncoding,
+    subtitles: &[CensoredTrack],
 ) -> Result<()> {
     info!("Combining video {:?} with audio {:?}", video_path, audio_path);
 
+    let mut args: Vec<String> = [
+        "-i", video_path.to_str().context("Invalid video path")?,
+        "-i", audio_path.to_str().context("Invalid audio path")?,
+    ].map(String::from).to_vec();
+    args.extend(subtitles::input_args(subtitles)?);
+    args.extend([
+        "-c:v", "copy", // Copy video stream without re-encoding
+        "-map", "0:v:0", // Map first video stream from first input
+        "-map", "1:a:0", // Map first audio stream from second input
+    ].map(String::from));
+    args.extend(encoding.args());
+    // Cleaned subtitle tracks follow the video and audio inputs
+    args.extend(subtitles::mapping_args(subtitles, 2, output_path));
+    args.extend([
+        "-shortest", // End when shortest stream ends
+        "-y", // Overwrite output file if it exists
+        output_path.to_str().context("Invalid output path")?,
+    ].map(String::from));
+
     let output = Command::new("ffmpeg")
-        .args([
-            "-i", video_path.to_str().context("Invalid video path")?,
-            "-i", audio_path.to_str().context("Invalid audio path")?,
-            "-c:v", "copy", // Copy video stream without re-encoding
-            "-c:a", "aac",  // Re-encode audio as AAC
-            "-map", "0:v:0", // Map first video stream from first input
-            "-map", "1:a:0", // Map first audio stream from second input
-            "-shortest", // End when shortest stream ends
-            "-y", // Overwrite output file if it exists
-            output_path.to_str().context("Invalid output path")?,
-        ])
+        .args(&args)
         .output()
         .await
         .context("Failed to execute ffmpeg")?;
@@ -199,6 +404,269 @@ pub async fn combine_video_audio(
     Ok(())
 }
 
+/// Keyframe timestamps of the first video stream, in seconds
+pub async fn keyframe_times(path: &Path) -> Result<Vec<f64>> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v", "error",
+            "-select_streams", "v:0",
+            "-skip_frame", "nokey",
+            "-show_entries", "frame=pts_time",
+            "-of", "csv=p=0",
+            path.to_str().context("Invalid path encoding")?,
+        ])
+        .output()
+        .await
+        .context("Failed to execute ffprobe")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffprobe failed to list keyframes: {}", error);
+    }
+
+    let mut keyframes: Vec<f64> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().trim_end_matches(',').parse().ok())
+        .collect();
+    keyframes.sort_by(f64::total_cmp);
+    Ok(keyframes)
+}
+
+/// Spans of whole GOPs that cover `regions`, merged where they meet. Each
+/// span starts on a keyframe and ends on the keyframe after its last
+/// region, or at `duration`.
+pub fn plan_chunks(keyframes: &[f64], regions: &[(f64, f64)], duration: f64) -> Vec<(f64, f64)> {
+    let mut sorted = regions.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut chunks: Vec<(f64, f64)> = Vec::new();
+    for (start, end) in sorted {
+        let chunk_start = keyframes.iter().rev().find(|&&k| k <= start).copied().unwrap_or(0.0);
+        let chunk_end = keyframes.iter().find(|&&k| k > end).copied().unwrap_or(duration);
+        match chunks.last_mut() {
+            Some(last) if chunk_start <= last.1 => last.1 = last.1.max(chunk_end),
+            _ => chunks.push((chunk_start, chunk_end)),
+        }
+    }
+    chunks
+}
+
+/// Encoder arguments that write the same codec, profile, level, pixel
+/// format and colour tags as the source, with parameter sets repeated on
+/// every keyframe, so re-encoded chunks can be joined to stream-copied ones.
+/// `None` for codecs that can't be re-encoded in part.
+pub fn chunk_encoder_args(format: &VideoFormat) -> Option<Vec<String>> {
+    let mut args: Vec<String> = match format.codec.as_str() {
+        "h264" => {
+            let mut args = ["-c:v", "libx264", "-preset", "medium", "-crf", "18"].map(String::from).to_vec();
+            let profile = format.profile.as_deref().and_then(|p| match p {
+                "Baseline" | "Constrained Baseline" => Some("baseline"),
+                "Main" => Some("main"),
+                "High" => Some("high"),
+                "High 10" => Some("high10"),
+                "High 4:2:2" => Some("high422"),
+                "High 4:4:4 Predictive" => Some("high444"),
+                _ => None,
+            });
+            if let Some(profile) = profile {
+                args.extend(["-profile:v".to_string(), profile.to_string()]);
+            }
+            if let Some(level) = format.level {
+                args.extend(["-level".to_string(), format!("{:.1}", level as f64 / 10.0)]);
+            }
+            args.extend(["-x264-params".to_string(), "repeat-headers=1".to_string()]);
+            args
+        }
+        "hevc" => {
+            let mut args = ["-c:v", "libx265", "-preset", "medium", "-crf", "20"].map(String::from).to_vec();
+            let profile = format.profile.as_deref().and_then(|p| match p {
+                "Main" => Some("main"),
+                "Main 10" => Some("main10"),
+                "Main Still Picture" => Some("mainstillpicture"),
+                _ => None,
+            });
+            if let Some(profile) = profile {
+                args.extend(["-profile:v".to_string(), profile.to_string()]);
+            }
+            let mut params = "repeat-headers=1".to_string();
+            if let Some(level) = format.level {
+                params.push_str(&format!(":level-idc={:.1}", level as f64 / 30.0));
+            }
+            args.extend(["-x265-params".to_string(), params]);
+            args
+        }
+        "vp9" => {
+            let mut args = ["-c:v", "libvpx-vp9", "-crf", "24", "-row-mt", "1"].map(String::from).to_vec();
+            // Constrained quality needs a target bitrate, 0 means pure CRF
+            args.extend(["-b:v".to_string(), format.bitrate.unwrap_or(0).to_string()]);
+            let profile = format.profile.as_deref()
+                .and_then(|p| p.strip_prefix("Profile "))
+                .filter(|p| p.len() == 1);
+            if let Some(profile) = profile {
+                args.extend(["-profile:v".to_string(), profile.to_string()]);
+            }
+            args
+        }
+        _ => return None,
+    };
+    if format.codec != "vp9" {
+        args.extend(format.rate_args());
+    }
+    if let Some(ref pix_fmt) = format.pix_fmt {
+        args.extend(["-pix_fmt".to_string(), pix_fmt.clone()]);
+    }
+    args.extend(format.color_args());
+    Some(args)
+}
+
+/// Encoder arguments for re-encoding a whole stream whose codec has no
+/// matching encoder
+fn fallback_encoder_args(format: &VideoFormat) -> Vec<String> {
+    let mut args = ["-c:v", "libx264", "-preset", "medium", "-crf", "18"].map(String::from).to_vec();
+    args.extend(format.color_args());
+    args
+}
+
+/// Bitstream filter that puts the parameter sets in-band on every keyframe
+/// of a stream-copied piece. MP4 and Matroska keep them only in extradata,
+/// and after a join the decoder would otherwise apply a re-encoded chunk's
+/// SPS/PPS to the copied GOPs that follow it.
+fn copy_bitstream_filter(codec: &str) -> Option<&'static str> {
+    match codec {
+        "h264" => Some("h264_mp4toannexb"),
+        "hevc" => Some("hevc_mp4toannexb"),
+        _ => None,
+    }
+}
+
+/// Cut `start..end` of the first video stream of `input_path` into `piece`,
+/// copying it (through `copy_bsf`, if any), or re-encoding it through
+/// `filter` when one is given
+async fn write_piece(
+    input_path: &Path,
+    piece: &Path,
+    start: f64,
+    end: f64,
+    filter: Option<(&str, &[String])>,
+    copy_bsf: Option<&str>,
+) -> Result<()> {
+    let mut args: Vec<String> = [
+        "-ss", &format!("{:.6}", start),
+        "-i", input_path.to_str().context("Invalid input path")?,
+        "-t", &format!("{:.6}", end - start),
+    ].map(String::from).to_vec();
+    match filter {
+        Some((graph, encoder_args)) => {
+            args.extend(["-filter_complex".to_string(), graph.to_string(), "-map".to_string(), "[v]".to_string()]);
+            args.extend(encoder_args.iter().cloned());
+        }
+        None => {
+            args.extend(["-map", "0:v:0", "-c", "copy", "-avoid_negative_ts", "make_zero"].map(String::from));
+            if let Some(bsf) = copy_bsf {
+                args.extend(["-bsf:v".to_string(), bsf.to_string()]);
+            }
+        }
+    }
+    args.extend(["-an", "-sn", "-y", piece.to_str().context("Invalid piece path")?].map(String::from));
+
+    let output = Command::new("ffmpeg")
+        .args(&args)
+        .output()
+        .await
+        .context("Failed to execute ffmpeg")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg failed to write video piece {:.2}-{:.2}s: {}", start, end, error);
+    }
+    Ok(())
+}
+
+/// Write the first video stream of `input_path` to `output_path` (Matroska,
+/// no audio), re-encoding only the GOPs around `regions`. `filter` gets the
+/// start and end of each re-encoded chunk and returns a filter graph from
+/// `[0:v]` to `[v]`, with times relative to the chunk. Chunks are encoded
+/// to match the source (see [`chunk_encoder_args`]) and everything else is
+/// stream-copied with its parameter sets in-band. Sources without a matching encoder are re-encoded whole
+/// with libx264.
+pub async fn reencode_regions<F>(
+    input_path: &Path,
+    output_path: &Path,
+    regions: &[(f64, f64)],
+    filter: F,
+) -> Result<()>
+where
+    F: Fn(f64, f64) -> String,
+{
+    let metadata = get_video_metadata(input_path).await?;
+    if regions.is_empty() {
+        debug!("No video regions to re-encode, copying the stream");
+        return write_piece(input_path, output_path, 0.0, metadata.duration, None, None).await;
+    }
+
+    let (encoder_args, chunks) = match chunk_encoder_args(&metadata.format) {
+        Some(args) => {
+            let keyframes = keyframe_times(input_path).await?;
+            (args, plan_chunks(&keyframes, regions, metadata.duration))
+        }
+        None => {
+            warn!("Can't re-encode {} video in part, re-encoding all of it", metadata.codec);
+            (fallback_encoder_args(&metadata.format), vec![(0.0, metadata.duration)])
+        }
+    };
+    debug!("Chunk encoder: {}", encoder_args.join(" "));
+    let copy_bsf = copy_bitstream_filter(&metadata.codec);
+
+    let reencoded: f64 = chunks.iter().map(|(start, end)| end - start).sum();
+    info!(
+        "Re-encoding {:.1}s of {:.1}s of video in {} chunks",
+        reencoded, metadata.duration, chunks.len()
+    );
+
+    let pieces_dir = tempfile::tempdir().context("Failed to create directory for video pieces")?;
+    let mut pieces = Vec::new();
+    let mut position = 0.0;
+    for (start, end) in chunks.iter().copied().chain(std::iter::once((metadata.duration, metadata.duration))) {
+        if start > position {
+            let piece = pieces_dir.path().join(format!("piece_{:04}.mkv", pieces.len()));
+            write_piece(input_path, &piece, position, start, None, copy_bsf).await?;
+            pieces.push(piece);
+        }
+        if end > start {
+            let piece = pieces_dir.path().join(format!("piece_{:04}.mkv", pieces.len()));
+            write_piece(input_path, &piece, start, end, Some((&filter(start, end), &encoder_args)), None).await?;
+            pieces.push(piece);
+        }
+        position = position.max(end);
+    }
+
+    let list_path = pieces_dir.path().join("pieces.txt");
+    let list: String = pieces.iter()
+        .map(|piece| format!("file '{}'\n", piece.display()))
+        .collect();
+    tokio::fs::write(&list_path, list).await.context("Failed to write video piece list")?;
+
+    let output = Command::new("ffmpeg")
+        .args([
+            "-f", "concat",
+            "-safe", "0",
+            "-i", list_path.to_str().context("Invalid piece list path")?,
+            "-c", "copy",
+            "-y",
+            output_path.to_str().context("Invalid output path")?,
+        ])
+        .output()
+        .await
+        .context("Failed to execute ffmpeg")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg failed to join video pieces: {}", error);
+    }
+    debug!("Joined {} video pieces into {:?}", pieces.len(), output_path);
+    Ok(())
+}
+
 /// Extract video without audio (for testing purposes)
 pub async fn extract_video_only(input_path: &Path, output_path: &Path) -> Result<()> {
     let output = Command::new("ffmpeg")
@@ -233,20 +701,155 @@ mod tests {
     }
 
     #[test]
-    fn test_validate_video_file_extension() {
-        use std::fs::File;
-        use tempfile::tempdir;
-
-        let temp_dir = tempdir().unwrap();
-        
-        // Test valid extension
-        let valid_path = temp_dir.path().join("test.mp4");
-        File::create(&valid_path).unwrap();
-        assert!(validate_video_file(&valid_path).is_ok());
-
-        // Test invalid extension
-        let invalid_path = temp_dir.path().join("test.txt");
-        File::create(&invalid_path).unwrap();
-        assert!(validate_video_file(&invalid_path).is_err());
-    }
-}
\ No newline at end of file
+    fn test_is_audio_file() {
+        assert!(is_audio_file(Path::new("episode.MP3")));
+        assert!(is_audio_file(Path::new("book.m4a")));
+        assert!(!is_audio_file(Path::new("clip.mp4")));
+        assert!(!is_audio_file(Path::new("no_extension")));
+    }
+
+    #[test]
+    fn test_parse_media_probe() {
+        let path = Path::new("input");
+        let podcast = serde_json::json!({
+            "format": {"format_name": "mp3"},
+            "streams": [
+                {"codec_type": "audio", "codec_name": "mp3"},
+                {"codec_type": "video", "codec_name": "mjpeg", "disposition": {"attached_pic": 1}}
+            ]
+        });
+        let probe = parse_media_probe(path, &podcast).unwrap();
+        assert!(probe.is_audio_only());
+        assert!(probe.subtitle_streams.is_empty());
+        assert!(probe.has_cover_art);
+        assert_eq!(probe.audio_codec, "mp3");
+
+        let film = serde_json::json!({
+            "format": {"format_name": "matroska,webm"},
+            "streams": [
+                {"codec_type": "video", "codec_name": "h264", "disposition": {"attached_pic": 0}},
+                {"codec_type": "audio", "codec_name": "opus"},
+                {"index": 2, "codec_type": "subtitle", "codec_name": "ass", "tags": {"language": "eng"}}
+            ]
+        });
+        let probe = parse_media_probe(path, &film).unwrap();
+        assert!(probe.has_video);
+        assert_eq!(probe.subtitle_streams.len(), 1);
+        assert_eq!(probe.subtitle_streams[0].index, Some(2));
+
+        let silent = serde_json::json!({"streams": [{"codec_type": "video", "codec_name": "h264"}]});
+        assert!(matches!(parse_media_probe(path, &silent), Err(BabymodeError::VideoProcessing { .. })));
+
+        let odd = serde_json::json!({"streams": [{"codec_type": "audio", "codec_tag_string": "XYZ1"}]});
+        assert!(matches!(parse_media_probe(path, &odd), Err(BabymodeError::UnsupportedFormat { .. })));
+    }
+
+    #[test]
+    fn test_decode_error_kinds() {
+        let path = Path::new("input.mkv");
+        assert!(matches!(
+            decode_error(path, "ac4", "Decoder (codec ac4) not found for input stream #0:1"),
+            BabymodeError::UnsupportedFormat { .. }
+        ));
+        assert!(matches!(
+            decode_error(path, "aac", "[aac @ 0x1] channel element 0.0 is not allocated\nError while decoding stream"),
+            BabymodeError::VideoProcessing { .. }
+        ));
+    }
+
+    #[test]
+    fn test_plan_chunks() {
+        let keyframes = [0.0, 2.0, 4.0, 6.0, 8.0];
+        // Regions in the same or neighbouring GOPs share a chunk
+        let chunks = plan_chunks(&keyframes, &[(4.5, 5.0), (2.5, 3.0), (8.5, 9.0)], 10.0);
+        assert_eq!(chunks, vec![(2.0, 6.0), (8.0, 10.0)]);
+    }
+
+    #[test]
+    fn test_chunk_encoder_args() {
+        let stream = serde_json::json!({
+            "codec_name": "h264",
+            "profile": "High",
+            "level": 41,
+            "pix_fmt": "yuv420p",
+            "color_range": "tv",
+            "color_space": "bt709",
+            "color_transfer": "unknown",
+            "bit_rate": "4000000"
+        });
+        let format = VideoFormat::from_stream(&stream);
+        assert_eq!(format.color_transfer, None);
+        assert_eq!(
+            chunk_encoder_args(&format).unwrap().join(" "),
+            "-c:v libx264 -preset medium -crf 18 -profile:v high -level 4.1 -x264-params repeat-headers=1 \
+             -maxrate 4000000 -bufsize 8000000 -pix_fmt yuv420p -color_range tv -colorspace bt709"
+        );
+
+        let hevc = VideoFormat {
+            codec: "hevc".to_string(),
+            profile: Some("Main 10".to_string()),
+            level: Some(123),
+            ..Default::default()
+        };
+        assert_eq!(
+            chunk_encoder_args(&hevc).unwrap().join(" "),
+            "-c:v libx265 -preset medium -crf 20 -profile:v main10 -x265-params repeat-headers=1:level-idc=4.1"
+        );
+
+        let vp9 = VideoFormat { codec: "vp9".to_string(), profile: Some("Profile 2".to_string()), ..Default::default() };
+        assert!(chunk_encoder_args(&vp9).unwrap().join(" ").ends_with("-b:v 0 -profile:v 2"));
+        assert!(chunk_encoder_args(&VideoFormat { codec: "prores".to_string(), ..Default::default() }).is_none());
+    }
+
+    async fn ffmpeg_has_encoder(encoder: &str) -> bool {
+        match Command::new("ffmpeg").args(["-hide_banner", "-encoders"]).output().await {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).contains(encoder),
+            Err(_) => false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spliced_h264_mp4_decodes_cleanly() {
+        if !ffmpeg_has_encoder("libx264").await {
+            println!("Skipping: ffmpeg with libx264 not available");
+            return;
+        }
+
+        // A CAVLC source (ultrafast) whose SPS/PPS differ from the CABAC
+        // chunks, with parameter sets only in the MP4's avcC extradata
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().join("source.mp4");
+        let output = Command::new("ffmpeg")
+            .args(["-f", "lavfi", "-i", "testsrc=duration=8:size=320x240:rate=25"])
+            .args(["-c:v", "libx264", "-preset", "ultrafast", "-g", "50", "-pix_fmt", "yuv420p", "-y"])
+            .arg(&source)
+            .output()
+            .await
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+        let edited = temp_dir.path().join("edited.mkv");
+        reencode_regions(&source, &edited, &[(2.5, 3.0)], |_, _| "[0:v]boxblur=8[v]".to_string())
+            .await
+            .unwrap();
+
+        let output = Command::new("ffmpeg")
+            .args(["-v", "error", "-xerror", "-i"])
+            .arg(&edited)
+            .args(["-f", "null", "-"])
+            .output()
+            .await
+            .unwrap();
+        let errors = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success() && errors.trim().is_empty(), "decode errors: {}", errors);
+
+        let frames = Command::new("ffprobe")
+            .args(["-v", "error", "-count_frames", "-select_streams", "v:0"])
+            .args(["-show_entries", "stream=nb_read_frames", "-of", "csv=p=0"])
+            .arg(&edited)
+            .output()
+            .await
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&frames.stdout).trim(), "200");
+    }
+}
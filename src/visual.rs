@@ -0,0 +1,441 @@
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use tempfile::NamedTempFile;
+use tokio::process::Command;
+
+use crate::audio::AudioSegment;
+use crate::error::{config_error, BabymodeError};
+use crate::indicator::Indicator;
+use crate::video;
+
+/// How the mouth region is hidden
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlurStyle {
+    #[default]
+    Blur,
+    Pixelate,
+}
+
+impl BlurStyle {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlurStyle::Blur => "blur",
+            BlurStyle::Pixelate => "pixelate",
+        }
+    }
+}
+
+impl std::str::FromStr for BlurStyle {
+    type Err = BabymodeError;
+
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "blur" => Ok(BlurStyle::Blur),
+            "pixelate" | "pixel" | "mosaic" => Ok(BlurStyle::Pixelate),
+            _ => Err(config_error(
+                "mouth_blur",
+                format!("Invalid blur style '{}'. Valid options: blur, pixelate", s)
+            )),
+        }
+    }
+}
+
+/// Which part of the frame is hidden
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum BlurRegion {
+    /// The mouth of the largest face, found in each segment
+    #[default]
+    Face,
+    /// A fixed area: pixels, or fractions of the frame when every value is
+    /// at most 1
+    Fixed { x: f64, y: f64, width: f64, height: f64 },
+}
+
+impl BlurRegion {
+    /// The fixed area in pixels of a `frame_width` by `frame_height` frame
+    pub fn rect(&self, frame_width: u32, frame_height: u32) -> Option<Rect> {
+        match *self {
+            BlurRegion::Face => None,
+            BlurRegion::Fixed { x, y, width, height } => {
+                let (sx, sy) = if [x, y, width, height].iter().all(|v| *v <= 1.0) {
+                    (frame_width as f64, frame_height as f64)
+                } else {
+                    (1.0, 1.0)
+                };
+                Rect::new(x * sx, y * sy, width * sx, height * sy, frame_width, frame_height)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for BlurRegion {
+    type Err = BabymodeError;
+
+    /// `face`, or `x,y,width,height`
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        if s.trim().eq_ignore_ascii_case("face") {
+            return Ok(BlurRegion::Face);
+        }
+        let values: Vec<f64> = s.split(',')
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| config_error("blur_region", format!("Invalid blur region '{}'. Use face or x,y,width,height", s)))?;
+        match values[..] {
+            [x, y, width, height] if x >= 0.0 && y >= 0.0 && width > 0.0 && height > 0.0 => {
+                Ok(BlurRegion::Fixed { x, y, width, height })
+            }
+            _ => Err(config_error(
+                "blur_region",
+                format!("Blur region '{}' needs four values: x,y,width,height, with a positive size", s)
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for BlurRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlurRegion::Face => write!(f, "face"),
+            BlurRegion::Fixed { x, y, width, height } => write!(f, "{},{},{},{}", x, y, width, height),
+        }
+    }
+}
+
+impl Serialize for BlurRegion {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for BlurRegion {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Area of the frame, in pixels, with even edges for 4:2:0 video
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    /// Clamp an area to the frame, or `None` when too little of it is left
+    fn new(x: f64, y: f64, width: f64, height: f64, frame_width: u32, frame_height: u32) -> Option<Self> {
+        let even = |v: f64| (v.max(0.0) as u32) & !1;
+        let x = even(x).min(frame_width);
+        let y = even(y).min(frame_height);
+        let width = even(width).min((frame_width - x) & !1);
+        let height = even(height).min((frame_height - y) & !1);
+        (width >= 8 && height >= 8).then_some(Self { x, y, width, height })
+    }
+
+    /// Filter that hides the cropped area
+    fn hide_filter(&self, style: BlurStyle) -> String {
+        let crop = format!("crop={}:{}:{}:{}", self.width, self.height, self.x, self.y);
+        let size = self.width.min(self.height);
+        match style {
+            BlurStyle::Blur => {
+                let radius = (size / 6).max(1);
+                format!(
+                    "{},boxblur=luma_radius={}:luma_power=2:chroma_radius={}:chroma_power=2",
+                    crop, radius, (radius / 2).max(1)
+                )
+            }
+            BlurStyle::Pixelate => {
+                let block = (size / 8).max(2);
+                format!(
+                    "{},scale={}:{}:flags=area,scale={}:{}:flags=neighbor",
+                    crop,
+                    (self.width / block).max(1),
+                    (self.height / block).max(1),
+                    self.width,
+                    self.height
+                )
+            }
+        }
+    }
+}
+
+/// Stretch of video to hide an area in
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlurWindow {
+    pub start: f64,
+    pub end: f64,
+    pub rect: Rect,
+}
+
+/// Filter graph from `[input]` to `[output]` that hides each window
+/// overlapping `chunk_start..chunk_end`, with times relative to the chunk
+pub fn blur_filter(
+    style: BlurStyle,
+    windows: &[BlurWindow],
+    chunk_start: f64,
+    chunk_end: f64,
+    input: &str,
+    output: &str,
+) -> String {
+    let active: Vec<&BlurWindow> = windows.iter()
+        .filter(|w| w.end > chunk_start && w.start < chunk_end)
+        .collect();
+    if active.is_empty() {
+        return format!("[{}]null[{}]", input, output);
+    }
+
+    let mut graph = format!("[{}]split={}[base]", input, active.len() + 1);
+    for i in 0..active.len() {
+        graph.push_str(&format!("[src{}]", i));
+    }
+    for (i, window) in active.iter().enumerate() {
+        graph.push_str(&format!(";[src{}]{}[hidden{}]", i, window.rect.hide_filter(style), i));
+    }
+    for (i, window) in active.iter().enumerate() {
+        let from = if i == 0 { "base".to_string() } else { format!("out{}", i - 1) };
+        let to = if i == active.len() - 1 { output.to_string() } else { format!("out{}", i) };
+        graph.push_str(&format!(
+            ";[{}][hidden{}]overlay={}:{}:enable='between(t,{:.3},{:.3})'[{}]",
+            from,
+            i,
+            window.rect.x,
+            window.rect.y,
+            (window.start - chunk_start).max(0.0),
+            window.end - chunk_start,
+            to
+        ));
+    }
+    graph
+}
+
+/// Create a temporary Python script that finds the mouth of the largest face
+/// in each segment with OpenCV's bundled Haar cascade
+fn create_face_script() -> Result<NamedTempFile> {
+    let script_content = r#"
+import sys
+import json
+import cv2
+
+def mouth_regions(video_path, segments):
+    cascade = cv2.CascadeClassifier(cv2.data.haarcascades + "haarcascade_frontalface_default.xml")
+    capture = cv2.VideoCapture(video_path)
+    regions = []
+    for start, end in segments:
+        box = None
+        for i in range(5):
+            capture.set(cv2.CAP_PROP_POS_MSEC, (start + (end - start) * i / 4) * 1000)
+            ok, frame = capture.read()
+            if not ok:
+                continue
+            gray = cv2.cvtColor(frame, cv2.COLOR_BGR2GRAY)
+            faces = cascade.detectMultiScale(gray, scaleFactor=1.1, minNeighbors=5, minSize=(32, 32))
+            if len(faces) == 0:
+                continue
+            x, y, w, h = max(faces, key=lambda f: f[2] * f[3])
+            # Mouth and chin: the lower part of the face, a little wider
+            mouth = (x - w * 0.1, y + h * 0.55, x + w * 1.1, y + h * 1.05)
+            if box is None:
+                box = mouth
+            else:
+                box = (min(box[0], mouth[0]), min(box[1], mouth[1]), max(box[2], mouth[2]), max(box[3], mouth[3]))
+        regions.append(None if box is None else [float(box[0]), float(box[1]), float(box[2] - box[0]), float(box[3] - box[1])])
+    return regions
+
+if __name__ == "__main__":
+    video_path = sys.argv[1]
+    segments = json.loads(sys.argv[2])
+    print(json.dumps(mouth_regions(video_path, segments)))
+"#;
+
+    let mut temp_file = NamedTempFile::new()
+        .context("Failed to create temporary Python script")?;
+    temp_file.write_all(script_content.as_bytes())
+        .context("Failed to write Python script")?;
+    temp_file.flush().context("Failed to flush Python script")?;
+    Ok(temp_file)
+}
+
+/// Mouth area of the largest face in each of `segments`, `None` where no
+/// face was found
+async fn detect_mouths(video_path: &Path, segments: &[(f64, f64)]) -> Result<Vec<Option<[f64; 4]>>> {
+    let script = create_face_script()?;
+    let output = Command::new("python3")
+        .args([
+            script.path().to_str().context("Invalid script path")?,
+            video_path.to_str().context("Invalid video path")?,
+            &serde_json::to_string(segments)?,
+        ])
+        .output()
+        .await
+        .context("Failed to run Python for face detection")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        if error.contains("No module named 'cv2'") {
+            anyhow::bail!("Face detection needs OpenCV: pip install opencv-python");
+        }
+        anyhow::bail!("Face detection failed: {}", error);
+    }
+
+    let regions: Vec<Option<[f64; 4]>> = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse face detection results")?;
+    if regions.len() != segments.len() {
+        anyhow::bail!("Face detection returned {} regions for {} segments", regions.len(), segments.len());
+    }
+    Ok(regions)
+}
+
+/// Where to hide the mouth during each censored segment, widened by
+/// `margin`. Segments where no face is found are left out.
+pub async fn blur_windows(
+    input_path: &Path,
+    style: BlurStyle,
+    region: &BlurRegion,
+    segments: &[AudioSegment],
+    margin: f64,
+) -> Result<Vec<BlurWindow>> {
+    let metadata = video::get_video_metadata(input_path).await?;
+    let spans: Vec<(f64, f64)> = segments.iter()
+        .map(|s| ((s.start_time - margin).max(0.0), (s.end_time + margin).min(metadata.duration)))
+        .collect();
+
+    let rects: Vec<Option<Rect>> = match region {
+        BlurRegion::Face => detect_mouths(input_path, &spans).await?
+            .into_iter()
+            .map(|found| found.and_then(|[x, y, w, h]| Rect::new(x, y, w, h, metadata.width, metadata.height)))
+            .collect(),
+        fixed => {
+            let rect = fixed.rect(metadata.width, metadata.height)
+                .with_context(|| format!("Blur region {} is outside the {}x{} frame", fixed, metadata.width, metadata.height))?;
+            vec![Some(rect); spans.len()]
+        }
+    };
+
+    let mut windows = Vec::new();
+    for (&(start, end), rect) in spans.iter().zip(rects) {
+        match rect {
+            Some(rect) => windows.push(BlurWindow { start, end, rect }),
+            None => warn!("No face found at {:.2}-{:.2}s, leaving it unblurred", start, end),
+        }
+    }
+    info!("Hiding mouths in {} of {} segments with {}", windows.len(), spans.len(), style.as_str());
+    for window in &windows {
+        debug!("  {:.2}-{:.2}s: {:?}", window.start, window.end, window.rect);
+    }
+    Ok(windows)
+}
+
+/// Picture changes made while words are censored, applied in one pass
+#[derive(Debug, Clone, Default)]
+pub struct VideoEdits {
+    /// Mouth areas to hide
+    pub blur: Option<(BlurStyle, Vec<BlurWindow>)>,
+    /// Indicator to draw and the censored segments to draw it in
+    pub indicator: Option<(Indicator, Vec<(f64, f64)>)>,
+}
+
+impl VideoEdits {
+    pub fn is_empty(&self) -> bool {
+        self.blur.is_none() && self.indicator.is_none()
+    }
+
+    /// Every stretch of video that changes
+    fn regions(&self) -> Vec<(f64, f64)> {
+        let mut regions: Vec<(f64, f64)> = Vec::new();
+        if let Some((_, ref windows)) = self.blur {
+            regions.extend(windows.iter().map(|w| (w.start, w.end)));
+        }
+        if let Some((_, ref windows)) = self.indicator {
+            regions.extend(windows.iter().copied());
+        }
+        regions
+    }
+
+    /// Filter graph from `[0:v]` to `[v]` for `chunk_start..chunk_end`:
+    /// the blur first, so the indicator is drawn over it
+    pub fn filter(&self, chunk_start: f64, chunk_end: f64) -> String {
+        match (&self.blur, &self.indicator) {
+            (Some((style, windows)), Some((indicator, segments))) => format!(
+                "{};{}",
+                blur_filter(*style, windows, chunk_start, chunk_end, "0:v", "blurred"),
+                indicator.filter(segments, chunk_start, chunk_end, "blurred", "v")
+            ),
+            (Some((style, windows)), None) => blur_filter(*style, windows, chunk_start, chunk_end, "0:v", "v"),
+            (None, Some((indicator, segments))) => indicator.filter(segments, chunk_start, chunk_end, "0:v", "v"),
+            (None, None) => "[0:v]null[v]".to_string(),
+        }
+    }
+
+    /// Write the video stream of `input_path` with the edits to
+    /// `output_path` (Matroska). Only the GOPs around the edits are
+    /// re-encoded.
+    pub async fn apply(&self, input_path: &Path, output_path: &Path) -> Result<()> {
+        let regions = self.regions();
+        video::reencode_regions(input_path, output_path, &regions, |chunk_start, chunk_end| {
+            self.filter(chunk_start, chunk_end)
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blur_region() {
+        assert_eq!("face".parse::<BlurRegion>().unwrap(), BlurRegion::Face);
+        let fraction: BlurRegion = "0.25,0.5,0.5,0.25".parse().unwrap();
+        assert_eq!(fraction.rect(1920, 1080), Some(Rect { x: 480, y: 540, width: 960, height: 270 }));
+        let pixels: BlurRegion = "1801,1001,400,400".parse().unwrap();
+        assert_eq!(pixels.rect(1920, 1080), Some(Rect { x: 1800, y: 1000, width: 120, height: 80 }));
+        assert_eq!(pixels.to_string(), "1801,1001,400,400");
+        assert!("1,2,3".parse::<BlurRegion>().is_err());
+        assert!("0,0,0,10".parse::<BlurRegion>().is_err());
+    }
+
+    #[test]
+    fn test_blur_filter() {
+        let rect = Rect { x: 100, y: 200, width: 64, height: 48 };
+        let windows = vec![
+            BlurWindow { start: 10.0, end: 11.0, rect },
+            BlurWindow { start: 12.5, end: 13.0, rect },
+            BlurWindow { start: 40.0, end: 41.0, rect },
+        ];
+        let graph = blur_filter(BlurStyle::Blur, &windows, 8.0, 16.0, "0:v", "v");
+        assert_eq!(
+            graph,
+            "[0:v]split=3[base][src0][src1]\
+             ;[src0]crop=64:48:100:200,boxblur=luma_radius=8:luma_power=2:chroma_radius=4:chroma_power=2[hidden0]\
+             ;[src1]crop=64:48:100:200,boxblur=luma_radius=8:luma_power=2:chroma_radius=4:chroma_power=2[hidden1]\
+             ;[base][hidden0]overlay=100:200:enable='between(t,2.000,3.000)'[out0]\
+             ;[out0][hidden1]overlay=100:200:enable='between(t,4.500,5.000)'[v]"
+        );
+        assert!(blur_filter(BlurStyle::Pixelate, &windows, 39.0, 42.0, "0:v", "v").contains("scale=10:8:flags=area,scale=64:48:flags=neighbor"));
+        assert_eq!(blur_filter(BlurStyle::Blur, &windows, 20.0, 30.0, "0:v", "v"), "[0:v]null[v]");
+    }
+
+    #[test]
+    fn test_video_edits() {
+        let rect = Rect { x: 100, y: 200, width: 64, height: 48 };
+        let indicator = crate::indicator::IndicatorSettings {
+            style: Some(crate::indicator::IndicatorStyle::Bar),
+            ..Default::default()
+        }.indicator().unwrap().unwrap();
+        let edits = VideoEdits {
+            blur: Some((BlurStyle::Blur, vec![BlurWindow { start: 9.75, end: 11.25, rect }])),
+            indicator: Some((indicator, vec![(10.0, 11.0)])),
+        };
+        assert_eq!(edits.regions(), vec![(9.75, 11.25), (10.0, 11.0)]);
+        let graph = edits.filter(8.0, 16.0);
+        assert!(graph.starts_with("[0:v]split=2[base][src0];"));
+        assert!(graph.contains("[base][hidden0]overlay=100:200:enable='between(t,1.750,3.250)'[blurred];[blurred]drawbox="));
+        assert!(graph.ends_with("enable='between(t,2.000,3.000)'[v]"));
+        assert!(VideoEdits::default().is_empty());
+    }
+}
@@ -9,22 +9,51 @@ use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 
 use crate::audio::AudioSegment;
+use crate::lexicon::Severity;
 use crate::Config;
 
+/// Language Whisper transcribes in
+pub const LANGUAGE: &str = "en";
+
 /// Word detection result with timing and confidence
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct WordDetection {
     pub word: String,
     pub start_time: f64,
     pub end_time: f64,
     pub confidence: f64,
     pub is_swear: bool,
+    /// Entry of the swear word list this word matched
+    #[serde(default)]
+    pub matched: Option<String>,
+    /// Lexicon category of the matched swear word
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    /// Speaker label, when the transcript has one
+    #[serde(default)]
+    pub speaker: Option<String>,
+    /// The word as transcribed, with case and punctuation
+    #[serde(default)]
+    pub text: String,
 }
 
 impl WordDetection {
     pub fn to_audio_segment(&self) -> AudioSegment {
         AudioSegment::new(self.start_time, self.end_time)
     }
+
+    /// Record `matched` and set category and severity from its lexicon entry
+    pub(crate) fn classified(mut self, matched: Option<&str>, config: &Config) -> Self {
+        if let Some(swear) = matched {
+            self.matched = Some(swear.to_string());
+            let entry = config.lexicon.classify(swear);
+            self.category = Some(entry.category);
+            self.severity = Some(entry.severity);
+        }
+        self
+    }
 }
 
 /// Whisper transcription segment
@@ -47,6 +76,18 @@ struct WhisperWord {
 
 /// Detect swear words in audio using faster-whisper via Python
 pub async fn detect_swear_words(audio_path: &Path, config: &Config) -> Result<Vec<WordDetection>> {
+    let swear_detections: Vec<WordDetection> = transcribe(audio_path, config).await?
+        .into_iter()
+        .filter(|d| d.is_swear)
+        .collect();
+
+    info!("Found {} swear word occurrences", swear_detections.len());
+    Ok(swear_detections)
+}
+
+/// Transcribe every word in audio using faster-whisper via Python, with
+/// swear words marked
+pub async fn transcribe(audio_path: &Path, config: &Config) -> Result<Vec<WordDetection>> {
     info!("Detecting swear words using faster-whisper model: {}", config.whisper_model.as_str());
 
     // Create temporary Python script for faster-whisper
@@ -70,7 +111,8 @@ pub async fn detect_swear_words(audio_path: &Path, config: &Config) -> Result<Ve
         if let Some(words) = segment.words {
             for word in words {
                 let cleaned_word = clean_word(&word.word);
-                let is_swear = is_swear_word(&cleaned_word, &config.swear_words);
+                let matched = matched_swear_word(&cleaned_word, &config.swear_words);
+                let is_swear = matched.is_some();
                 
                 let detection = WordDetection {
                     word: cleaned_word.clone(),
@@ -78,7 +120,10 @@ pub async fn detect_swear_words(audio_path: &Path, config: &Config) -> Result<Ve
                     end_time: word.end,
                     confidence: word.probability,
                     is_swear,
-                };
+                    text: word.word.trim().to_string(),
+                    ..Default::default()
+                }
+                .classified(matched, config);
                 
                 if is_swear {
                     info!("Detected swear word: '{}' at {:.2}s-{:.2}s (confidence: {:.2})", 
@@ -95,7 +140,8 @@ pub async fn detect_swear_words(audio_path: &Path, config: &Config) -> Result<Ve
             
             for (i, word) in words.enumerate() {
                 let cleaned_word = clean_word(word);
-                let is_swear = is_swear_word(&cleaned_word, &config.swear_words);
+                let matched = matched_swear_word(&cleaned_word, &config.swear_words);
+                let is_swear = matched.is_some();
                 
                 // Estimate word timing based on position in segment
                 let word_start = segment.start + (i as f64 / word_count) * segment_duration;
@@ -107,7 +153,10 @@ pub async fn detect_swear_words(audio_path: &Path, config: &Config) -> Result<Ve
                     end_time: word_end,
                     confidence: 0.8, // Default confidence for segment-based detection
                     is_swear,
-                };
+                    text: word.to_string(),
+                    ..Default::default()
+                }
+                .classified(matched, config);
                 
                 if is_swear {
                     warn!("Detected swear word (estimated timing): '{}' at {:.2}s-{:.2}s", 
@@ -119,12 +168,7 @@ pub async fn detect_swear_words(audio_path: &Path, config: &Config) -> Result<Ve
         }
     }
 
-    let swear_detections: Vec<WordDetection> = detections.into_iter()
-        .filter(|d| d.is_swear)
-        .collect();
-
-    info!("Found {} swear word occurrences", swear_detections.len());
-    Ok(swear_detections)
+    Ok(detections)
 }
 
 /// Create a temporary Python script for faster-whisper
@@ -135,7 +179,7 @@ import json
 import os
 from faster_whisper import WhisperModel
 
-def transcribe_audio(model_size, audio_path):
+def transcribe_audio(model_size, audio_path, language):
     try:
         # Check if audio file exists
         if not os.path.exists(audio_path):
@@ -150,7 +194,7 @@ def transcribe_audio(model_size, audio_path):
         segments, info = model.transcribe(
             audio_path,
             word_timestamps=True,
-            language="en"  # Assuming English, could be auto-detected
+            language=language
         )
         
         # Convert segments to serializable format
@@ -183,14 +227,15 @@ def transcribe_audio(model_size, audio_path):
         return []
 
 if __name__ == "__main__":
-    if len(sys.argv) != 3:
-        print("Usage: python script.py <model_size> <audio_path>", file=sys.stderr)
+    if len(sys.argv) != 4:
+        print("Usage: python script.py <model_size> <audio_path> <language>", file=sys.stderr)
         sys.exit(1)
     
     model_size = sys.argv[1]
     audio_path = sys.argv[2]
+    language = sys.argv[3]
     
-    result = transcribe_audio(model_size, audio_path)
+    result = transcribe_audio(model_size, audio_path, language)
     print(json.dumps(result, indent=2))
 "#;
 
@@ -224,6 +269,7 @@ async fn run_whisper_transcription(
             script_path.path().to_str().context("Invalid script path")?,
             model_size,
             audio_path.to_str().context("Invalid audio path")?,
+            LANGUAGE,
         ])
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -268,7 +314,7 @@ async fn run_whisper_transcription(
 }
 
 /// Clean a word by removing punctuation and converting to lowercase
-fn clean_word(word: &str) -> String {
+pub(crate) fn clean_word(word: &str) -> String {
     // Remove leading/trailing whitespace and punctuation
     let cleaned = word.trim()
         .trim_matches(|c: char| c.is_ascii_punctuation())
@@ -278,40 +324,76 @@ fn clean_word(word: &str) -> String {
 }
 
 /// Check if a word is in the swear words list
+#[cfg(test)]
 fn is_swear_word(word: &str, swear_words: &[String]) -> bool {
+    matched_swear_word(word, swear_words).is_some()
+}
+
+/// Entry of `swear_words` that `word` matches, if any
+pub(crate) fn matched_swear_word<'a>(word: &str, swear_words: &'a [String]) -> Option<&'a str> {
     if word.is_empty() || word.len() < 2 {
-        return false; // Ignore single letters
+        return None; // Ignore single letters
     }
     
     let word_lower = word.to_lowercase();
     
     // Skip common false positives
     if matches!(word_lower.as_str(), "i" | "a" | "he" | "it" | "in" | "is" | "to" | "or" | "as" | "be" | "we" | "on" | "so" | "up" | "an" | "my" | "at" | "go" | "do" | "if" | "no" | "me" | "us" | "oh") {
-        return false;
+        return None;
     }
     
     // Direct match
-    if swear_words.contains(&word_lower) {
-        return true;
+    if let Some(swear) = swear_words.iter().find(|s| **s == word_lower) {
+        return Some(swear);
     }
     
     // Check for partial matches (but only for words >= 4 chars to avoid false positives)
     if word.len() >= 4 {
         for swear in swear_words {
-            if swear.len() >= 4 && (word_lower.contains(swear) || swear.contains(&word_lower)) {
-                return true;
+            if swear.len() >= 4 && (word_lower.contains(swear.as_str()) || swear.contains(&word_lower)) {
+                return Some(swear);
             }
         }
     }
     
     // Check common variations (e.g., "sh*t", "f**k")
-    for swear in swear_words {
-        if is_censored_variation(&word_lower, swear) {
-            return true;
-        }
-    }
-    
-    false
+    swear_words.iter()
+        .find(|swear| is_censored_variation(&word_lower, swear))
+        .map(|swear| swear.as_str())
+}
+
+/// Endings that turn a swear word into one of its forms (`fucking`, `shits`)
+const SWEAR_WORD_SUFFIXES: &[&str] = &["s", "es", "ed", "er", "ers", "ing", "in", "y"];
+
+/// Entry of `swear_words` that a whole word of written text matches: the
+/// word itself, one of its inflected forms, or a starred-out spelling such
+/// as `sh*t`. Unlike `matched_swear_word` it never matches inside a longer
+/// word, so subtitle text like "Hello" or "shell" is left alone.
+pub(crate) fn matched_swear_form<'a>(word: &str, swear_words: &'a [String]) -> Option<&'a str> {
+    let word = word.to_lowercase();
+    swear_words.iter()
+        .find(|swear| {
+            let swear = swear.as_str();
+            word == swear
+                || SWEAR_WORD_SUFFIXES.iter().any(|suffix| {
+                    // Allow a doubled last letter, as in "shitty"
+                    word.strip_suffix(suffix).is_some_and(|stem| {
+                        stem == swear || (stem.len() == swear.len() + 1
+                            && stem.starts_with(swear)
+                            && stem.ends_with(&swear[swear.len() - 1..]))
+                    })
+                })
+                || is_starred_spelling(&word, swear)
+        })
+        .map(|swear| swear.as_str())
+}
+
+/// Whether `word` is `swear` with some letters replaced by censoring marks
+fn is_starred_spelling(word: &str, swear: &str) -> bool {
+    let is_mark = |c: char| matches!(c, '*' | '#' | '@');
+    word.chars().count() == swear.chars().count()
+        && word.chars().any(is_mark)
+        && word.chars().zip(swear.chars()).all(|(w, s)| w == s || is_mark(w))
 }
 
 /// Check if a word is a censored variation of a swear word
@@ -392,6 +474,22 @@ mod tests {
         assert!(!is_swear_word("", &swear_words));
     }
 
+    #[test]
+    fn test_matched_word_is_classified() {
+        let config = Config::default();
+        let matched = matched_swear_word("fucking", &config.swear_words);
+        assert_eq!(matched, Some("fuck"));
+
+        let detection = WordDetection {
+            word: "fucking".to_string(),
+            is_swear: true,
+            ..Default::default()
+        }
+        .classified(matched, &config);
+        assert_eq!(detection.category.as_deref(), Some("profanity"));
+        assert_eq!(detection.severity, Some(Severity::Strong));
+    }
+
     #[test]
     fn test_is_censored_variation() {
         assert!(is_censored_variation("f**k", "fuck"));
@@ -410,6 +508,7 @@ mod tests {
                 end_time: 10.5,
                 confidence: 0.9,
                 is_swear: true,
+                ..Default::default()
             },
             WordDetection {
                 word: "that".to_string(),
@@ -417,6 +516,7 @@ mod tests {
                 end_time: 11.0,
                 confidence: 0.8,
                 is_swear: false,
+                ..Default::default()
             },
             WordDetection {
                 word: "shit".to_string(),
@@ -424,6 +524,7 @@ mod tests {
                 end_time: 11.5,
                 confidence: 0.95,
                 is_swear: true,
+                ..Default::default()
             },
         ];
         